@@ -0,0 +1,493 @@
+use std::fs;
+use std::path::PathBuf;
+
+use chrono::NaiveDate;
+use clap::{Args, ValueEnum};
+use serde::Serialize;
+
+use crate::config::{self, Difficulty, ImageStyle, SupportLevel};
+use crate::error::{Result, VisoLearnError};
+use crate::models::evaluation::{compare_details_chat_fn, extract_key_details, generate_detailed_description};
+use crate::models::image_generation::{self, generate_image, ImageGenerationConfig, ImageGenerator};
+use crate::models::progression::ProgressionPolicy;
+use crate::models::prompt_generation::{generate_prompt_from_options, GenerativeModel};
+use crate::models::vision::{self, VisionModel};
+use crate::utils::bundle::{checklist_from_session, export_bundle, SessionBundle};
+use crate::utils::file_operations::write_session_log;
+use crate::utils::image_store::{GcReport, ImageStore};
+use crate::utils::learner_db::LearnerDatabase;
+use crate::utils::learner_profile::LearnerProfile;
+use crate::utils::report::{write_reports, DateRange, ProgressReport};
+use crate::utils::session::Session;
+
+// One function per subcommand. Each takes the backends it needs, so tests can pass mocks,
+// and returns a value that `cli::run` prints as JSON.
+
+/// The options an image prompt is written from.
+#[derive(Args, Debug, Clone)]
+pub struct PromptArgs {
+    /// e.g. "Very Simple" or "Moderate"
+    #[arg(long, default_value_t)]
+    pub difficulty: Difficulty,
+    #[arg(long, default_value = "3")]
+    pub age: String,
+    /// "Level 1", "Level 2" or "Level 3"
+    #[arg(long, default_value_t)]
+    pub support_level: SupportLevel,
+    #[arg(long, default_value = "")]
+    pub topic: String,
+    /// Replaces the support level's default plan.
+    #[arg(long)]
+    pub treatment_plan: Option<String>,
+    #[arg(long, default_value_t)]
+    pub style: ImageStyle,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PromptOutput {
+    pub prompt: String,
+    pub difficulty: Difficulty,
+    pub support_level: SupportLevel,
+    pub image_style: ImageStyle,
+}
+
+pub async fn prompt(model: &GenerativeModel, args: &PromptArgs) -> Result<PromptOutput> {
+    let prompt = generate_prompt_from_options(
+        model,
+        args.difficulty,
+        &args.age,
+        args.support_level,
+        &args.topic,
+        args.treatment_plan.as_deref(),
+        args.style,
+    )
+    .await?;
+    Ok(PromptOutput {
+        prompt,
+        difficulty: args.difficulty,
+        support_level: args.support_level,
+        image_style: args.style,
+    })
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct ImageArgs {
+    /// Where to save the image; the extension picks the format.
+    #[arg(short, long)]
+    pub output: PathBuf,
+    /// Uses this prompt instead of writing one from the options below.
+    #[arg(long)]
+    pub prompt: Option<String>,
+    #[command(flatten)]
+    pub options: PromptArgs,
+    /// Defaults to `IMAGE_SEED`, or a random seed.
+    #[arg(long)]
+    pub seed: Option<u64>,
+    /// Defaults to the width in `IMAGE_SIZE`, or 1024.
+    #[arg(long)]
+    pub width: Option<u32>,
+    /// Defaults to the height in `IMAGE_SIZE`, or 1024.
+    #[arg(long)]
+    pub height: Option<u32>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ImageOutput {
+    pub path: PathBuf,
+    pub prompt: String,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Generates an image with `config`, overridden by any seed or size in `args`, writing its
+/// prompt first if none was given. `model` is only used in that case.
+pub async fn image(
+    model: Option<&GenerativeModel>,
+    generator: &dyn ImageGenerator,
+    config: &ImageGenerationConfig,
+    args: &ImageArgs,
+) -> Result<ImageOutput> {
+    let prompt = match (&args.prompt, model) {
+        (Some(prompt), _) => prompt.clone(),
+        (None, Some(model)) => prompt(model, &args.options).await?.prompt,
+        (None, None) => return Err(VisoLearnError::Config("no text backend to write the prompt".to_string())),
+    };
+    let config = ImageGenerationConfig {
+        seed: args.seed.or(config.seed),
+        width: args.width.unwrap_or(config.width),
+        height: args.height.unwrap_or(config.height),
+        ..config.clone()
+    };
+    let image = generate_image(generator, &prompt, &config).await?;
+    image.save(&args.output)?;
+    Ok(ImageOutput {
+        path: args.output.clone(),
+        prompt,
+        width: image.width(),
+        height: image.height(),
+    })
+}
+
+/// An existing image to analyze, and what it was made for.
+#[derive(Args, Debug, Clone)]
+pub struct ImageFileArgs {
+    pub image: PathBuf,
+    /// The prompt the image was generated from, if known.
+    #[arg(long, default_value = "")]
+    pub prompt: String,
+    #[arg(long, default_value = "")]
+    pub topic: String,
+    #[arg(long, default_value_t)]
+    pub difficulty: Difficulty,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DescribeOutput {
+    pub image: PathBuf,
+    pub description: String,
+}
+
+pub async fn describe(vision: &dyn VisionModel, args: &ImageFileArgs) -> Result<DescribeOutput> {
+    let bytes = fs::read(&args.image)?;
+    let description = generate_detailed_description(vision, Some(bytes), &args.prompt, args.difficulty, &args.topic).await?;
+    Ok(DescribeOutput {
+        image: args.image.clone(),
+        description,
+    })
+}
+
+#[derive(Debug, Serialize)]
+pub struct DetailsOutput {
+    pub image: PathBuf,
+    pub details: Vec<String>,
+}
+
+pub async fn details(vision: &dyn VisionModel, args: &ImageFileArgs) -> Result<DetailsOutput> {
+    let bytes = fs::read(&args.image)?;
+    let details = extract_key_details(vision, Some(bytes), &args.prompt, &args.topic).await?;
+    Ok(DetailsOutput {
+        image: args.image.clone(),
+        details,
+    })
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct EvaluateArgs {
+    #[command(flatten)]
+    pub image: ImageFileArgs,
+    /// What the learner said they see.
+    #[arg(long)]
+    pub description: String,
+    /// A key detail to look for; repeat for each. Extracted from the image when left out.
+    #[arg(long = "detail")]
+    pub details: Vec<String>,
+    /// A reference description of the image, e.g. from `describe`.
+    #[arg(long)]
+    pub reference: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct EvaluateOutput {
+    pub feedback: String,
+    pub key_details: Vec<String>,
+    /// The key details the model was confident the description mentions.
+    pub matched_details: Vec<String>,
+}
+
+/// Evaluates a description against an image the same way the practice loop does.
+pub async fn evaluate(vision: &dyn VisionModel, args: &EvaluateArgs) -> Result<EvaluateOutput> {
+    let bytes = fs::read(&args.image.image)?;
+    let key_details = if args.details.is_empty() {
+        extract_key_details(vision, Some(bytes.clone()), &args.image.prompt, &args.image.topic).await?
+    } else {
+        args.details.clone()
+    };
+    let images = ImageStore::in_memory();
+    let session = Session {
+        prompt: Some(args.image.prompt.clone()).filter(|prompt| !prompt.is_empty()),
+        image: Some(images.put(bytes)?),
+        image_description: args.reference.clone(),
+        topic_focus: Some(args.image.topic.clone()),
+        difficulty: args.image.difficulty,
+        key_details,
+        ..Session::default()
+    };
+    let evaluation = compare_details_chat_fn(vision, &images, &args.description, &session).await?;
+    Ok(EvaluateOutput {
+        matched_details: evaluation.matched_detail_texts(&session.key_details),
+        feedback: evaluation.feedback,
+        key_details: session.key_details,
+    })
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    /// A `.visolearn` bundle with the images, which the app can import.
+    Bundle,
+    /// The sessions as a JSON session log.
+    Log,
+    /// HTML and PDF progress reports, written into the output directory.
+    Report,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct ExportArgs {
+    /// The learner's id, or their name if no other learner shares it.
+    pub learner: String,
+    #[arg(long, value_enum, default_value_t = ExportFormat::Bundle)]
+    pub format: ExportFormat,
+    #[arg(short, long)]
+    pub output: PathBuf,
+    /// First day to include, as YYYY-MM-DD.
+    #[arg(long)]
+    pub from: Option<NaiveDate>,
+    /// Last day to include, as YYYY-MM-DD.
+    #[arg(long)]
+    pub to: Option<NaiveDate>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ExportOutput {
+    pub learner_id: String,
+    pub format: ExportFormat,
+    pub sessions: usize,
+    pub files: Vec<PathBuf>,
+}
+
+fn find_learner(db: &LearnerDatabase, learner: &str) -> Result<LearnerProfile> {
+    if let Some(profile) = db.get_profile(learner)? {
+        return Ok(profile);
+    }
+    let mut named: Vec<LearnerProfile> = db
+        .list_profiles()?
+        .into_iter()
+        .filter(|profile| profile.name.eq_ignore_ascii_case(learner.trim()))
+        .collect();
+    match named.len() {
+        0 => Err(VisoLearnError::NotFound(format!("learner {}", learner))),
+        1 => Ok(named.remove(0)),
+        _ => Err(VisoLearnError::InvalidValue {
+            kind: "learner",
+            value: learner.to_string(),
+            expected: "a learner id, as several learners have that name".to_string(),
+        }),
+    }
+}
+
+/// Exports a learner's stored sessions from `db`.
+pub fn export(db: &LearnerDatabase, images: &ImageStore, args: &ExportArgs) -> Result<ExportOutput> {
+    let profile = find_learner(db, &args.learner)?;
+    let range = DateRange::new(args.from, args.to);
+    let program = db
+        .load_program(&profile.id, images)?
+        .ok_or_else(|| VisoLearnError::NotFound(format!("learner {}", profile.id)))?;
+    let in_range = |session: &Session| session.prompt.is_some() && range.contains(session.date());
+    let saved: Vec<Session> = program.saved_sessions.into_iter().filter(|s| in_range(s)).collect();
+    let active = program.active_session.filter(|s| in_range(s));
+
+    let (sessions, files) = match args.format {
+        ExportFormat::Bundle => {
+            let checklist = active.as_ref().map(checklist_from_session).unwrap_or_default();
+            let manifest = export_bundle(&args.output, &SessionBundle::new(&saved, active.as_ref(), &checklist), images)?;
+            (manifest.session_count, vec![args.output.clone()])
+        }
+        ExportFormat::Log => {
+            write_session_log(&args.output, &saved, active.as_ref())?;
+            (saved.len() + usize::from(active.is_some()), vec![args.output.clone()])
+        }
+        ExportFormat::Report => {
+            let report = ProgressReport::for_learner(db, images, &profile.id, range)?;
+            let (html, pdf) = write_reports(&args.output, &format!("progress_{}", profile.id), &report, images)?;
+            (report.sessions.len(), vec![html, pdf])
+        }
+    };
+    Ok(ExportOutput {
+        learner_id: profile.id,
+        format: args.format,
+        sessions,
+        files,
+    })
+}
+
+#[derive(Debug, Serialize)]
+pub struct Check {
+    pub name: &'static str,
+    pub ok: bool,
+    pub detail: String,
+}
+
+impl Check {
+    fn new<T>(name: &'static str, result: Result<T>, describe: impl FnOnce(T) -> String) -> Self {
+        match result {
+            Ok(value) => Self {
+                name,
+                ok: true,
+                detail: describe(value),
+            },
+            Err(e) => Self {
+                name,
+                ok: false,
+                detail: e.user_message(),
+            },
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct DoctorOutput {
+    pub ok: bool,
+    pub checks: Vec<Check>,
+}
+
+/// Checks the configuration and local storage without calling any model. With `gc`, also
+/// deletes the stored images that no session in the learner database uses.
+pub fn doctor(gc: bool) -> DoctorOutput {
+    let text = config::text_config();
+    let vision = config::vision_config();
+    let images = config::image_backend_config();
+    let mut checks = vec![
+        Check::new("text backend", GenerativeModel::from_config(&text), |_| text.backend.clone()),
+        Check::new("vision backend", vision::from_config(&vision), |model| model.name().to_string()),
+        Check::new("image backend", image_generation::from_config(&images), |generator| generator.name().to_string()),
+        Check::new("image settings", image_generation::generation_config(&images), |settings| {
+            let model = settings.model_id.as_deref().unwrap_or("backend default");
+            format!("{}, {}x{}, {} steps", model, settings.width, settings.height, settings.num_inference_steps)
+        }),
+        Check::new("progression policy", ProgressionPolicy::from_name(&config::progression_policy()), |_| {
+            config::progression_policy()
+        }),
+        Check::new(
+            "learner database",
+            LearnerDatabase::open(config::learner_db_path()).and_then(|db| db.list_profiles()),
+            |profiles| format!("{} ({} learners)", config::learner_db_path(), profiles.len()),
+        ),
+        Check::new("image store", ImageStore::open(config::image_store_dir()), |_| config::image_store_dir()),
+    ];
+    if gc {
+        checks.push(Check::new("unused images", collect_garbage(), |report| {
+            format!("removed {} ({} bytes)", report.removed, report.bytes_freed)
+        }));
+    }
+    DoctorOutput {
+        ok: checks.iter().all(|check| check.ok),
+        checks,
+    }
+}
+
+fn collect_garbage() -> Result<GcReport> {
+    let db = LearnerDatabase::open(config::learner_db_path())?;
+    ImageStore::open(config::image_store_dir())?.collect_garbage(&db.referenced_images()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+    use serde_json::json;
+
+    use crate::models::image_generation::PlaceholderGenerator;
+    use crate::models::vision::VisionImage;
+    use crate::utils::session_store::new_session_id;
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("visolearn-cli-{}-{}", new_session_id(), name))
+    }
+
+    struct ScriptedVision;
+
+    #[async_trait]
+    impl VisionModel for ScriptedVision {
+        fn name(&self) -> &str {
+            "Scripted"
+        }
+
+        async fn generate(&self, _image: &VisionImage, prompt: &str, _json_output: bool) -> Result<String> {
+            Ok(if prompt.contains("JSON array of strings") {
+                r#"["red ball", "green tree"]"#.to_string()
+            } else {
+                json!({
+                    "feedback": "Yes, there is a red ball!",
+                    "matched_details": [{ "index": 1, "confidence": 0.9 }]
+                })
+                .to_string()
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_image_then_evaluate() {
+        let path = temp_path("ball.png");
+        let args = ImageArgs {
+            output: path.clone(),
+            prompt: Some("a red ball".to_string()),
+            options: PromptArgs {
+                difficulty: Difficulty::default(),
+                age: "3".to_string(),
+                support_level: SupportLevel::default(),
+                topic: String::new(),
+                treatment_plan: None,
+                style: ImageStyle::default(),
+            },
+            seed: Some(7),
+            width: Some(64),
+            height: Some(48),
+        };
+        let generated = image(None, &PlaceholderGenerator, &ImageGenerationConfig::default(), &args).await.unwrap();
+        assert_eq!((generated.width, generated.height), (64, 48));
+
+        let args = EvaluateArgs {
+            image: ImageFileArgs {
+                image: path.clone(),
+                prompt: "a red ball".to_string(),
+                topic: "toys".to_string(),
+                difficulty: Difficulty::Simple,
+            },
+            description: "a red ball".to_string(),
+            details: Vec::new(),
+            reference: None,
+        };
+        let output = serde_json::to_value(evaluate(&ScriptedVision, &args).await.unwrap()).unwrap();
+        assert_eq!(output["key_details"], json!(["red ball", "green tree"]));
+        assert_eq!(output["matched_details"], json!(["red ball"]));
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_export_finds_learner_by_name() {
+        let db = LearnerDatabase::open_in_memory().unwrap();
+        let images = ImageStore::in_memory();
+        let profile = LearnerProfile::new("Sam");
+        db.save_profile(&profile).unwrap();
+        let played = Session {
+            prompt: Some("A cat on a mat".to_string()),
+            key_details: vec!["cat".to_string()],
+            completed: true,
+            ..profile.new_session()
+        };
+        db.save_program(&profile.id, &[played], Some(&profile.new_session())).unwrap();
+
+        let path = temp_path("log.json");
+        let args = ExportArgs {
+            learner: "sam".to_string(),
+            format: ExportFormat::Log,
+            output: path.clone(),
+            from: None,
+            to: None,
+        };
+        let output = export(&db, &images, &args).unwrap();
+        assert_eq!(output.learner_id, profile.id);
+        assert_eq!(output.sessions, 1);
+        let log: serde_json::Value = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(log["saved_sessions"][0]["prompt"], "A cat on a mat");
+        fs::remove_file(&path).unwrap();
+
+        let args = ExportArgs {
+            learner: "Alex".to_string(),
+            ..args
+        };
+        assert!(matches!(export(&db, &images, &args), Err(VisoLearnError::NotFound(_))));
+    }
+}
+
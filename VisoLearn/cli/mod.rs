@@ -0,0 +1,180 @@
+use clap::{Parser, Subcommand};
+use serde::Serialize;
+use serde_json::json;
+
+use crate::config;
+#[cfg(feature = "desktop")]
+use crate::desktop;
+use crate::error::{Result, VisoLearnError};
+use crate::models::image_generation;
+use crate::models::prompt_generation::{GenerativeModel, PROMPT_WRITER_SYSTEM_PROMPT};
+use crate::models::vision;
+use crate::server;
+use crate::utils::image_store::ImageStore;
+use crate::utils::learner_db::LearnerDatabase;
+
+pub mod commands;
+
+use commands::{EvaluateArgs, ExportArgs, ImageArgs, ImageFileArgs, PromptArgs};
+
+// Every one-shot subcommand prints a single JSON value on stdout, or a JSON error object
+// (`{"error": <user message>}`, as the API returns) on stderr with exit code 1. The full error is
+// logged just before it, and `RUST_LOG=off` leaves only the JSON.
+// Progress messages from the models go to stderr, so stdout can always be piped to `jq`.
+
+#[derive(Parser, Debug)]
+#[command(name = "visolearn", version, about = "Educational image description practice for autistic learners")]
+pub struct Cli {
+    /// Runs the API server when left out.
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Opens the desktop app.
+    #[cfg(feature = "desktop")]
+    Desktop {
+        /// Full screen, and locked until the therapist unlocks it.
+        #[arg(long)]
+        kiosk: bool,
+    },
+    #[command(flatten)]
+    Async(AsyncCommand),
+}
+
+/// The subcommands that run on the async runtime; the desktop app starts its own.
+#[derive(Subcommand, Debug)]
+pub enum AsyncCommand {
+    /// Serves the API and the web UI.
+    Serve {
+        /// Defaults to `SERVER_ADDR`.
+        #[arg(long)]
+        addr: Option<String>,
+    },
+    /// Writes an image prompt from the given options.
+    Prompt(PromptArgs),
+    /// Generates an image and saves it.
+    Image(ImageArgs),
+    /// Describes an image file in detail.
+    Describe(ImageFileArgs),
+    /// Lists the key details a learner could find in an image file.
+    Details(ImageFileArgs),
+    /// Evaluates a learner's description of an image file.
+    Evaluate(EvaluateArgs),
+    /// Exports a learner's sessions as a bundle, session log or progress report.
+    Export(ExportArgs),
+    /// Checks the configuration and local storage.
+    Doctor {
+        /// Also deletes stored images no learner's sessions use. Run it while the server and
+        /// desktop app are closed, as their open sessions are not in the learner database yet.
+        #[arg(long)]
+        gc: bool,
+    },
+}
+
+fn text_model() -> Result<GenerativeModel> {
+    Ok(GenerativeModel::from_config(&config::text_config())?.with_system_prompt(PROMPT_WRITER_SYSTEM_PROMPT))
+}
+
+fn print_json<T: Serialize>(value: &T) -> Result<()> {
+    println!("{}", serde_json::to_string_pretty(value)?);
+    Ok(())
+}
+
+// Runs a one-shot subcommand and prints its output.
+async fn run_command(command: AsyncCommand) -> Result<bool> {
+    match command {
+        AsyncCommand::Serve { addr } => {
+            server::serve(&addr.unwrap_or_else(config::server_addr)).await?;
+        }
+        AsyncCommand::Prompt(args) => print_json(&commands::prompt(&text_model()?, &args).await?)?,
+        AsyncCommand::Image(args) => {
+            let model = if args.prompt.is_none() { Some(text_model()?) } else { None };
+            let images = config::image_backend_config();
+            let generator = image_generation::from_config(&images)?;
+            let settings = image_generation::generation_config(&images)?;
+            print_json(&commands::image(model.as_ref(), generator.as_ref(), &settings, &args).await?)?
+        }
+        AsyncCommand::Describe(args) => {
+            let vision = vision::from_config(&config::vision_config())?;
+            print_json(&commands::describe(vision.as_ref(), &args).await?)?
+        }
+        AsyncCommand::Details(args) => {
+            let vision = vision::from_config(&config::vision_config())?;
+            print_json(&commands::details(vision.as_ref(), &args).await?)?
+        }
+        AsyncCommand::Evaluate(args) => {
+            let vision = vision::from_config(&config::vision_config())?;
+            print_json(&commands::evaluate(vision.as_ref(), &args).await?)?
+        }
+        AsyncCommand::Export(args) => {
+            let db = LearnerDatabase::open(config::learner_db_path())?;
+            let images = ImageStore::open(config::image_store_dir())?;
+            print_json(&commands::export(&db, &images, &args)?)?
+        }
+        AsyncCommand::Doctor { gc } => {
+            let report = commands::doctor(gc);
+            print_json(&report)?;
+            return Ok(report.ok);
+        }
+    }
+    Ok(true)
+}
+
+/// Runs the command line and returns the process exit code.
+pub fn run(cli: Cli) -> i32 {
+    let command = cli.command.unwrap_or(Command::Async(AsyncCommand::Serve { addr: None }));
+    let result = match command {
+        #[cfg(feature = "desktop")]
+        Command::Desktop { kiosk } => desktop::launch(kiosk).map(|()| true),
+        Command::Async(command) => tokio::runtime::Runtime::new()
+            .map_err(VisoLearnError::from)
+            .and_then(|runtime| runtime.block_on(run_command(command))),
+    };
+    match result {
+        Ok(true) => 0,
+        Ok(false) => 1,
+        Err(e) => {
+            log::error!("{}", e);
+            eprintln!("{}", json!({ "error": e.user_message() }));
+            1
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+
+    use crate::config::Difficulty;
+
+    #[test]
+    fn test_parses_subcommands_and_labels() {
+        Cli::command().debug_assert();
+
+        let cli = Cli::try_parse_from([
+            "visolearn",
+            "evaluate",
+            "ball.png",
+            "--description",
+            "a red ball",
+            "--detail",
+            "red ball",
+            "--detail",
+            "green tree",
+            "--difficulty",
+            "very simple",
+        ])
+        .unwrap();
+        let Some(Command::Async(AsyncCommand::Evaluate(args))) = cli.command else {
+            panic!("expected evaluate, got {:?}", cli.command);
+        };
+        assert_eq!(args.details, ["red ball", "green tree"]);
+        assert_eq!(args.image.difficulty, Difficulty::VerySimple);
+
+        assert!(Cli::try_parse_from(["visolearn", "prompt", "--support-level", "Level 9"]).is_err());
+        assert!(Cli::try_parse_from(["visolearn"]).unwrap().command.is_none());
+    }
+}
@@ -0,0 +1,517 @@
+use std::sync::Arc;
+
+use dioxus::prelude::*;
+use tokio::sync::broadcast::error::RecvError;
+
+use crate::config::{self, Palette, IMAGE_STYLES, SUPPORT_LEVELS, THEME_PALETTES};
+use crate::error::{Result, VisoLearnError};
+use crate::server::types::SessionStatus;
+use crate::ui::copy::{self, SaveBox, DISPLAY_SWITCHES, SAVE_IMAGES, SAVE_LOG};
+use crate::ui::view::{error_banner_html, page_style, PracticeAction, PracticeView, SettingsForm};
+use crate::utils::image_store::ImageRef;
+use crate::utils::learner_profile::LearnerProfile;
+use crate::utils::session::Session;
+use crate::utils::theme::{Theme, ThemeOptions};
+
+use super::Desktop;
+
+/// Records `error` so the banner shows its user-facing message.
+fn show_error(mut view: Signal<PracticeView>, error: &VisoLearnError) {
+    log::error!("{}", error);
+    view.write().apply(PracticeAction::Failed(error.user_message()));
+}
+
+// Loads the learner's stored program into the engine, starting a session from their profile
+// if none was in progress.
+async fn open_program(desktop: &Desktop, profile: &LearnerProfile) -> Result<Session> {
+    let (active, saved) = match desktop.db.load_program(&profile.id, desktop.state.store.images())? {
+        Some(program) => (
+            program.active_session.unwrap_or_else(|| program.profile.new_session()),
+            program.saved_sessions,
+        ),
+        None => (profile.new_session(), Vec::new()),
+    };
+    let status = desktop.state.resume(active, saved)?;
+    Ok(desktop.state.practice(&status.session_id).await?.active)
+}
+
+// Writes the session back to the learner database after every step, so closing the lid
+// loses at most the step in progress. Returns the history for the panels.
+async fn persist(desktop: &Desktop, learner_id: &str, session_id: &str) -> Result<Vec<Session>> {
+    let practice = desktop.state.practice(session_id).await?;
+    desktop.db.save_program(learner_id, &practice.saved, Some(&practice.active))?;
+    Ok(practice.history())
+}
+
+/// Redraws from a finished operation and saves the learner's program.
+async fn finish(desktop: &Desktop, learner_id: &str, mut view: Signal<PracticeView>, result: Result<SessionStatus>) {
+    let history = match result {
+        Ok(status) => {
+            let session_id = status.session_id.clone();
+            view.write().apply(PracticeAction::Status(status));
+            persist(desktop, learner_id, &session_id).await
+        }
+        Err(e) => Err(e),
+    };
+    match history {
+        Ok(history) => view.write().apply(PracticeAction::History(history)),
+        Err(e) => show_error(view, &e),
+    }
+}
+
+#[component]
+pub fn App() -> Element {
+    let desktop = use_context::<Arc<Desktop>>();
+    let mut learner = use_signal(|| None::<LearnerProfile>);
+    let mut locked = use_signal(|| desktop.kiosk);
+    let mut unlocking = use_signal(|| false);
+    let theme_options = use_signal(ThemeOptions::default);
+    let theme = Theme::from_options(&theme_options.read());
+
+    let mut unlock = move || {
+        super::unlock_window();
+        locked.set(false);
+    };
+    // The therapist's way out of kiosk mode: Ctrl+Shift+Q, then `KIOSK_PIN` (`launch` makes
+    // sure there is one).
+    let on_key = move |e: KeyboardEvent| {
+        let modifiers = e.modifiers();
+        if locked() && modifiers.ctrl() && modifiers.shift() && e.code() == Code::KeyQ {
+            unlocking.set(true);
+        }
+    };
+    let on_unlock = move |unlocked: bool| {
+        unlocking.set(false);
+        if unlocked {
+            unlock();
+        }
+    };
+
+    rsx! {
+        div {
+            tabindex: "0",
+            autofocus: true,
+            onkeydown: on_key,
+            style: "{page_style(&theme)} min-height: 100vh; outline: none;",
+            h1 { {copy::TITLE} }
+            DisplaySettings { options: theme_options }
+            match learner() {
+                None => rsx! {
+                    LearnerPicker { theme: theme.clone(), on_pick: move |profile| learner.set(Some(profile)) }
+                },
+                Some(profile) => rsx! {
+                    Practice {
+                        key: "{profile.id}",
+                        profile,
+                        theme: theme.clone(),
+                        locked: locked(),
+                        on_leave: move |_| learner.set(None),
+                    }
+                },
+            }
+            if unlocking() {
+                KioskUnlock { theme: theme.clone(), on_close: on_unlock }
+            }
+        }
+    }
+}
+
+#[component]
+fn DisplaySettings(options: Signal<ThemeOptions>) -> Element {
+    // Each checkbox updates one switch in `options`.
+    let toggle = move |set: fn(&mut ThemeOptions, bool)| move |e: FormEvent| set(&mut options.write(), e.checked());
+    let current = options();
+
+    rsx! {
+        section { id: "display-settings", style: "display: flex; gap: 1rem; align-items: center; flex-wrap: wrap;",
+            label {
+                {copy::COLOURS_LABEL}
+                " "
+                select {
+                    onchange: move |e: FormEvent| {
+                        if let Ok(palette) = e.value().parse::<Palette>() {
+                            options.write().palette = palette;
+                        }
+                    },
+                    for palette in THEME_PALETTES {
+                        option { value: palette, selected: palette == current.palette.as_str(), "{palette}" }
+                    }
+                }
+            }
+            for switch in DISPLAY_SWITCHES {
+                label {
+                    input { r#type: "checkbox", checked: (switch.get)(&current), onchange: toggle(switch.set) }
+                    " {switch.label}"
+                }
+            }
+        }
+    }
+}
+
+/// The therapist chooses who is practising, or adds a new learner.
+#[component]
+fn LearnerPicker(theme: Theme, on_pick: EventHandler<LearnerProfile>) -> Element {
+    let desktop = use_context::<Arc<Desktop>>();
+    let profiles = use_hook(|| desktop.db.list_profiles().map_err(|e| e.user_message()));
+    let mut name = use_signal(String::new);
+    let mut age = use_signal(|| LearnerProfile::default().age);
+    let mut error_message = use_signal(|| None::<String>);
+
+    let on_add = move |_| {
+        let profile = LearnerProfile {
+            age: age(),
+            ..LearnerProfile::new(&name())
+        };
+        match desktop.db.save_profile(&profile) {
+            Ok(()) => on_pick.call(profile),
+            Err(e) => error_message.set(Some(e.user_message())),
+        }
+    };
+
+    rsx! {
+        section { style: theme.panel_style(),
+            h2 { "Who is practising?" }
+            div { dangerous_inner_html: error_banner_html(error_message().as_deref(), &theme) }
+            match profiles {
+                Ok(profiles) => rsx! {
+                    div { style: "display: flex; gap: 1rem; flex-wrap: wrap;",
+                        for profile in profiles {
+                            button { onclick: move |_| on_pick.call(profile.clone()), "{profile.name}" }
+                        }
+                    }
+                },
+                Err(message) => rsx! {
+                    div { dangerous_inner_html: error_banner_html(Some(&message), &theme) }
+                },
+            }
+            h3 { "New learner" }
+            div { style: "display: flex; gap: 1rem;",
+                input {
+                    r#type: "text",
+                    placeholder: "Name or pseudonym",
+                    value: "{name}",
+                    oninput: move |e: FormEvent| name.set(e.value()),
+                }
+                input {
+                    r#type: "text",
+                    placeholder: copy::AGE_PLACEHOLDER,
+                    value: "{age}",
+                    oninput: move |e: FormEvent| age.set(e.value()),
+                }
+                button { disabled: name.read().trim().is_empty(), onclick: on_add, "Add and start" }
+            }
+        }
+    }
+}
+
+/// The generated image, read from the local image store.
+#[component]
+fn Picture(image: ImageRef) -> Element {
+    let desktop = use_context::<Arc<Desktop>>();
+    match desktop.state.store.images().data_url(&image) {
+        Ok(src) => rsx! {
+            img { src, alt: copy::IMAGE_ALT, style: "max-width: 100%;" }
+        },
+        Err(e) => rsx! {
+            p { "{e.user_message()}" }
+        },
+    }
+}
+
+/// The practice loop for one learner, the same panels as the web UI.
+#[component]
+fn Practice(profile: LearnerProfile, theme: Theme, locked: bool, on_leave: EventHandler<()>) -> Element {
+    let desktop = use_context::<Arc<Desktop>>();
+    let mut view = use_signal(PracticeView::default);
+    let mut settings = use_signal(SettingsForm::default);
+    let mut chat_input = use_signal(String::new);
+    let save_images_result = use_signal(String::new);
+    let save_log_result = use_signal(String::new);
+    let learner_id = profile.id.clone();
+
+    // Opens the learner's program, then follows its events until the screen closes.
+    use_future({
+        let desktop = desktop.clone();
+        let profile = profile.clone();
+        let learner_id = learner_id.clone();
+        move || {
+            let desktop = desktop.clone();
+            let profile = profile.clone();
+            let learner_id = learner_id.clone();
+            async move {
+                let active = match open_program(&desktop, &profile).await {
+                    Ok(active) => active,
+                    Err(e) => return show_error(view, &e),
+                };
+                settings.set(SettingsForm::from_session(&active));
+                let status = desktop.state.status(&active.id).await;
+                finish(&desktop, &learner_id, view, status).await;
+
+                let Ok(mut events) = desktop.state.store.events().subscribe(&active.id) else {
+                    return;
+                };
+                loop {
+                    match events.recv().await {
+                        Ok(event) => view.write().apply(PracticeAction::Event(event)),
+                        Err(RecvError::Lagged(_)) => continue,
+                        Err(RecvError::Closed) => break,
+                    }
+                }
+            }
+        }
+    });
+
+    let on_generate_click = {
+        let desktop = desktop.clone();
+        let learner_id = learner_id.clone();
+        move |_| {
+            let Some(session_id) = view.read().session_id.clone() else {
+                return;
+            };
+            // Validate the selections before anything is sent to the models.
+            let request = match settings.read().to_request() {
+                Ok(request) => request,
+                Err(e) => return show_error(view, &e),
+            };
+            view.write().apply(PracticeAction::Busy("Starting a new picture...".to_string()));
+            let desktop = desktop.clone();
+            let learner_id = learner_id.clone();
+            spawn(async move {
+                let result = desktop.state.generate_image(&session_id, Some(request)).await;
+                finish(&desktop, &learner_id, view, result).await;
+            });
+        }
+    };
+
+    let on_chat_send = {
+        let desktop = desktop.clone();
+        let learner_id = learner_id.clone();
+        move |_| {
+            let Some(session_id) = view.read().session_id.clone().filter(|_| view.read().image.is_some()) else {
+                return show_error(view, &VisoLearnError::NoImage);
+            };
+            let input = chat_input.read().trim().to_string();
+            if input.is_empty() {
+                return;
+            }
+            view.write().apply(PracticeAction::Busy("Checking the description...".to_string()));
+            // Show the child's words straight away; the reply replaces the whole transcript.
+            view.write().apply(PracticeAction::ChildSaid(input.clone()));
+            chat_input.set(String::new());
+
+            let desktop = desktop.clone();
+            let learner_id = learner_id.clone();
+            spawn(async move {
+                let result = desktop.state.describe(&session_id, &input).await;
+                finish(&desktop, &learner_id, view, result).await;
+            });
+        }
+    };
+
+    // The hint is added to the transcript with the rest of the reply.
+    let on_hint_click = {
+        let desktop = desktop.clone();
+        let learner_id = learner_id.clone();
+        move |_| {
+            let Some(session_id) = view.read().session_id.clone().filter(|_| view.read().image.is_some()) else {
+                return show_error(view, &VisoLearnError::NoImage);
+            };
+            view.write().apply(PracticeAction::Busy("Finding a hint...".to_string()));
+            let desktop = desktop.clone();
+            let learner_id = learner_id.clone();
+            spawn(async move {
+                let result = desktop.state.hint(&session_id).await.map(|hinted| hinted.status);
+                finish(&desktop, &learner_id, view, result).await;
+            });
+        }
+    };
+
+    // Each save reports its outcome in the box next to its button.
+    let save = |mut result: Signal<String>, log: bool| {
+        let desktop = desktop.clone();
+        move |_| {
+            let Some(session_id) = view.read().session_id.clone() else {
+                return;
+            };
+            let desktop = desktop.clone();
+            spawn(async move {
+                let saved = if log {
+                    desktop.state.save_log(&session_id).await
+                } else {
+                    desktop.state.save_images(&session_id).await
+                };
+                match saved {
+                    Ok(saved) => result.set(saved.status),
+                    Err(e) => {
+                        result.set(format!("❌ {}", e.user_message()));
+                        show_error(view, &e);
+                    }
+                }
+            });
+        }
+    };
+    let on_save_images = save(save_images_result, false);
+    let on_save_log = save(save_log_result, true);
+
+    let current = view.read();
+    let form = settings.read();
+    let busy = current.is_busy();
+
+    rsx! {
+        div { style: "display: flex; gap: 1rem; align-items: center;",
+            strong { "Learner: " }
+            "{profile.name}"
+            if !locked {
+                button { onclick: move |_| on_leave.call(()), "Change learner" }
+            }
+        }
+        div { dangerous_inner_html: current.error_html(&theme) }
+        // What the app is doing right now, for screen readers too.
+        div { id: "loading-status", role: "status", "aria-live": "polite",
+            if let Some(message) = &current.loading {
+                p { style: "color: {theme.muted_text};", "{message}" }
+            }
+        }
+        div { id: "difficulty-label",
+            strong { "Current Difficulty: " }
+            "{current.difficulty}"
+        }
+        div { style: "display: flex; gap: 2rem;",
+            div { style: "flex: 2;",
+                section {
+                    h2 { {copy::GENERATE_HEADING} }
+                    p { {copy::GENERATE_INTRO} }
+                    div { style: "display: flex; gap: 1rem;",
+                        input {
+                            r#type: "text",
+                            placeholder: copy::AGE_PLACEHOLDER,
+                            value: "{form.age}",
+                            oninput: move |e: FormEvent| settings.write().age = e.value(),
+                        }
+                        select { onchange: move |e: FormEvent| settings.write().support_level = e.value(),
+                            for level in SUPPORT_LEVELS {
+                                option { value: level, selected: level == form.support_level, "{level}" }
+                            }
+                        }
+                    }
+                    input {
+                        r#type: "text",
+                        placeholder: copy::TOPIC_PLACEHOLDER,
+                        value: "{form.topic_focus}",
+                        oninput: move |e: FormEvent| settings.write().topic_focus = e.value(),
+                    }
+                    textarea {
+                        placeholder: copy::PLAN_PLACEHOLDER,
+                        value: "{form.treatment_plan}",
+                        oninput: move |e: FormEvent| settings.write().treatment_plan = e.value(),
+                    }
+                    div { style: "display: flex; gap: 1rem;",
+                        input {
+                            r#type: "number",
+                            min: "1",
+                            value: "{form.attempt_limit}",
+                            oninput: move |e: FormEvent| {
+                                if let Ok(limit) = e.value().parse() {
+                                    settings.write().attempt_limit = limit;
+                                }
+                            },
+                        }
+                        input {
+                            r#type: "range",
+                            min: "10",
+                            max: "100",
+                            step: "5",
+                            value: "{form.details_threshold}",
+                            oninput: move |e: FormEvent| {
+                                if let Ok(threshold) = e.value().parse() {
+                                    settings.write().details_threshold = threshold;
+                                }
+                            },
+                        }
+                    }
+                    select { onchange: move |e: FormEvent| settings.write().image_style = e.value(),
+                        for style in IMAGE_STYLES {
+                            option { value: style, selected: style == form.image_style, "{style}" }
+                        }
+                    }
+                    div {
+                        button { onclick: on_generate_click, disabled: busy, {copy::GENERATE_BUTTON} }
+                    }
+                    div {
+                        if let Some(image) = current.image.clone() {
+                            Picture { image }
+                        }
+                    }
+                }
+                section {
+                    h2 { {copy::PRACTICE_HEADING} }
+                    p { {copy::PRACTICE_INTRO} }
+                    div { dangerous_inner_html: current.transcript_html(&theme) }
+                    div { style: "display: flex; gap: 1rem;",
+                        input {
+                            r#type: "text",
+                            placeholder: copy::DESCRIPTION_PLACEHOLDER,
+                            value: "{chat_input}",
+                            oninput: move |e: FormEvent| chat_input.set(e.value()),
+                        }
+                        button { onclick: on_chat_send, disabled: busy, {copy::SUBMIT_BUTTON} }
+                        button { onclick: on_hint_click, disabled: busy, {copy::HINT_BUTTON} }
+                    }
+                }
+            }
+            div { style: "flex: 1;", dangerous_inner_html: current.details_html(&theme) }
+        }
+        div { dangerous_inner_html: current.tracking_html(&theme) }
+        section { style: "display: flex; gap: 2rem;",
+            SaveButton { text: SAVE_IMAGES, result: save_images_result, onclick: on_save_images }
+            SaveButton { text: SAVE_LOG, result: save_log_result, onclick: on_save_log }
+        }
+    }
+}
+
+/// A save button with the box its outcome is reported in.
+#[component]
+fn SaveButton(text: SaveBox, result: Signal<String>, onclick: EventHandler<MouseEvent>) -> Element {
+    rsx! {
+        div { style: "flex: 1;",
+            h2 { {text.heading} }
+            p { {text.intro} }
+            button { onclick: move |e| onclick.call(e), {text.button} }
+            input { r#type: "text", placeholder: text.placeholder, readonly: true, value: "{result}" }
+        }
+    }
+}
+
+/// Asks for the therapist's PIN before leaving kiosk mode.
+#[component]
+fn KioskUnlock(theme: Theme, on_close: EventHandler<bool>) -> Element {
+    let mut pin = use_signal(String::new);
+    let mut wrong = use_signal(|| false);
+
+    let on_submit = move |_| {
+        if pin() == config::kiosk_pin() {
+            on_close.call(true);
+        } else {
+            wrong.set(true);
+            pin.set(String::new());
+        }
+    };
+
+    rsx! {
+        div { role: "dialog", "aria-modal": "true", style: theme.panel_style(),
+            h2 { "Leave kiosk mode" }
+            if wrong() {
+                p { role: "alert", "That PIN is not right." }
+            }
+            input {
+                r#type: "password",
+                placeholder: "Therapist PIN",
+                autofocus: true,
+                value: "{pin}",
+                oninput: move |e: FormEvent| pin.set(e.value()),
+            }
+            button { onclick: on_submit, "Unlock" }
+            button { onclick: move |_| on_close.call(false), "Cancel" }
+        }
+    }
+}
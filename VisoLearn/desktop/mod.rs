@@ -0,0 +1,85 @@
+use std::sync::Arc;
+
+use dioxus::desktop::tao::window::Fullscreen;
+use dioxus::desktop::{Config, WindowBuilder};
+use dioxus::prelude::LaunchBuilder;
+
+use crate::config;
+use crate::error::{Result, VisoLearnError};
+use crate::models::progression::{ProgressionEngine, ProgressionPolicy};
+use crate::server::AppState;
+use crate::utils::image_store::ImageStore;
+use crate::utils::learner_db::LearnerDatabase;
+use crate::utils::session_store::SessionStore;
+use crate::utils::state_management::Backends;
+
+mod app;
+
+// The desktop build runs the practice loop in-process, through the same `AppState` the HTTP
+// server wraps, so a therapy room needs no server. Images go to the image store and learner
+// programs to the learner database on this machine; with the local model backends the whole
+// app works offline. The screens mirror the web UI's and are drawn from the same
+// `ui::view::PracticeView`.
+
+/// What every screen of the desktop app shares.
+pub struct Desktop {
+    pub state: AppState,
+    pub db: LearnerDatabase,
+    /// Whether the app started in kiosk mode.
+    pub kiosk: bool,
+}
+
+/// Opens the desktop app and blocks until its window closes.
+///
+/// In kiosk mode the window fills the screen, stays on top and cannot be closed, minimised or
+/// given a context menu until the therapist unlocks it with Ctrl+Shift+Q and `KIOSK_PIN`, so
+/// it refuses to start without a PIN. Keys the operating system handles itself (e.g.
+/// switching users) need the machine's own kiosk settings as well.
+pub fn launch(kiosk: bool) -> Result<()> {
+    if kiosk && config::kiosk_pin().trim().is_empty() {
+        return Err(VisoLearnError::Config("KIOSK_PIN must be set to use kiosk mode".to_string()));
+    }
+    let backends = Backends::from_env()?;
+    let engine = ProgressionEngine::new(ProgressionPolicy::from_name(&config::progression_policy())?);
+    let store = SessionStore::with_images(ImageStore::open(config::image_store_dir())?);
+    let desktop = Arc::new(Desktop {
+        state: AppState::new(backends, store, engine),
+        db: LearnerDatabase::open(config::learner_db_path())?,
+        kiosk,
+    });
+
+    LaunchBuilder::desktop()
+        .with_cfg(window_config(kiosk))
+        .with_context(desktop)
+        .launch(app::App);
+    Ok(())
+}
+
+fn window_config(kiosk: bool) -> Config {
+    let window = WindowBuilder::new().with_title("VisoLearn");
+    if !kiosk {
+        return Config::new().with_window(window.with_maximized(true));
+    }
+    let window = window
+        .with_fullscreen(Some(Fullscreen::Borderless(None)))
+        .with_decorations(false)
+        .with_always_on_top(true)
+        .with_closable(false)
+        .with_minimizable(false)
+        .with_resizable(false);
+    Config::new()
+        .with_window(window)
+        .with_menu(None)
+        .with_disable_context_menu(true)
+}
+
+/// Leaves kiosk mode: the window can be closed and switched away from again.
+pub fn unlock_window() {
+    let window = dioxus::desktop::window();
+    window.set_fullscreen(false);
+    window.set_always_on_top(false);
+    window.set_decorations(true);
+    window.set_closable(true);
+    window.set_minimizable(true);
+    window.set_resizable(true);
+}
@@ -1,74 +1,29 @@
-use base64::{engine::general_purpose, Engine as _};
-use image::io::Reader as ImageReader;
-use image::ImageOutputFormat;
-use reqwest::header::{HeaderMap, HeaderValue, CONTENT_TYPE};
-use reqwest::Client;
-use serde::{Deserialize, Serialize};
-use serde_json::json;
-use std::error::Error;
-use std::io::Cursor;
-
-// Define structs to represent the Gemini API request and response structure.
-// You might need to adjust these based on the actual Gemini API documentation.
-
-#[derive(Serialize)]
-struct GeminiPart {
-    inline_data: Option<GeminiInlineData>,
-    text: Option<String>,
-}
-
-#[derive(Serialize)]
-struct GeminiInlineData {
-    mime_type: String,
-    data: String,
-}
-
-#[derive(Serialize)]
-struct GeminiContent {
-    parts: Vec<GeminiPart>,
-}
-
-#[derive(Serialize)]
-struct GeminiRequest {
-    contents: Vec<GeminiContent>,
-}
-
-#[derive(Deserialize, Debug)]
-struct GeminiResponse {
-    candidates: Option<Vec<GeminiCandidate>>,
-}
-
-#[derive(Deserialize, Debug)]
-struct GeminiCandidate {
-    content: GeminiContentResponse,
-}
-
-#[derive(Deserialize, Debug)]
-struct GeminiContentResponse {
-    parts: Vec<GeminiPartResponse>,
-}
-
-#[derive(Deserialize, Debug)]
-struct GeminiPartResponse {
-    text: Option<String>,
-}
+use serde::Deserialize;
+
+#[cfg(not(target_arch = "wasm32"))]
+use crate::config::Difficulty;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::error::{Result, VisoLearnError};
+use crate::models::matching::{best_match, match_score, DEFAULT_MATCH_THRESHOLD};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::models::vision::{OnToken, VisionImage, VisionModel};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::utils::image_store::ImageStore;
+use crate::utils::session::ChecklistItem;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::utils::session::Session;
 
 // --- Function: generate_detailed_description ---
+#[cfg(not(target_arch = "wasm32"))]
 pub async fn generate_detailed_description(
+    vision: &dyn VisionModel,
     image_input: Option<Vec<u8>>, // Using Option<Vec<u8>> to represent optional image input as bytes
     prompt: &str,
-    difficulty: &str,
+    difficulty: Difficulty,
     topic_focus: &str,
-) -> Result<String, String> {
-    if image_input.is_none() {
-        return Err(
-            "Error: No image provided. Please make sure an image is generated or uploaded first."
-                .to_string(),
-        );
-    }
-    let image_bytes = image_input.unwrap();
+) -> Result<String> {
+    let image_bytes = image_input.ok_or(VisoLearnError::NoImage)?;
 
-    let base64_img = general_purpose::STANDARD.encode(&image_bytes);
     let query = format!(
         r#"
             You are an expert educator specializing in teaching users with autism.
@@ -88,75 +43,21 @@ pub async fn generate_detailed_description(
         prompt, topic_focus, difficulty
     );
 
-    let gemini_request = GeminiRequest {
-        contents: vec![GeminiContent {
-            parts: vec![
-                GeminiPart {
-                    inline_data: Some(GeminiInlineData {
-                        mime_type: "image/png".to_string(), // Assuming PNG format for simplicity
-                        data: base64_img,
-                    }),
-                    text: None,
-                },
-                GeminiPart {
-                    inline_data: None,
-                    text: Some(query),
-                },
-            ],
-        }],
-    };
-
-    let api_key = std::env::var("GEMINI_API_KEY").expect("GEMINI_API_KEY must be set"); // Make sure you have environment variable GEMINI_API_KEY
-    let client = Client::new();
-    let gemini_url = format!(
-        "https://generativelanguage.googleapis.com/v1beta/models/gemini-2.0-flash-thinking-exp-01-21:generateContent?key={}",
-        api_key
-    );
-
-
-    let mut headers = HeaderMap::new();
-    headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
-
-    match client
-        .post(&gemini_url)
-        .headers(headers)
-        .json(&gemini_request)
-        .send()
+    vision
+        .generate(&VisionImage::from_bytes(image_bytes), &query, false)
         .await
-    {
-        Ok(response) => {
-            match response.json::<GeminiResponse>().await {
-                Ok(gemini_response) => {
-                    if let Some(candidates) = gemini_response.candidates {
-                        if let Some(candidate) = candidates.get(0) {
-                            if let Some(part_response) = candidate.content.parts.get(0) {
-                                if let Some(text) = &part_response.text {
-                                    return Ok(text.trim().to_string());
-                                }
-                            }
-                        }
-                    }
-                    Err("Error: No text response from Gemini API".to_string())
-                }
-                Err(e) => Err(format!("Error parsing Gemini API response: {}", e)),
-            }
-        }
-        Err(e) => Err(format!("Error calling Gemini API: {}", e)),
-    }
 }
 
 
 // --- Function: extract_key_details ---
+#[cfg(not(target_arch = "wasm32"))]
 pub async fn extract_key_details(
+    vision: &dyn VisionModel,
     image_input: Option<Vec<u8>>, // Using Option<Vec<u8>> to represent optional image input as bytes
     prompt: &str,
     topic_focus: &str,
-) -> Result<Vec<String>, String> {
-    if image_input.is_none() {
-        return Ok(vec!["Error: No image provided".to_string()]); // Return Ok with error string as per Python
-    }
-    let image_bytes = image_input.unwrap();
-    let base64_img = general_purpose::STANDARD.encode(&image_bytes);
+) -> Result<Vec<String>> {
+    let image_bytes = image_input.ok_or(VisoLearnError::NoImage)?;
 
     let query = format!(
         r#"
@@ -177,192 +78,683 @@ pub async fn extract_key_details(
         prompt, topic_focus
     );
 
-    let gemini_request = GeminiRequest {
-        contents: vec![GeminiContent {
-            parts: vec![
-                GeminiPart {
-                    inline_data: Some(GeminiInlineData {
-                        mime_type: "image/png".to_string(), // Assuming PNG format for simplicity
-                        data: base64_img,
-                    }),
-                    text: None,
-                },
-                GeminiPart {
-                    inline_data: None,
-                    text: Some(query),
-                },
-            ],
-        }],
+    let text = vision
+        .generate(&VisionImage::from_bytes(image_bytes), &query, true)
+        .await?;
+    parse_key_details(&text)
+}
+
+/// Reads the key details from the model's reply: a JSON array of strings, or failing that
+/// bullet-point lines. A reply with neither is a `Parse` error, so the caller can ask again.
+#[cfg(not(target_arch = "wasm32"))]
+fn parse_key_details(text: &str) -> Result<Vec<String>> {
+    // Attempt to parse JSON from the text response
+    let text = text.trim();
+    let json_text = text
+        .strip_prefix("```json")
+        .or_else(|| text.strip_prefix("```"))
+        .and_then(|rest| rest.trim_end().strip_suffix("```"))
+        .unwrap_or(text)
+        .trim();
+    if let Ok(details) = serde_json::from_str::<Vec<String>>(json_text) {
+        if details.is_empty() {
+            return Err(VisoLearnError::Parse("key details: the model listed none".to_string()));
+        }
+        return Ok(details);
+    }
+
+    // If JSON parsing fails, attempt to extract bullet points or lines (similar to Python)
+    let mut details = Vec::new();
+    for line in text.lines() {
+        let line = line.trim();
+        if line.starts_with('-') || line.starts_with('*') {
+            details.push(line[1..].trim().to_string());
+        }
+    }
+    if details.is_empty() {
+        return Err(VisoLearnError::Parse("key details: no JSON array or bullet list in the reply".to_string()));
+    }
+    Ok(details.into_iter().take(15).collect()) // Limit to max 15
+}
+
+
+// --- Function: compare_details_chat_fn ---
+
+/// The vision model's verdict on one of the child's descriptions.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Evaluation {
+    pub feedback: String,
+    /// Zero-based indices into the session's `key_details` that the description matched.
+    pub matched_details: Vec<usize>,
+    /// The model's confidence (0.0-1.0) for each key detail; 0.0 for details not matched.
+    pub detail_confidence: Vec<f32>,
+}
+
+impl Evaluation {
+    /// Whether the model matched key detail `index` confidently enough to count it as found.
+    pub fn is_confident(&self, index: usize) -> bool {
+        self.detail_confidence.get(index).is_some_and(|&confidence| confidence >= DEFAULT_MATCH_THRESHOLD)
+    }
+
+    /// The key detail texts referenced by `matched_details` that the model was confident about.
+    pub fn matched_detail_texts(&self, key_details: &[String]) -> Vec<String> {
+        self.matched_details
+            .iter()
+            .filter(|&&i| self.is_confident(i))
+            .filter_map(|&i| key_details.get(i).cloned())
+            .collect()
+    }
+}
+
+/// Why a model reply could not be turned into an [`Evaluation`].
+#[derive(Debug, thiserror::Error)]
+pub enum EvaluationParseError {
+    #[error("the evaluation response was empty")]
+    Empty,
+    #[error("no JSON object found in the evaluation response")]
+    NoJson,
+    #[error("invalid evaluation JSON: {0}")]
+    InvalidJson(#[from] serde_json::Error),
+}
+
+// The JSON shape requested from the model. The alias accepts the older `newly_identified`
+// name some prompts still produce; fields older prompts asked for, such as a suggested
+// difficulty or a score, are ignored, as progression is the `ProgressionEngine`'s call.
+#[derive(Deserialize)]
+struct RawEvaluation {
+    feedback: String,
+    #[serde(default, alias = "newly_identified")]
+    matched_details: Vec<RawDetailMatch>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawDetailMatch {
+    Number(usize),
+    Scored {
+        index: usize,
+        #[serde(default = "full_confidence")]
+        confidence: f32,
+    },
+}
+
+fn full_confidence() -> f32 {
+    1.0
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn numbered_list(items: &[String]) -> String {
+    if items.is_empty() {
+        return "(none)".to_string();
+    }
+    items
+        .iter()
+        .enumerate()
+        .map(|(i, item)| format!("{}. {}", i + 1, item))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Sends the child's description, together with the session's image, reference description
+/// and key details, to the vision model and returns its structured evaluation.
+#[cfg(not(target_arch = "wasm32"))]
+pub async fn compare_details_chat_fn(
+    vision: &dyn VisionModel,
+    images: &ImageStore,
+    user_details: &str,
+    active_session: &Session,
+) -> Result<Evaluation> {
+    compare_details_chat_stream(vision, images, user_details, active_session, &|_| {}).await
+}
+
+/// Like [`compare_details_chat_fn`], but passes the model's raw reply to `on_token` piece by
+/// piece as it is generated.
+#[cfg(not(target_arch = "wasm32"))]
+pub async fn compare_details_chat_stream(
+    vision: &dyn VisionModel,
+    images: &ImageStore,
+    user_details: &str,
+    active_session: &Session,
+    on_token: &OnToken<'_>,
+) -> Result<Evaluation> {
+    let image_ref = active_session.image.as_ref().ok_or(VisoLearnError::NoImage)?;
+    let image = VisionImage {
+        mime_type: image_ref.mime_type.clone(),
+        data: images.load(image_ref)?.to_vec(),
     };
 
-    let api_key = std::env::var("GEMINI_API_KEY").expect("GEMINI_API_KEY must be set");
-    let client = Client::new();
-    let gemini_url = format!(
-        "https://generativelanguage.googleapis.com/v1beta/models/gemini-2.0-pro-exp-02-05:generateContent?key={}",
-        api_key
+    let already_identified: Vec<String> = active_session
+        .key_details
+        .iter()
+        .enumerate()
+        .filter(|(_, detail)| active_session.identified_details.contains(detail))
+        .map(|(i, _)| (i + 1).to_string())
+        .collect();
+
+    let query = format!(
+        r#"
+            You are a supportive teacher helping a person with autism practise describing images.
+            The image was generated at a {} difficulty level and focuses on the topic: "{}".
+            Reference description of the image:
+            {}
+            Numbered key details the person should notice:
+            {}
+            Numbers of the details they have ALREADY identified: {}
+            The person just described the image as:
+            "{}"
+            Evaluate the description:
+            1. Decide which key details that are not already identified are now clearly mentioned, even if worded differently
+            2. Give each match a confidence between 0.0 and 1.0
+            3. Give short, warm, concrete feedback, and encourage them to look for what is still missing without giving it away
+            Respond ONLY with a JSON object of the form:
+            {{"feedback": "...", "matched_details": [{{"index": <key detail number>, "confidence": 0.9}}]}}
+            "#,
+        active_session.difficulty,
+        active_session.topic_focus.as_deref().unwrap_or(""),
+        active_session
+            .image_description
+            .as_deref()
+            .unwrap_or("(no description available)"),
+        numbered_list(&active_session.key_details),
+        if already_identified.is_empty() {
+            "(none)".to_string()
+        } else {
+            already_identified.join(", ")
+        },
+        user_details
     );
 
-    let mut headers = HeaderMap::new();
-    headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+    let text = vision.generate_streaming(&image, &query, true, on_token).await?;
 
+    let mut evaluation = parse_evaluation(&text, active_session.key_details.len())?;
 
-    match client
-        .post(&gemini_url)
-        .headers(headers)
-        .json(&gemini_request)
-        .send()
-        .await
-    {
-        Ok(response) => {
-            match response.json::<GeminiResponse>().await {
-                Ok(gemini_response) => {
-                    if let Some(candidates) = gemini_response.candidates {
-                        if let Some(candidate) = candidates.get(0) {
-                            if let Some(part_response) = candidate.content.parts.get(0) {
-                                if let Some(text) = &part_response.text {
-                                    // Attempt to parse JSON from the text response
-                                    match serde_json::from_str::<Vec<String>>(text.trim()) {
-                                        Ok(details) => Ok(details),
-                                        Err(_) => {
-                                            // If JSON parsing fails, attempt to extract bullet points or lines (similar to Python)
-                                            let lines: Vec<&str> = text.trim().split('\n').collect();
-                                            let mut details = Vec::new();
-                                            for line in lines {
-                                                if line.trim().starts_with('-') || line.trim().starts_with('*') {
-                                                    details.push(line.trim()[1..].trim().to_string());
-                                                }
-                                            }
-                                            if !details.is_empty() {
-                                                Ok(details.into_iter().take(15).collect()) // Limit to max 15
-                                            } else {
-                                                Ok(vec!["object in image".to_string(), "color".to_string(), "shape".to_string(), "background".to_string()]) // Default fallback
-                                            }
-                                        }
-                                    }
-                                } else {
-                                    Err("Error: No text response from Gemini API".to_string())
-                                }
-                            } else {
-                                Err("Error: Unexpected API response format (no parts)".to_string())
-                            }
-                        } else {
-                            Err("Error: Unexpected API response format (no candidates)".to_string())
-                        }
-                    } else {
-                        Err("Error: Unexpected API response format (no candidates)".to_string())
+    // Only keep matches for key details the child has not found yet.
+    evaluation.matched_details.retain(|&i| {
+        !active_session
+            .identified_details
+            .contains(&active_session.key_details[i])
+    });
+
+    Ok(evaluation)
+}
+
+/// Parses the vision model's reply into an [`Evaluation`].
+///
+/// The reply is expected to be a JSON object whose `matched_details` are the 1-based numbers
+/// shown in the prompt. If it is not valid JSON on its own, the first fenced code block or
+/// `{...}` object embedded in the text that parses is used instead. Matches outside
+/// `1..=key_detail_count` are ignored.
+pub fn parse_evaluation(
+    evaluation_text: &str,
+    key_detail_count: usize,
+) -> std::result::Result<Evaluation, EvaluationParseError> {
+    let text = evaluation_text.trim();
+    if text.is_empty() {
+        return Err(EvaluationParseError::Empty);
+    }
+
+    let raw = match serde_json::from_str::<RawEvaluation>(text) {
+        Ok(raw) => raw,
+        Err(strict_err) => {
+            let mut last_err = None;
+            let mut found = None;
+            for candidate in json_candidates(text) {
+                match serde_json::from_str::<RawEvaluation>(candidate) {
+                    Ok(raw) => {
+                        found = Some(raw);
+                        break;
                     }
+                    Err(e) => last_err = Some(e),
                 }
-                Err(e) => Err(format!("Error parsing Gemini API response: {}", e)),
             }
+            match (found, last_err) {
+                (Some(raw), _) => raw,
+                (None, Some(e)) => return Err(EvaluationParseError::InvalidJson(e)),
+                // Text that merely starts like JSON gets the strict error; prose gets NoJson.
+                (None, None) if text.starts_with('{') => {
+                    return Err(EvaluationParseError::InvalidJson(strict_err))
+                }
+                (None, None) => return Err(EvaluationParseError::NoJson),
+            }
+        }
+    };
+
+    let mut matched_details = Vec::new();
+    let mut detail_confidence = vec![0.0; key_detail_count];
+    for detail_match in raw.matched_details {
+        let (number, confidence) = match detail_match {
+            RawDetailMatch::Number(number) => (number, 1.0),
+            RawDetailMatch::Scored { index, confidence } => (index, confidence),
+        };
+        if number == 0 || number > key_detail_count {
+            continue;
+        }
+        let index = number - 1;
+        if !matched_details.contains(&index) {
+            matched_details.push(index);
         }
-        Err(e) => Err(format!("Error calling Gemini API: {}", e)),
+        detail_confidence[index] = confidence.clamp(0.0, 1.0);
     }
+
+    Ok(Evaluation {
+        feedback: raw.feedback.trim().to_string(),
+        matched_details,
+        detail_confidence,
+    })
 }
 
+/// Yields JSON-looking snippets from free text: fenced code blocks first, then every
+/// balanced `{...}` object.
+fn json_candidates(text: &str) -> Vec<&str> {
+    let mut candidates = Vec::new();
+
+    let mut rest = text;
+    while let Some(start) = rest.find("```") {
+        let after_fence = &rest[start + 3..];
+        // Skip an optional language tag such as `json`.
+        let body_start = after_fence.find('\n').map(|i| i + 1).unwrap_or(0);
+        let body = &after_fence[body_start..];
+        match body.find("```") {
+            Some(end) => {
+                candidates.push(body[..end].trim());
+                rest = &body[end + 3..];
+            }
+            None => break,
+        }
+    }
 
-// --- Placeholder functions for the remaining Python functions ---
-// You will need to implement these functions based on the Python code provided,
-// translating the logic to Rust and using appropriate Rust libraries.
+    let bytes = text.as_bytes();
+    let mut start = 0;
+    while let Some(open) = text[start..].find('{').map(|i| i + start) {
+        let mut depth = 0usize;
+        let mut in_string = false;
+        let mut escaped = false;
+        let mut close = None;
+        for (offset, &byte) in bytes[open..].iter().enumerate() {
+            if in_string {
+                match byte {
+                    _ if escaped => escaped = false,
+                    b'\\' => escaped = true,
+                    b'"' => in_string = false,
+                    _ => {}
+                }
+                continue;
+            }
+            match byte {
+                b'"' => in_string = true,
+                b'{' => depth += 1,
+                b'}' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        close = Some(open + offset);
+                        break;
+                    }
+                }
+                _ => {}
+            }
+        }
+        match close {
+            Some(close) => {
+                candidates.push(&text[open..=close]);
+                start = close + 1;
+            }
+            None => break,
+        }
+    }
 
-pub async fn compare_details_chat_fn(
-    _user_details: &str,
-    _active_session: &mut std::collections::HashMap<String, serde_json::Value>, // Using HashMap as a placeholder for active_session
-    _global_image_data_url: Option<String>, // Placeholder for image data URL
-    _global_image_description: Option<String>, // Placeholder for image description
-) -> Result<String, String> {
-    // Implement the logic of compare_details_chat_fn here in Rust
-    // ...
-    Ok("Function compare_details_chat_fn not yet implemented in Rust".to_string()) // Placeholder return
+    candidates
 }
 
-pub fn parse_evaluation(
-    _evaluation_text: &str,
-    _active_session: &mut std::collections::HashMap<String, serde_json::Value>, // Placeholder for active_session
-) -> Result<(String, String, bool, Vec<String>, i32), String> {
-    // Implement the logic of parse_evaluation here in Rust
-    // ...
-    Ok((
-        "Function parse_evaluation not yet implemented".to_string(),
-        "Very Simple".to_string(),
-        false,
-        vec![],
-        0,
-    )) // Placeholder return
-}
+/// The `feedback` text of an evaluation reply that is still being streamed, so it can be shown
+/// as it is written. Returns what has arrived so far, or nothing before the field starts.
+pub fn partial_feedback(reply: &str) -> String {
+    let Some(key) = reply.find("\"feedback\"") else {
+        return String::new();
+    };
+    let after_key = reply[key + "\"feedback\"".len()..].trim_start();
+    let Some(value) = after_key.strip_prefix(':').map(str::trim_start) else {
+        return String::new();
+    };
+    let Some(value) = value.strip_prefix('"') else {
+        return String::new();
+    };
 
+    let mut feedback = String::new();
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => break,
+            '\\' => match chars.next() {
+                Some('n') => feedback.push('\n'),
+                Some('t') => feedback.push('\t'),
+                Some('u') => {
+                    let hex: String = chars.by_ref().take(4).collect();
+                    if hex.len() < 4 {
+                        break;
+                    }
+                    // Surrogate halves are skipped; the finished feedback shows them properly.
+                    if let Some(c) = u32::from_str_radix(&hex, 16).ok().and_then(char::from_u32) {
+                        feedback.push(c);
+                    }
+                }
+                Some(other) => feedback.push(other),
+                None => break,
+            },
+            c => feedback.push(c),
+        }
+    }
+    feedback
+}
 
+/// Marks the checklist items named by `newly_identified` (the model's matches and/or the
+/// child's own phrases) as identified, using the offline fuzzy matcher.
+///
+/// Each phrase identifies at most one item, the best-scoring one not yet identified.
+/// Items already identified stay identified, and every item keeps the best match score
+/// seen so far, so near misses are visible too.
 pub fn update_checklist(
-    _checklist: Vec<serde_json::Value>, // Placeholder for checklist type
-    _newly_identified: Vec<String>,
-    _key_details: Vec<String>,
-) -> Vec<serde_json::Value> {
-    // Implement the logic of update_checklist here in Rust
-    // ...
-    vec![] // Placeholder return
+    checklist: &[ChecklistItem],
+    newly_identified: &[String],
+) -> Vec<ChecklistItem> {
+    let mut updated = checklist.to_vec();
+
+    for phrase in newly_identified {
+        for item in updated.iter_mut() {
+            let score = match_score(phrase, &item.detail);
+            if score > item.match_score {
+                item.match_score = score;
+            }
+        }
+
+        let open_items: Vec<usize> = (0..updated.len())
+            .filter(|&i| !updated[i].identified)
+            .collect();
+        let best = best_match(
+            phrase,
+            open_items.iter().map(|&i| updated[i].detail.as_str()),
+            DEFAULT_MATCH_THRESHOLD,
+        );
+        if let Some((position, _)) = best {
+            updated[open_items[position]].identified = true;
+        }
+    }
+
+    updated
+}
+
+/// Applies the model's matches to the checklist: each matched detail's `match_score` rises to
+/// the model's confidence, and it is marked identified if the model was confident enough.
+pub fn apply_evaluation(checklist: &[ChecklistItem], evaluation: &Evaluation) -> Vec<ChecklistItem> {
+    let mut updated = checklist.to_vec();
+    for &index in &evaluation.matched_details {
+        let Some(item) = updated.iter_mut().find(|item| item.id == index) else {
+            continue;
+        };
+        item.match_score = item.match_score.max(evaluation.detail_confidence[index]);
+        item.identified |= evaluation.is_confident(index);
+    }
+    updated
 }
 
 
-// --- Helper function to load image from path (for testing) ---
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::fs;
+    use async_trait::async_trait;
+    use serde_json::json;
+    use std::sync::Mutex;
+
+    /// An in-process vision model that replays canned replies and records its prompts.
+    struct FakeVision {
+        /// `None` makes every call fail with a quota error.
+        reply: Option<String>,
+        calls: Mutex<Vec<(VisionImage, String, bool)>>,
+    }
+
+    impl FakeVision {
+        fn replying(reply: &str) -> Self {
+            Self {
+                reply: Some(reply.to_string()),
+                calls: Mutex::new(Vec::new()),
+            }
+        }
 
-    async fn load_image_from_path(path: &str) -> Result<Vec<u8>, String> {
-        fs::read(path).map_err(|e| format!("Error reading image file: {}", e))
+        fn failing() -> Self {
+            Self {
+                reply: None,
+                calls: Mutex::new(Vec::new()),
+            }
+        }
+
+        fn last_prompt(&self) -> String {
+            self.calls.lock().unwrap().last().unwrap().1.clone()
+        }
+    }
+
+    #[async_trait]
+    impl VisionModel for FakeVision {
+        fn name(&self) -> &str {
+            "Fake"
+        }
+
+        async fn generate(
+            &self,
+            image: &VisionImage,
+            prompt: &str,
+            json_output: bool,
+        ) -> Result<String> {
+            self.calls
+                .lock()
+                .unwrap()
+                .push((image.clone(), prompt.to_string(), json_output));
+            self.reply.clone().ok_or_else(|| VisoLearnError::QuotaExceeded {
+                service: "Fake".to_string(),
+                detail: "HTTP 429".to_string(),
+            })
+        }
     }
 
     #[tokio::test]
     async fn test_generate_detailed_description() {
-        // Replace "path/to/your/image.png" with the actual path to a test image file
-        let image_path = "path/to/your/image.png"; // <---  Put your image path here for testing
-        let image_bytes_result = load_image_from_path(image_path).await;
-
-        if let Ok(image_bytes) = image_bytes_result {
-            let prompt = "A cat sitting on a mat";
-            let difficulty = "Simple";
-            let topic_focus = "Animals";
-
-            match generate_detailed_description(Some(image_bytes), prompt, difficulty, topic_focus).await {
-                Ok(description) => {
-                    println!("Detailed Description:\n{}", description);
-                    assert!(!description.is_empty()); // Just check if description is not empty for now
-                }
-                Err(err) => {
-                    eprintln!("Error generating detailed description: {}", err);
-                    panic!("Test failed due to error: {}", err);
-                }
-            }
-        } else if let Err(err) = image_bytes_result {
-            eprintln!("Error loading image: {}", err);
-            panic!("Test setup failed: {}", err);
-        }
+        let vision = FakeVision::replying("  A cat sits on a striped mat.  ");
+        let description = generate_detailed_description(
+            &vision,
+            Some(vec![0xFF, 0xD8, 0xFF, 0xE0]),
+            "A cat sitting on a mat",
+            Difficulty::Simple,
+            "Animals",
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(description, "  A cat sits on a striped mat.  ");
+        let calls = vision.calls.lock().unwrap();
+        assert_eq!(calls[0].0.mime_type, "image/jpeg");
+        assert!(calls[0].1.contains("A cat sitting on a mat"));
+        assert!(!calls[0].2);
     }
 
+    #[tokio::test]
+    async fn test_generate_detailed_description_requires_image() {
+        let vision = FakeVision::replying("unused");
+        assert!(matches!(
+            generate_detailed_description(&vision, None, "p", Difficulty::Simple, "Animals").await,
+            Err(VisoLearnError::NoImage)
+        ));
+        assert!(vision.calls.lock().unwrap().is_empty());
+    }
 
     #[tokio::test]
     async fn test_extract_key_details() {
-        // Replace "path/to/your/image.png" with the actual path to a test image file
-        let image_path = "path/to/your/image.png"; // <---  Put your image path here for testing
-        let image_bytes_result = load_image_from_path(image_path).await;
-
-        if let Ok(image_bytes) = image_bytes_result {
-            let prompt = "A dog playing in the park";
-            let topic_focus = "Outdoor activities";
-
-            match extract_key_details(Some(image_bytes), prompt, topic_focus).await {
-                Ok(details) => {
-                    println!("Key Details:\n{:?}", details);
-                    assert!(!details.is_empty()); // Check if details list is not empty
-                }
-                Err(err) => {
-                    eprintln!("Error extracting key details: {}", err);
-                    panic!("Test failed due to error: {}", err);
-                }
-            }
-        } else if let Err(err) = image_bytes_result {
-            eprintln!("Error loading image: {}", err);
-            panic!("Test setup failed: {}", err);
+        let vision = FakeVision::replying("```json\n[\"dog playing fetch\", \"green park bench\"]\n```");
+        let details = extract_key_details(&vision, Some(vec![1, 2, 3]), "A dog playing in the park", "Outdoor activities")
+            .await
+            .unwrap();
+        assert_eq!(details, vec!["dog playing fetch", "green park bench"]);
+        assert!(vision.calls.lock().unwrap()[0].2);
+
+        let vision = FakeVision::replying("Details:\n- red ball\n* blue sky");
+        let details = extract_key_details(&vision, Some(vec![1, 2, 3]), "p", "t").await.unwrap();
+        assert_eq!(details, vec!["red ball", "blue sky"]);
+
+        for reply in ["I can't make out the picture.", "[]"] {
+            let vision = FakeVision::replying(reply);
+            assert!(matches!(
+                extract_key_details(&vision, Some(vec![1, 2, 3]), "p", "t").await,
+                Err(VisoLearnError::Parse(_))
+            ));
         }
     }
-}
\ No newline at end of file
+
+    fn session_with_image(images: &ImageStore) -> Session {
+        let mut session = Session::new();
+        session.image = Some(images.put_data_url("data:image/png;base64,iVBORw0KGgo=").unwrap());
+        session.image_description = Some("A brown dog sits on green grass under a blue sky.".to_string());
+        session.topic_focus = Some("Animals".to_string());
+        session.key_details = vec![
+            "brown dog sitting on the grass".to_string(),
+            "blue sky".to_string(),
+            "red ball".to_string(),
+        ];
+        session.identified_details = vec!["blue sky".to_string()];
+        session
+    }
+
+    #[tokio::test]
+    async fn test_compare_details_chat_fn() {
+        let model_output = json!({
+            "feedback": "Great job spotting the dog! What is it playing with?",
+            "matched_details": [{ "index": 1, "confidence": 0.8 }, { "index": 2 }, { "index": 9 }]
+        })
+        .to_string();
+        let vision = FakeVision::replying(&model_output);
+        let images = ImageStore::in_memory();
+
+        let evaluation = compare_details_chat_fn(&vision, &images, "I see a dog on the grass", &session_with_image(&images))
+            .await
+            .expect("evaluation should succeed");
+
+        // Already-identified and out-of-range details are dropped.
+        assert_eq!(evaluation.matched_details, vec![0]);
+        assert_eq!(evaluation.detail_confidence[0], 0.8);
+
+        let prompt = vision.last_prompt();
+        assert!(prompt.contains("I see a dog on the grass"));
+        assert!(prompt.contains("A brown dog sits on green grass"));
+        assert!(prompt.contains("ALREADY identified: 2"));
+        assert_eq!(vision.calls.lock().unwrap()[0].0.data, vec![0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A]);
+    }
+
+    #[tokio::test]
+    async fn test_compare_details_chat_fn_reports_model_errors() {
+        let vision = FakeVision::failing();
+        let images = ImageStore::in_memory();
+        let err = compare_details_chat_fn(&vision, &images, "a dog", &session_with_image(&images))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, VisoLearnError::QuotaExceeded { .. }), "unexpected error: {}", err);
+    }
+
+    #[tokio::test]
+    async fn test_compare_details_chat_fn_requires_image() {
+        let vision = FakeVision::replying("unused");
+        assert!(matches!(
+            compare_details_chat_fn(&vision, &ImageStore::in_memory(), "a dog", &Session::new()).await,
+            Err(VisoLearnError::NoImage)
+        ));
+
+        // A session whose image was garbage-collected is treated the same way.
+        let images = ImageStore::in_memory();
+        let session = session_with_image(&images);
+        images.collect_unreferenced([]).unwrap();
+        assert!(matches!(
+            compare_details_chat_fn(&vision, &images, "a dog", &session).await,
+            Err(VisoLearnError::NoImage)
+        ));
+    }
+
+    #[test]
+    fn test_parse_evaluation_strict_json() {
+        // Fields older prompts asked for are ignored.
+        let text = r#"{"feedback": " Nice! ", "matched_details": [{"index": 2, "confidence": 1.4}, 3],
+            "suggested_difficulty": "very_simple", "should_advance": true, "score": 87.6}"#;
+        let evaluation = parse_evaluation(text, 3).unwrap();
+        assert_eq!(evaluation.feedback, "Nice!");
+        assert_eq!(evaluation.matched_details, vec![1, 2]);
+        assert_eq!(evaluation.detail_confidence, vec![0.0, 1.0, 1.0]);
+    }
+
+    #[test]
+    fn test_parse_evaluation_from_fenced_block() {
+        let text = "Here is my evaluation:\n```json\n{\"feedback\": \"Good\", \"newly_identified\": [1], \"updated_difficulty\": \"Moderate\"}\n```\nThanks!";
+        let evaluation = parse_evaluation(text, 2).unwrap();
+        assert_eq!(evaluation.matched_details, vec![0]);
+    }
+
+    #[test]
+    fn test_parse_evaluation_from_prose() {
+        let text = r#"Sure! {"feedback": "You found the {red} ball", "matched_details": [], "suggested_difficulty": "Detailed"} Hope that helps."#;
+        let evaluation = parse_evaluation(text, 4).unwrap();
+        assert_eq!(evaluation.feedback, "You found the {red} ball");
+        assert!(evaluation.matched_details.is_empty());
+    }
+
+    #[test]
+    fn test_parse_evaluation_errors() {
+        assert!(matches!(parse_evaluation("   ", 3), Err(EvaluationParseError::Empty)));
+        assert!(matches!(
+            parse_evaluation("The child did well.", 3),
+            Err(EvaluationParseError::NoJson)
+        ));
+        assert!(matches!(
+            parse_evaluation(r#"{"matched_details": [1]}"#, 3),
+            Err(EvaluationParseError::InvalidJson(_))
+        ));
+    }
+
+    #[test]
+    fn test_partial_feedback() {
+        let reply = r#"{"feedback": "Yes, a \"red\" ball!\nWhat else"#;
+        assert_eq!(partial_feedback(reply), "Yes, a \"red\" ball!\nWhat else");
+        assert_eq!(partial_feedback(r#"{"feedback": "Done.", "score": 80}"#), "Done.");
+        assert_eq!(partial_feedback(r#"{"feedback": "caf\u00e9 \u00"#), "café ");
+        assert_eq!(partial_feedback(r#"{"feedba"#), "");
+        assert_eq!(partial_feedback(r#"{"feedback": "#), "");
+    }
+
+    #[test]
+    fn test_update_checklist_fuzzy_and_sticky() {
+        let checklist: Vec<ChecklistItem> = ["brown dog sitting on the grass", "red ball", "blue sky"]
+            .iter()
+            .enumerate()
+            .map(|(id, detail)| ChecklistItem {
+                detail: detail.to_string(),
+                identified: id == 2,
+                id,
+                match_score: if id == 2 { 1.0 } else { 0.0 },
+            })
+            .collect();
+
+        let updated = update_checklist(&checklist, &["a doggy".to_string(), "the grass".to_string()]);
+        assert!(updated[0].identified);
+        assert!(updated[0].match_score >= DEFAULT_MATCH_THRESHOLD);
+        assert!(!updated[1].identified);
+        assert_eq!(updated[1].match_score, 0.0);
+        // Previously identified items stay identified even when nothing mentions them.
+        assert!(updated[2].identified);
+        assert_eq!(updated[2].match_score, 1.0);
+
+        // The model's confidence becomes the match score; unsure matches are not counted.
+        let evaluation = parse_evaluation(
+            r#"{"feedback": "ok", "matched_details": [{"index": 1, "confidence": 0.3}, {"index": 2, "confidence": 0.9}]}"#,
+            3,
+        )
+        .unwrap();
+        let applied = apply_evaluation(&checklist, &evaluation);
+        assert!(!applied[0].identified);
+        assert_eq!(applied[0].match_score, 0.3);
+        assert!(applied[1].identified);
+        assert_eq!(applied[1].match_score, 0.9);
+        assert_eq!(evaluation.matched_detail_texts(&["dog".to_string(), "ball".to_string()]), ["ball"]);
+    }
+}
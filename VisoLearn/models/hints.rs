@@ -0,0 +1,310 @@
+use serde::{Deserialize, Serialize};
+
+use crate::models::matching::{canonical, content_tokens, head_word, normalize};
+use crate::utils::session::{ChecklistItem, Session};
+
+// Graduated hints for key details the learner has not found yet. Each request for the same
+// detail gives a more specific hint: where to look, what kind of thing it is, the first
+// letter of its name, and finally the answer. Hints are worked out offline from the detail
+// text and the session's reference description, so asking for one never costs an API call.
+
+/// How specific a hint is, from least to most.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum HintLevel {
+    Region,
+    Category,
+    FirstLetter,
+    Answer,
+}
+
+impl HintLevel {
+    pub const ALL: [HintLevel; 4] = [
+        HintLevel::Region,
+        HintLevel::Category,
+        HintLevel::FirstLetter,
+        HintLevel::Answer,
+    ];
+
+    /// The next more specific level, or `None` after the answer.
+    pub fn next(&self) -> Option<Self> {
+        Self::ALL.get(*self as usize + 1).copied()
+    }
+
+    /// How much of a detail's credit this hint gives away (0.25 for a region, 1.0 for the answer).
+    pub fn cost(&self) -> f32 {
+        (*self as usize + 1) as f32 / Self::ALL.len() as f32
+    }
+}
+
+/// A hint that was shown to the learner, as recorded in `Session::used_hints`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UsedHint {
+    /// `ChecklistItem::id` of the detail the hint was for; `None` for hints from version 1
+    /// logs, which did not record it.
+    pub detail_id: Option<usize>,
+    pub detail: String,
+    pub level: HintLevel,
+    pub text: String,
+}
+
+/// Messages that ask for a hint instead of describing the image.
+pub fn is_hint_request(message: &str) -> bool {
+    matches!(
+        normalize(message).as_str(),
+        "hint" | "a hint" | "hint please" | "help" | "clue" | "give me a hint" | "can i have a hint"
+            | "i need a hint" | "another hint"
+    )
+}
+
+// Words that say where something is, checked in order so "top left" wins over "top".
+const REGIONS: &[(&str, &str)] = &[
+    ("top left", "the top left of the picture"),
+    ("top right", "the top right of the picture"),
+    ("bottom left", "the bottom left of the picture"),
+    ("bottom right", "the bottom right of the picture"),
+    ("upper left", "the top left of the picture"),
+    ("upper right", "the top right of the picture"),
+    ("lower left", "the bottom left of the picture"),
+    ("lower right", "the bottom right of the picture"),
+    ("background", "the back of the picture, far away"),
+    ("foreground", "the front of the picture, close to you"),
+    ("centre", "the middle of the picture"),
+    ("center", "the middle of the picture"),
+    ("middle", "the middle of the picture"),
+    ("top", "the top of the picture"),
+    ("above", "the top of the picture"),
+    ("bottom", "the bottom of the picture"),
+    ("below", "the bottom of the picture"),
+    ("left", "the left side of the picture"),
+    ("right", "the right side of the picture"),
+];
+
+// Things that are almost always in the same part of a picture.
+const USUAL_REGIONS: &[(&str, &str)] = &[
+    ("sky", "the top of the picture"),
+    ("cloud", "the top of the picture"),
+    ("sun", "the top of the picture"),
+    ("moon", "the top of the picture"),
+    ("ceiling", "the top of the picture"),
+    ("grass", "the bottom of the picture"),
+    ("floor", "the bottom of the picture"),
+    ("ground", "the bottom of the picture"),
+    ("sand", "the bottom of the picture"),
+    ("road", "the bottom of the picture"),
+];
+
+// Categories keyed by canonical token (see `matching::canonical`).
+const CATEGORIES: &[(&str, &[&str])] = &[
+    ("an animal", &["dog", "cat", "bird", "horse", "rabbit", "fish", "cow", "sheep", "duck", "pig", "bear", "mouse", "frog", "butterfly", "bee", "lion", "elephant", "owl", "squirrel"]),
+    ("a person", &["child", "boy", "girl", "man", "woman", "person", "mother", "father", "teacher", "friend", "family", "baby"]),
+    ("something to eat or drink", &["apple", "banana", "orange", "bread", "cake", "cookie", "sandwich", "milk", "juice", "water", "pizza", "carrot", "egg", "food", "fruit", "ice"]),
+    ("part of nature", &["tree", "flower", "grass", "leaf", "sea", "river", "rock", "mountain", "hill", "garden", "bush", "plant", "lake", "beach", "sand", "forest"]),
+    ("something in the sky", &["sky", "sun", "cloud", "moon", "star", "rainbow", "rain", "snow"]),
+    ("a toy", &["ball", "kite", "doll", "teddy", "block", "puzzle", "balloon", "toy", "train"]),
+    ("a vehicle", &["car", "bike", "bus", "truck", "boat", "plane", "tractor"]),
+    ("something to wear", &["hat", "shirt", "jumper", "trousers", "dress", "shoe", "sock", "coat", "scarf", "glove", "boot"]),
+    ("furniture", &["chair", "table", "sofa", "bed", "desk", "shelf", "bench", "lamp"]),
+    ("a building or part of one", &["house", "window", "door", "roof", "wall", "fence", "school", "shop", "building"]),
+    ("a feeling", &["happy", "sad", "angry", "scared", "surprised", "calm", "excited"]),
+];
+
+const COLOURS: &[&str] = &[
+    "red", "orange", "yellow", "green", "blue", "purple", "pink", "brown", "black", "white",
+    "gray", "grey", "gold", "golden", "silver",
+];
+
+fn region_hint(item: &ChecklistItem, description: Option<&str>) -> String {
+    let head = head_word(&item.detail).map(|word| canonical(&word));
+    let find_region = |text: &str| {
+        let text = format!(" {} ", normalize(text));
+        REGIONS
+            .iter()
+            .find(|(words, _)| text.contains(&format!(" {} ", words)))
+            .map(|(_, region)| *region)
+    };
+
+    // The reference description usually says where things are.
+    let described = description.and_then(|description| {
+        let head = head.as_ref()?;
+        description
+            .split(['.', '!', '?', '\n'])
+            .filter(|sentence| content_tokens(sentence).contains(head))
+            .find_map(find_region)
+    });
+    let usual = || {
+        let tokens = content_tokens(&item.detail);
+        USUAL_REGIONS
+            .iter()
+            .find(|(token, _)| tokens.iter().any(|t| t == token))
+            .map(|(_, region)| *region)
+    };
+
+    match described.or_else(|| find_region(&item.detail)).or_else(usual) {
+        Some(region) => format!("Look at {}. There is something there you haven't told me about yet.", region),
+        None => "Look slowly around the whole picture, from the top to the bottom. There is something you haven't told me about yet.".to_string(),
+    }
+}
+
+fn category_hint(item: &ChecklistItem) -> String {
+    let tokens = content_tokens(&item.detail);
+    let head = head_word(&item.detail).map(|word| canonical(&word));
+    let category = head
+        .iter()
+        .chain(tokens.iter())
+        .find_map(|token| {
+            CATEGORIES
+                .iter()
+                .find(|(_, members)| members.contains(&token.as_str()))
+                .map(|(category, _)| *category)
+        });
+    let colour = normalize(&item.detail)
+        .split_whitespace()
+        .find(|word| COLOURS.contains(word))
+        .map(str::to_string);
+
+    match (category, colour) {
+        (Some(category), Some(colour)) => format!("It's {}, and it is {}.", category, colour),
+        (Some(category), None) => format!("It's {}.", category),
+        (None, Some(colour)) => format!("It's something {}.", colour),
+        (None, None) => "It's a thing you can see and name with one word.".to_string(),
+    }
+}
+
+fn first_letter_hint(item: &ChecklistItem) -> String {
+    let word = head_word(&item.detail).unwrap_or_else(|| normalize(&item.detail));
+    match word.chars().next() {
+        Some(letter) => format!(
+            "Its name starts with the letter \"{}\" and has {} letters.",
+            letter.to_uppercase(),
+            word.chars().filter(|c| c.is_alphanumeric()).count()
+        ),
+        None => "Have another look - you're close!".to_string(),
+    }
+}
+
+/// The hint text for `item` at `level`. `description` is the session's reference description.
+pub fn hint_text(item: &ChecklistItem, level: HintLevel, description: Option<&str>) -> String {
+    match level {
+        HintLevel::Region => region_hint(item, description),
+        HintLevel::Category => category_hint(item),
+        HintLevel::FirstLetter => first_letter_hint(item),
+        HintLevel::Answer => format!("It's the {}. Can you find it in the picture?", item.detail.trim()),
+    }
+}
+
+/// The most specific hint already given for the checklist item with `detail_id`.
+pub fn last_hint_level(session: &Session, detail_id: usize) -> Option<HintLevel> {
+    session
+        .used_hints
+        .iter()
+        .filter(|hint| hint.detail_id == Some(detail_id))
+        .map(|hint| hint.level)
+        .max()
+}
+
+/// Gives the next hint for `item`, records it in `session.used_hints` and returns it.
+/// Returns `None` if the item is already identified.
+pub fn next_hint_for(session: &mut Session, item: &ChecklistItem) -> Option<UsedHint> {
+    if item.identified {
+        return None;
+    }
+    let level = match last_hint_level(session, item.id) {
+        None => HintLevel::Region,
+        // After the answer, repeat it rather than running out of help.
+        Some(level) => level.next().unwrap_or(HintLevel::Answer),
+    };
+    let hint = UsedHint {
+        detail_id: Some(item.id),
+        detail: item.detail.clone(),
+        level,
+        text: hint_text(item, level, session.image_description.as_deref()),
+    };
+    session.used_hints.push(hint.clone());
+    Some(hint)
+}
+
+/// Gives the next hint for the session, continuing with the detail from the most recent
+/// hint while it is still unidentified, otherwise starting on the first unidentified detail.
+/// Returns `None` once every detail has been identified.
+pub fn request_hint(session: &mut Session, checklist: &[ChecklistItem]) -> Option<UsedHint> {
+    let in_progress = session.used_hints.last().and_then(|last| {
+        checklist
+            .iter()
+            .find(|item| last.detail_id == Some(item.id) && !item.identified)
+    });
+    let item = in_progress.or_else(|| checklist.iter().find(|item| !item.identified))?;
+    next_hint_for(session, item)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(id: usize, detail: &str) -> ChecklistItem {
+        ChecklistItem {
+            detail: detail.to_string(),
+            identified: false,
+            id,
+            match_score: 0.0,
+        }
+    }
+
+    #[test]
+    fn test_hints_get_more_specific() {
+        let mut session = Session::new();
+        session.image_description = Some(
+            "A sunny park. In the bottom left corner a brown dog sits on the grass. Clouds float above."
+                .to_string(),
+        );
+        let dog = item(0, "brown dog sitting on the grass");
+
+        let texts: Vec<String> = (0..5)
+            .map(|_| next_hint_for(&mut session, &dog).unwrap().text)
+            .collect();
+        assert!(texts[0].contains("bottom left"), "{}", texts[0]);
+        assert_eq!(texts[1], "It's an animal, and it is brown.");
+        assert_eq!(texts[2], "Its name starts with the letter \"D\" and has 3 letters.");
+        assert!(texts[3].contains("brown dog sitting on the grass"));
+        assert_eq!(texts[4], texts[3]);
+
+        let levels: Vec<HintLevel> = session.used_hints.iter().map(|hint| hint.level).collect();
+        assert_eq!(
+            levels,
+            vec![HintLevel::Region, HintLevel::Category, HintLevel::FirstLetter, HintLevel::Answer, HintLevel::Answer]
+        );
+    }
+
+    #[test]
+    fn test_region_falls_back_to_usual_places() {
+        let sky = item(1, "blue sky with fluffy clouds");
+        assert!(hint_text(&sky, HintLevel::Region, None).contains("the top of the picture"));
+        let mystery = item(2, "a wooden spoon");
+        assert!(hint_text(&mystery, HintLevel::Region, None).starts_with("Look slowly around"));
+        assert_eq!(hint_text(&mystery, HintLevel::Category, None), "It's a thing you can see and name with one word.");
+    }
+
+    #[test]
+    fn test_request_hint_follows_the_checklist() {
+        let mut session = Session::new();
+        let mut checklist = vec![item(0, "red ball"), item(1, "green tree")];
+
+        assert_eq!(request_hint(&mut session, &checklist).unwrap().detail_id, Some(0));
+        assert_eq!(request_hint(&mut session, &checklist).unwrap().level, HintLevel::Category);
+
+        // Once the ball is found, hints move on to the tree.
+        checklist[0].identified = true;
+        let hint = request_hint(&mut session, &checklist).unwrap();
+        assert_eq!((hint.detail_id, hint.level), (Some(1), HintLevel::Region));
+
+        checklist[1].identified = true;
+        assert!(request_hint(&mut session, &checklist).is_none());
+        assert_eq!(session.used_hints.len(), 3);
+    }
+
+    #[test]
+    fn test_is_hint_request() {
+        assert!(is_hint_request("Hint!"));
+        assert!(is_hint_request("  give me a hint  "));
+        assert!(!is_hint_request("I see a hint of blue"));
+    }
+}
@@ -1,105 +1,620 @@
-use std::sync::Mutex;
-use once_cell::sync::Lazy;
-use reqwest;
+use std::time::Duration;
+use async_trait::async_trait;
+use base64::{engine::general_purpose, Engine as _};
 use serde_json::json;
-use base64::encode;
-use image::DynamicImage;
-use std::error::Error;
-
-// Global variables similar to Python globals.
-static GLOBAL_IMAGE_DATA_URL: Lazy<Mutex<Option<String>>> = Lazy::new(|| Mutex::new(None));
-static GLOBAL_IMAGE_PROMPT: Lazy<Mutex<Option<String>>> = Lazy::new(|| Mutex::new(None));
-// static GLOBAL_IMAGE_DESCRIPTION: Lazy<Mutex<Option<String>>> = Lazy::new(|| Mutex::new(None));
-
-// Assume you have a config module that provides your Hugging Face token.
-mod config {
-    // Replace with your actual Hugging Face API token.
-    pub const HF_TOKEN: &str = "your_huggingface_api_token";
-}
-
-/// Asynchronously generates an image from the given prompt using the Hugging Face Inference API,
-/// converts the image to a data URL, and updates global variables.
-pub async fn generate_image_fn(
-    selected_prompt: &str,
-    guidance_scale: f32,
-    negative_prompt: &str,
-    num_inference_steps: i32,
-) -> Result<DynamicImage, Box<dyn Error>> {
-    // Update global prompt variable.
-    {
-        let mut prompt_lock = GLOBAL_IMAGE_PROMPT.lock().unwrap();
-        *prompt_lock = Some(selected_prompt.to_string());
-    }
-
-    // Create a new reqwest client.
-    let client = reqwest::Client::new();
-    let url = "https://api-inference.huggingface.co/models/stabilityai/stable-diffusion-3.5-large-turbo";
-
-    // Construct the JSON payload.
-    let payload = json!({
-         "inputs": selected_prompt,
-         "parameters": {
-              "guidance_scale": guidance_scale,
-              "negative_prompt": negative_prompt,
-              "num_inference_steps": num_inference_steps
-         }
-    });
-
-    // Send the POST request with the authorization header.
-    let response = client.post(url)
-        .header("Authorization", format!("Bearer {}", config::HF_TOKEN))
-        .json(&payload)
-        .send()
-        .await?;
-
-    // Check if the request was successful.
-    if !response.status().is_success() {
-         println!("Error generating image: HTTP {}", response.status());
-         return Err(format!("HTTP error: {}", response.status()).into());
-    }
-
-    // Get the image bytes from the response.
-    let bytes = response.bytes().await?;
-    
-    // Load the image from memory using the `image` crate.
-    let img = image::load_from_memory(&bytes)?;
-
-    // Encode the image bytes to base64 and create the data URL.
-    let img_b64 = encode(&bytes);
-    let data_url = format!("data:image/png;base64,{}", img_b64);
-    
-    {
-        let mut data_url_lock = GLOBAL_IMAGE_DATA_URL.lock().unwrap();
-        *data_url_lock = Some(data_url);
-    }
-
-    println!(
-        "Successfully generated image with prompt: {}...",
-        selected_prompt.chars().take(50).collect::<String>()
-    );
+use image::{DynamicImage, Rgb, RgbImage};
+
+use crate::config::{self, ImageBackendConfig};
+use crate::error::{Result, VisoLearnError};
+
+const HF_API_BASE: &str = "https://api-inference.huggingface.co";
+const WEBUI_API_BASE: &str = "http://127.0.0.1:7860";
+const COMFYUI_API_BASE: &str = "http://127.0.0.1:8188";
+const HF_DEFAULT_MODEL: &str = "stabilityai/stable-diffusion-3.5-large-turbo";
+
+/// Parameters for a single text-to-image request.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImageGenerationConfig {
+    /// Model to run: a Hugging Face repo id, or a checkpoint name for local servers. `None`
+    /// uses the backend's default (the model WebUI has loaded, or a Stable Diffusion 3.5
+    /// model on Hugging Face); ComfyUI needs one.
+    pub model_id: Option<String>,
+    pub guidance_scale: f32,
+    pub num_inference_steps: u32,
+    pub negative_prompt: String,
+    /// Fixed seed for reproducible images; `None` lets the backend pick one.
+    pub seed: Option<u64>,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Default for ImageGenerationConfig {
+    fn default() -> Self {
+        Self {
+            model_id: None,
+            guidance_scale: 8.0,
+            num_inference_steps: 50,
+            negative_prompt: "blurry, distorted, low quality, pixelated, poorly drawn, deformed, unfinished, sketchy, cartoon, blur".to_string(),
+            seed: None,
+            width: 1024,
+            height: 1024,
+        }
+    }
+}
 
-    // Return the generated image.
-    Ok(img)
-}
-
-// Example usage with Tokio async runtime.
-#[tokio::main]
-async fn main() {
-    // You can adjust these parameters as needed.
-    let prompt = "A beautiful futuristic cityscape at sunset";
-    let guidance_scale = 8.0;
-    let negative_prompt = "blurry, distorted, low quality, pixelated, poorly drawn, deformed, unfinished, sketchy, cartoon, blur";
-    let num_inference_steps = 50;
-
-    match generate_image_fn(prompt, guidance_scale, negative_prompt, num_inference_steps).await {
-        Ok(img) => {
-            // Here, you might save the image or do further processing.
-            println!("Image generated successfully!");
-            // For example, save to disk:
-            if let Err(e) = img.save("output.png") {
-                eprintln!("Failed to save image: {}", e);
+/// A text-to-image backend.
+#[async_trait]
+pub trait ImageGenerator: Send + Sync {
+    /// Short name for logs and error messages, e.g. "Hugging Face".
+    fn name(&self) -> &str;
+
+    async fn generate(
+        &self,
+        prompt: &str,
+        config: &ImageGenerationConfig,
+    ) -> Result<DynamicImage>;
+}
+
+/// Builds the backend named by `config.backend`: "huggingface", "webui", "comfyui" or "placeholder".
+pub fn from_config(config: &ImageBackendConfig) -> Result<Box<dyn ImageGenerator>> {
+    match config.backend.trim().to_lowercase().as_str() {
+        "huggingface" | "hf" => {
+            let token = config.api_key.clone().unwrap_or_else(config::hf_token);
+            if token.is_empty() {
+                return Err(VisoLearnError::Config("HF_TOKEN is not set".to_string()));
             }
+            Ok(Box::new(HfInferenceGenerator {
+                base_url: config.api_base.clone().unwrap_or_else(|| HF_API_BASE.to_string()),
+                token,
+            }))
+        }
+        "webui" | "automatic1111" => Ok(Box::new(WebUiGenerator {
+            base_url: config.api_base.clone().unwrap_or_else(|| WEBUI_API_BASE.to_string()),
+        })),
+        "comfyui" => Ok(Box::new(ComfyUiGenerator {
+            base_url: config.api_base.clone().unwrap_or_else(|| COMFYUI_API_BASE.to_string()),
+            poll_interval: Duration::from_secs(1),
+            max_polls: 300,
+        })),
+        "placeholder" | "offline" => Ok(Box::new(PlaceholderGenerator)),
+        other => Err(VisoLearnError::Config(format!(
+            "unknown image backend \"{}\" (expected huggingface, webui, comfyui or placeholder)",
+            other
+        ))),
+    }
+}
+
+/// The request parameters from `IMAGE_MODEL`, `IMAGE_SEED`, `IMAGE_STEPS` and `IMAGE_SIZE`,
+/// with the defaults for any that are unset.
+pub fn generation_config(config: &ImageBackendConfig) -> Result<ImageGenerationConfig> {
+    fn parse<T: std::str::FromStr>(name: &str, value: &Option<String>) -> Result<Option<T>> {
+        value
+            .as_deref()
+            .map(|value| {
+                value
+                    .trim()
+                    .parse()
+                    .map_err(|_| VisoLearnError::Config(format!("{} \"{}\" is not a number", name, value)))
+            })
+            .transpose()
+    }
+
+    let defaults = ImageGenerationConfig::default();
+    let (width, height) = match config.size.as_deref() {
+        Some(size) => size
+            .trim()
+            .split_once('x')
+            .and_then(|(width, height)| Some((width.trim().parse().ok()?, height.trim().parse().ok()?)))
+            .ok_or_else(|| VisoLearnError::Config(format!("IMAGE_SIZE \"{}\" is not <width>x<height>", size)))?,
+        None => (defaults.width, defaults.height),
+    };
+    Ok(ImageGenerationConfig {
+        model_id: config.model.clone().filter(|model| !model.trim().is_empty()),
+        seed: parse("IMAGE_SEED", &config.seed)?,
+        num_inference_steps: parse("IMAGE_STEPS", &config.steps)?.unwrap_or(defaults.num_inference_steps),
+        width,
+        height,
+        ..defaults
+    })
+}
+
+fn decode_image(bytes: &[u8], backend: &str) -> Result<DynamicImage> {
+    image::load_from_memory(bytes)
+        .map_err(|e| VisoLearnError::ImageDecode(format!("image from {}: {}", backend, e)))
+}
+
+async fn check_status(response: reqwest::Response, backend: &str) -> Result<reqwest::Response> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+    let body = response.text().await.unwrap_or_default();
+    Err(VisoLearnError::from_status(backend, status.as_u16(), body))
+}
+
+// --- Hugging Face Inference API ---
+
+/// The Hugging Face Inference API (`POST /models/<model_id>`), which replies with image bytes.
+#[derive(Debug, Clone)]
+pub struct HfInferenceGenerator {
+    pub base_url: String,
+    pub token: String,
+}
+
+#[async_trait]
+impl ImageGenerator for HfInferenceGenerator {
+    fn name(&self) -> &str {
+        "Hugging Face"
+    }
+
+    async fn generate(
+        &self,
+        prompt: &str,
+        config: &ImageGenerationConfig,
+    ) -> Result<DynamicImage> {
+        let mut parameters = json!({
+            "guidance_scale": config.guidance_scale,
+            "negative_prompt": config.negative_prompt,
+            "num_inference_steps": config.num_inference_steps,
+            "width": config.width,
+            "height": config.height
+        });
+        if let Some(seed) = config.seed {
+            parameters["seed"] = json!(seed);
+        }
+        let payload = json!({ "inputs": prompt, "parameters": parameters });
+
+        let model = config.model_id.as_deref().unwrap_or(HF_DEFAULT_MODEL);
+        let url = format!("{}/models/{}", self.base_url.trim_end_matches('/'), model);
+        let response = reqwest::Client::new()
+            .post(url)
+            .bearer_auth(&self.token)
+            .json(&payload)
+            .send()
+            .await?;
+        let bytes = check_status(response, self.name())
+            .await?
+            .bytes()
+            .await?;
+
+        decode_image(&bytes, self.name())
+    }
+}
+
+// --- Automatic1111-compatible web UI ---
+
+/// An Automatic1111-compatible `/sdapi/v1/txt2img` endpoint (also served by Forge and SD.Next).
+#[derive(Debug, Clone)]
+pub struct WebUiGenerator {
+    pub base_url: String,
+}
+
+#[async_trait]
+impl ImageGenerator for WebUiGenerator {
+    fn name(&self) -> &str {
+        "Automatic1111"
+    }
+
+    async fn generate(
+        &self,
+        prompt: &str,
+        config: &ImageGenerationConfig,
+    ) -> Result<DynamicImage> {
+        // The API takes a signed seed, with -1 meaning random.
+        let seed = match config.seed {
+            Some(seed) => i64::try_from(seed).map_err(|_| VisoLearnError::InvalidValue {
+                kind: "seed",
+                value: seed.to_string(),
+                expected: format!("a number from 0 to {}", i64::MAX),
+            })?,
+            None => -1,
+        };
+        let mut payload = json!({
+            "prompt": prompt,
+            "negative_prompt": config.negative_prompt,
+            "steps": config.num_inference_steps,
+            "cfg_scale": config.guidance_scale,
+            "width": config.width,
+            "height": config.height,
+            "seed": seed
+        });
+        // Without a model the web UI keeps whichever checkpoint it has loaded.
+        if let Some(model) = &config.model_id {
+            payload["override_settings"] = json!({ "sd_model_checkpoint": model });
         }
-        Err(e) => eprintln!("Error generating image: {}", e),
+
+        let url = format!("{}/sdapi/v1/txt2img", self.base_url.trim_end_matches('/'));
+        let response = reqwest::Client::new()
+            .post(url)
+            .json(&payload)
+            .send()
+            .await?;
+        let body: serde_json::Value = check_status(response, self.name())
+            .await?
+            .json()
+            .await
+            .map_err(|e| VisoLearnError::invalid_response(self.name(), e.to_string()))?;
+
+        let encoded = body["images"][0]
+            .as_str()
+            .ok_or_else(|| VisoLearnError::invalid_response(self.name(), "no images in the reply"))?;
+        // Some builds prefix the payload with a data URL header.
+        let encoded = encoded.split_once(',').map(|(_, b64)| b64).unwrap_or(encoded);
+        let bytes = general_purpose::STANDARD.decode(encoded)?;
+
+        decode_image(&bytes, self.name())
+    }
+}
+
+// --- ComfyUI ---
+
+/// A ComfyUI server, driven through a minimal checkpoint -> KSampler -> SaveImage workflow.
+/// The workflow is queued with `POST /prompt`, `/history/<id>` is polled until it has an
+/// output, and the image is fetched from `/view`.
+#[derive(Debug, Clone)]
+pub struct ComfyUiGenerator {
+    pub base_url: String,
+    pub poll_interval: Duration,
+    pub max_polls: u32,
+}
+
+impl ComfyUiGenerator {
+    fn workflow(prompt: &str, config: &ImageGenerationConfig) -> Result<serde_json::Value> {
+        let checkpoint = config
+            .model_id
+            .as_deref()
+            .ok_or_else(|| VisoLearnError::Config("IMAGE_MODEL must name a ComfyUI checkpoint".to_string()))?;
+        Ok(json!({
+            "1": { "class_type": "CheckpointLoaderSimple", "inputs": { "ckpt_name": checkpoint } },
+            "2": { "class_type": "CLIPTextEncode", "inputs": { "text": prompt, "clip": ["1", 1] } },
+            "3": { "class_type": "CLIPTextEncode", "inputs": { "text": config.negative_prompt, "clip": ["1", 1] } },
+            "4": { "class_type": "EmptyLatentImage", "inputs": { "width": config.width, "height": config.height, "batch_size": 1 } },
+            "5": { "class_type": "KSampler", "inputs": {
+                "model": ["1", 0], "positive": ["2", 0], "negative": ["3", 0], "latent_image": ["4", 0],
+                "seed": config.seed.unwrap_or_else(rand_seed), "steps": config.num_inference_steps,
+                "cfg": config.guidance_scale, "sampler_name": "euler", "scheduler": "normal", "denoise": 1.0
+            } },
+            "6": { "class_type": "VAEDecode", "inputs": { "samples": ["5", 0], "vae": ["1", 2] } },
+            "7": { "class_type": "SaveImage", "inputs": { "images": ["6", 0], "filename_prefix": "visolearn" } }
+        }))
+    }
+}
+
+fn rand_seed() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_nanos() as u64)
+        .unwrap_or(0)
+}
+
+#[async_trait]
+impl ImageGenerator for ComfyUiGenerator {
+    fn name(&self) -> &str {
+        "ComfyUI"
+    }
+
+    async fn generate(
+        &self,
+        prompt: &str,
+        config: &ImageGenerationConfig,
+    ) -> Result<DynamicImage> {
+        let client = reqwest::Client::new();
+        let base = self.base_url.trim_end_matches('/');
+
+        let response = client
+            .post(format!("{}/prompt", base))
+            .json(&json!({ "prompt": Self::workflow(prompt, config)? }))
+            .send()
+            .await?;
+        let queued: serde_json::Value = check_status(response, self.name())
+            .await?
+            .json()
+            .await
+            .map_err(|e| VisoLearnError::invalid_response(self.name(), e.to_string()))?;
+        let prompt_id = queued["prompt_id"]
+            .as_str()
+            .ok_or_else(|| VisoLearnError::invalid_response(self.name(), "no prompt id in the reply"))?
+            .to_string();
+
+        for _ in 0..self.max_polls {
+            let response = client
+                .get(format!("{}/history/{}", base, prompt_id))
+                .send()
+                .await?;
+            let history: serde_json::Value = check_status(response, self.name())
+                .await?
+                .json()
+                .await
+                .map_err(|e| VisoLearnError::invalid_response(self.name(), e.to_string()))?;
+
+            let saved = history[prompt_id.as_str()]["outputs"]
+                .as_object()
+                .and_then(|outputs| outputs.values().find_map(|node| node["images"].get(0).cloned()));
+            if let Some(saved) = saved {
+                let response = client
+                    .get(format!("{}/view", base))
+                    .query(&[
+                        ("filename", saved["filename"].as_str().unwrap_or_default()),
+                        ("subfolder", saved["subfolder"].as_str().unwrap_or_default()),
+                        ("type", saved["type"].as_str().unwrap_or("output")),
+                    ])
+                    .send()
+                    .await?;
+                let bytes = check_status(response, self.name())
+                    .await?
+                    .bytes()
+                    .await?;
+                return decode_image(&bytes, self.name());
+            }
+
+            tokio::time::sleep(self.poll_interval).await;
+        }
+
+        Err(VisoLearnError::invalid_response(
+            self.name(),
+            format!("no output for prompt {} after {} polls", prompt_id, self.max_polls),
+        ))
+    }
+}
+
+// --- Offline placeholder ---
+
+/// Draws a deterministic abstract image from the prompt and seed, without any network access.
+/// Useful for demos, tests and running the practice loop offline.
+#[derive(Debug, Clone, Default)]
+pub struct PlaceholderGenerator;
+
+// FNV-1a, so the same prompt gives the same image on every platform and Rust version.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+#[async_trait]
+impl ImageGenerator for PlaceholderGenerator {
+    fn name(&self) -> &str {
+        "Placeholder"
+    }
+
+    async fn generate(
+        &self,
+        prompt: &str,
+        config: &ImageGenerationConfig,
+    ) -> Result<DynamicImage> {
+        let mut hash = fnv1a(prompt.as_bytes()) ^ config.seed.unwrap_or(0);
+        let mut next = move || {
+            hash = hash.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            (hash >> 33) as u32
+        };
+
+        let (width, height) = (config.width.max(1), config.height.max(1));
+        let top = Rgb([next() as u8, next() as u8, next() as u8]);
+        let bottom = Rgb([next() as u8, next() as u8, next() as u8]);
+        let mut img = RgbImage::from_fn(width, height, |_, y| {
+            let t = y as f32 / height as f32;
+            let mix = |a: u8, b: u8| (a as f32 * (1.0 - t) + b as f32 * t) as u8;
+            Rgb([mix(top[0], bottom[0]), mix(top[1], bottom[1]), mix(top[2], bottom[2])])
+        });
+
+        // A few solid circles give the evaluator something to describe.
+        for _ in 0..(3 + next() % 4) {
+            let (cx, cy) = ((next() % width) as i64, (next() % height) as i64);
+            let radius = (width.min(height) / 16 + next() % (width.min(height) / 6 + 1)) as i64;
+            let colour = Rgb([next() as u8, next() as u8, next() as u8]);
+            for y in (cy - radius).max(0)..(cy + radius).min(height as i64) {
+                for x in (cx - radius).max(0)..(cx + radius).min(width as i64) {
+                    if (x - cx).pow(2) + (y - cy).pow(2) <= radius.pow(2) {
+                        img.put_pixel(x as u32, y as u32, colour);
+                    }
+                }
+            }
+        }
+
+        Ok(DynamicImage::ImageRgb8(img))
+    }
+}
+
+/// Generates an image with the given backend, logging the outcome.
+pub async fn generate_image(
+    generator: &dyn ImageGenerator,
+    prompt: &str,
+    config: &ImageGenerationConfig,
+) -> Result<DynamicImage> {
+    let image = generator.generate(prompt, config).await.map_err(|e| {
+        log::error!("Error generating image with {}: {}", generator.name(), e);
+        e
+    })?;
+    log::info!(
+        "Successfully generated image with {} for prompt: {}...",
+        generator.name(),
+        prompt.chars().take(50).collect::<String>()
+    );
+    Ok(image)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::GenericImageView;
+    use wiremock::matchers::{body_partial_json, header, method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn png_bytes(width: u32, height: u32) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        DynamicImage::new_rgb8(width, height)
+            .write_to(&mut std::io::Cursor::new(&mut bytes), image::ImageOutputFormat::Png)
+            .unwrap();
+        bytes
+    }
+
+    fn small_config() -> ImageGenerationConfig {
+        ImageGenerationConfig {
+            model_id: Some("test/model".to_string()),
+            seed: Some(42),
+            width: 64,
+            height: 48,
+            ..ImageGenerationConfig::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_generation_config_from_settings() {
+        let settings = ImageBackendConfig {
+            backend: "webui".to_string(),
+            api_base: None,
+            api_key: None,
+            model: Some("dreamshaper_8.safetensors".to_string()),
+            seed: Some("7".to_string()),
+            steps: Some("20".to_string()),
+            size: Some("768x512".to_string()),
+        };
+        let config = generation_config(&settings).unwrap();
+        assert_eq!(config.model_id.as_deref(), Some("dreamshaper_8.safetensors"));
+        assert_eq!((config.seed, config.num_inference_steps, config.width, config.height), (Some(7), 20, 768, 512));
+
+        let unset = ImageBackendConfig { model: None, seed: None, steps: None, size: None, ..settings.clone() };
+        assert_eq!(generation_config(&unset).unwrap(), ImageGenerationConfig::default());
+        let bad = ImageBackendConfig { size: Some("large".to_string()), ..settings };
+        assert!(matches!(generation_config(&bad), Err(VisoLearnError::Config(_))));
+
+        // Without a model, the web UI keeps its loaded checkpoint and ComfyUI refuses to guess.
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/sdapi/v1/txt2img"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "images": [general_purpose::STANDARD.encode(png_bytes(8, 8))]
+            })))
+            .mount(&server)
+            .await;
+        let default_model = ImageGenerationConfig { model_id: None, ..small_config() };
+        WebUiGenerator { base_url: server.uri() }.generate("a red ball", &default_model).await.unwrap();
+        let sent: serde_json::Value = server.received_requests().await.unwrap()[0].body_json().unwrap();
+        assert!(sent.get("override_settings").is_none(), "{}", sent);
+        let comfyui = ComfyUiGenerator {
+            base_url: server.uri(),
+            poll_interval: Duration::from_millis(1),
+            max_polls: 1,
+        };
+        assert!(matches!(
+            comfyui.generate("a red ball", &default_model).await,
+            Err(VisoLearnError::Config(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_placeholder_is_deterministic() {
+        let config = small_config();
+        let a = PlaceholderGenerator.generate("a red ball", &config).await.unwrap();
+        let b = PlaceholderGenerator.generate("a red ball", &config).await.unwrap();
+        let c = PlaceholderGenerator.generate("a blue kite", &config).await.unwrap();
+        assert_eq!(a.dimensions(), (64, 48));
+        assert_eq!(a.as_bytes(), b.as_bytes());
+        assert_ne!(a.as_bytes(), c.as_bytes());
+    }
+
+    #[tokio::test]
+    async fn test_hf_inference_generator() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/models/test/model"))
+            .and(header("authorization", "Bearer hf_test"))
+            .and(body_partial_json(json!({
+                "inputs": "a red ball",
+                "parameters": { "seed": 42, "width": 64, "height": 48 }
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_bytes(png_bytes(64, 48)))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let generator = HfInferenceGenerator {
+            base_url: server.uri(),
+            token: "hf_test".to_string(),
+        };
+        let img = generator.generate("a red ball", &small_config()).await.unwrap();
+        assert_eq!(img.dimensions(), (64, 48));
+    }
+
+    #[tokio::test]
+    async fn test_webui_generator() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/sdapi/v1/txt2img"))
+            .and(body_partial_json(json!({ "prompt": "a red ball", "seed": 42, "cfg_scale": 8.0 })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "images": [general_purpose::STANDARD.encode(png_bytes(8, 8))]
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let generator = WebUiGenerator { base_url: server.uri() };
+        let img = generator.generate("a red ball", &small_config()).await.unwrap();
+        assert_eq!(img.dimensions(), (8, 8));
+
+        // A seed past i64::MAX is refused before anything is sent.
+        let config = ImageGenerationConfig {
+            seed: Some(u64::MAX),
+            ..small_config()
+        };
+        assert!(matches!(
+            generator.generate("a red ball", &config).await,
+            Err(VisoLearnError::InvalidValue { kind: "seed", .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_comfyui_generator() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/prompt"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "prompt_id": "abc" })))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/history/abc"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "abc": { "outputs": { "7": { "images": [
+                    { "filename": "visolearn_0001.png", "subfolder": "", "type": "output" }
+                ] } } }
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/view"))
+            .and(query_param("filename", "visolearn_0001.png"))
+            .respond_with(ResponseTemplate::new(200).set_body_bytes(png_bytes(16, 16)))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let generator = ComfyUiGenerator {
+            base_url: server.uri(),
+            poll_interval: Duration::from_millis(1),
+            max_polls: 3,
+        };
+        let img = generator.generate("a red ball", &small_config()).await.unwrap();
+        assert_eq!(img.dimensions(), (16, 16));
+    }
+
+    #[tokio::test]
+    async fn test_http_errors_are_reported() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(503).set_body_string("model loading"))
+            .mount(&server)
+            .await;
+
+        let generator = HfInferenceGenerator {
+            base_url: server.uri(),
+            token: "hf_test".to_string(),
+        };
+        let err = generator.generate("a red ball", &small_config()).await.unwrap_err();
+        assert!(
+            matches!(&err, VisoLearnError::Api { status: 503, body, .. } if body == "model loading"),
+            "unexpected error: {}",
+            err
+        );
     }
 }
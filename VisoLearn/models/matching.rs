@@ -0,0 +1,391 @@
+use std::collections::{HashMap, HashSet};
+
+use once_cell::sync::Lazy;
+
+// Offline matching of a child's free-text phrases against key details.
+//
+// Both sides are normalised (lowercase, punctuation stripped), stop words are dropped,
+// the remaining words are stemmed and mapped to a canonical synonym, and the resulting
+// token sets are compared. A phrase scores well against a detail when it names the
+// detail's head noun ("a dog" vs "brown dog sitting on the grass") or covers most of
+// its words. A phrase that denies something ("there is no dog") matches nothing.
+
+/// Score at or above which a phrase counts as identifying a detail.
+pub const DEFAULT_MATCH_THRESHOLD: f32 = 0.5;
+
+// Weight of the head noun in the score; the detail's other words share the rest.
+const HEAD_WEIGHT: f32 = 0.6;
+
+static STOP_WORDS: Lazy<HashSet<&'static str>> = Lazy::new(|| {
+    [
+        "a", "an", "the", "is", "are", "was", "were", "be", "there", "here", "i", "we", "you",
+        "see", "saw", "can", "it", "its", "this", "that", "these", "those", "some", "and", "or",
+        "of", "to", "at", "by", "for", "has", "have", "he", "she", "they", "his", "her", "their",
+        "looks", "look", "like", "very", "really", "also", "too", "just", "maybe", "think",
+        "there's", "it's", "i'm", "um", "uh", "so",
+    ]
+    .into_iter()
+    .collect()
+});
+
+// Words that end the leading noun phrase of a detail ("dog" in "brown dog on the grass").
+static PHRASE_BREAKS: Lazy<HashSet<&'static str>> = Lazy::new(|| {
+    [
+        "with", "on", "in", "under", "over", "near", "next", "behind", "beside", "between",
+        "above", "below", "holding", "wearing", "from", "into", "onto", "around", "against",
+        "inside", "outside", "across", "along", "by", "at",
+    ]
+    .into_iter()
+    .collect()
+});
+
+// Words that turn a phrase into a denial. Phrases are split at "and", "with" and punctuation
+// first, so "no dog and a ball" still finds the ball.
+static NEGATIONS: Lazy<HashSet<&'static str>> = Lazy::new(|| {
+    [
+        "no", "not", "never", "none", "nothing", "nobody", "nowhere", "cannot",
+        "isn't", "aren't", "wasn't", "weren't", "don't", "doesn't", "didn't", "can't", "couldn't",
+        "won't", "haven't", "hasn't", "isnt", "arent", "dont", "doesnt", "cant",
+    ]
+    .into_iter()
+    .collect()
+});
+
+// Modifiers that never identify a detail on their own.
+static MODIFIERS: Lazy<HashSet<&'static str>> = Lazy::new(|| {
+    [
+        "red", "orange", "yellow", "green", "blue", "purple", "pink", "brown", "black", "white",
+        "gray", "grey", "gold", "golden", "silver", "dark", "light", "bright", "pale", "big",
+        "small", "tall", "short", "long", "round", "square", "happy", "sad", "angry", "scared",
+        "calm", "one", "two", "three", "four", "five", "many", "few", "left", "right", "top",
+        "bottom", "front", "back", "middle", "old", "young", "new", "soft", "shiny", "fluffy",
+    ]
+    .into_iter()
+    .collect()
+});
+
+// Each group maps to its first entry. Entries are stemmed before lookup.
+const SYNONYM_GROUPS: &[&[&str]] = &[
+    &["dog", "puppy", "pup", "doggy", "doggie", "hound"],
+    &["cat", "kitten", "kitty"],
+    &["bird", "birdie", "sparrow", "robin"],
+    &["horse", "pony"],
+    &["rabbit", "bunny"],
+    &["child", "kid", "toddler", "baby"],
+    &["boy", "lad"],
+    &["girl", "lass"],
+    &["man", "guy", "gentleman", "men"],
+    &["woman", "lady", "women"],
+    &["person", "people", "someone", "human"],
+    &["mother", "mom", "mum", "mommy", "mummy", "mama"],
+    &["father", "dad", "daddy", "papa"],
+    &["teacher", "instructor"],
+    &["happy", "smile", "smiling", "glad", "joyful", "cheerful", "laugh", "grin"],
+    &["sad", "unhappy", "cry", "crying", "tear", "upset"],
+    &["angry", "mad", "cross", "furious"],
+    &["scared", "afraid", "frightened", "fear"],
+    &["big", "large", "huge", "giant"],
+    &["small", "little", "tiny", "mini"],
+    &["gray", "grey"],
+    &["car", "automobile", "auto"],
+    &["bike", "bicycle"],
+    &["house", "home", "cottage"],
+    &["grass", "lawn", "meadow", "field"],
+    &["sea", "ocean"],
+    &["rock", "stone", "pebble"],
+    &["sofa", "couch"],
+    &["hat", "cap"],
+    &["flower", "blossom", "bloom"],
+    &["sun", "sunshine", "sunny"],
+    &["cloud", "cloudy"],
+    &["road", "street"],
+    &["shirt", "tshirt", "blouse"],
+    &["jumper", "sweater"],
+    &["trousers", "pants", "jeans"],
+    &["picture", "photo", "painting"],
+    &["cup", "mug"],
+    &["one", "1"],
+    &["two", "2", "pair"],
+    &["three", "3"],
+    &["four", "4"],
+    &["five", "5"],
+];
+
+static SYNONYMS: Lazy<HashMap<String, String>> = Lazy::new(|| {
+    let mut map = HashMap::new();
+    for group in SYNONYM_GROUPS {
+        let canonical = stem(group[0]);
+        for word in group.iter() {
+            map.insert(stem(word), canonical.clone());
+        }
+    }
+    map
+});
+
+/// Lowercases text and replaces everything but letters, digits and apostrophes with spaces.
+pub fn normalize(text: &str) -> String {
+    text.to_lowercase()
+        .chars()
+        .map(|c| match c {
+            '\u{2019}' => '\'',
+            c if c.is_alphanumeric() || c == '\'' => c,
+            _ => ' ',
+        })
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// A light suffix-stripping stemmer: "puppies" -> "puppy", "sitting" -> "sit", "boxes" -> "box".
+pub fn stem(word: &str) -> String {
+    let word = word.to_lowercase();
+    let len = word.chars().count();
+    if len <= 3 || !word.is_ascii() {
+        return word;
+    }
+
+    let undouble = |stem: &str| -> String {
+        let bytes = stem.as_bytes();
+        let n = bytes.len();
+        if n >= 2 && bytes[n - 1] == bytes[n - 2] && !b"aeiouls".contains(&bytes[n - 1]) {
+            stem[..n - 1].to_string()
+        } else {
+            stem.to_string()
+        }
+    };
+
+    if let Some(base) = word.strip_suffix("ies") {
+        if base.len() >= 2 {
+            return format!("{}y", base);
+        }
+    }
+    if let Some(base) = word.strip_suffix("ing") {
+        if base.len() >= 3 {
+            return undouble(base);
+        }
+    }
+    if let Some(base) = word.strip_suffix("ed") {
+        if base.len() >= 3 {
+            return undouble(base);
+        }
+    }
+    if let Some(base) = word.strip_suffix("es") {
+        if ["s", "x", "z", "ch", "sh"].iter().any(|s| base.ends_with(s)) {
+            return base.to_string();
+        }
+    }
+    if let Some(base) = word.strip_suffix('s') {
+        if !base.ends_with('s') && !base.ends_with('u') && !base.ends_with('i') {
+            return base.to_string();
+        }
+    }
+    word
+}
+
+/// Stems a word and maps it to its canonical synonym.
+pub fn canonical(word: &str) -> String {
+    let stemmed = stem(word);
+    SYNONYMS.get(&stemmed).cloned().unwrap_or(stemmed)
+}
+
+fn is_modifier(word: &str) -> bool {
+    MODIFIERS.contains(word) || MODIFIERS.contains(canonical(word).as_str())
+}
+
+/// The canonical content tokens of a phrase, in order and without duplicates.
+pub fn content_tokens(text: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    for word in normalize(text).split_whitespace() {
+        if STOP_WORDS.contains(word) || PHRASE_BREAKS.contains(word) {
+            continue;
+        }
+        let token = canonical(word);
+        if !tokens.contains(&token) {
+            tokens.push(token);
+        }
+    }
+    tokens
+}
+
+/// The head noun of a detail as written, e.g. "dog" in "brown dog sitting on the grass".
+pub fn head_word(detail: &str) -> Option<String> {
+    let normalized = normalize(detail);
+    let mut head = None;
+    for word in normalized.split_whitespace() {
+        if PHRASE_BREAKS.contains(word) {
+            if head.is_some() {
+                break;
+            }
+            continue;
+        }
+        if STOP_WORDS.contains(word) {
+            continue;
+        }
+        // An -ing word after a noun ("dog sitting") starts a verb phrase.
+        if head.is_some() && word.ends_with("ing") && word.len() > 5 {
+            break;
+        }
+        if !is_modifier(word) && !word.ends_with("ing") {
+            head = Some(word.to_string());
+        }
+    }
+    head
+}
+
+/// The canonical head noun of a detail: the last content word of its leading noun phrase.
+fn head_token(detail: &str) -> Option<String> {
+    head_word(detail)
+        .map(|word| canonical(&word))
+        .or_else(|| content_tokens(detail).into_iter().last())
+}
+
+/// Whether a phrase says something is not there, e.g. "there is no dog" or "I don't see a cat".
+pub fn is_negated(phrase: &str) -> bool {
+    normalize(phrase).split_whitespace().any(|word| NEGATIONS.contains(word))
+}
+
+/// Scores how well a child's phrase identifies a key detail, from 0.0 to 1.0. A negated
+/// phrase scores 0.0.
+pub fn match_score(phrase: &str, detail: &str) -> f32 {
+    if is_negated(phrase) {
+        return 0.0;
+    }
+    let detail_tokens = content_tokens(detail);
+    let phrase_tokens = content_tokens(phrase);
+    if detail_tokens.is_empty() || phrase_tokens.is_empty() {
+        return 0.0;
+    }
+
+    let shared: Vec<&String> = detail_tokens
+        .iter()
+        .filter(|t| phrase_tokens.contains(t))
+        .collect();
+    if shared.is_empty() {
+        return 0.0;
+    }
+
+    // Coverage only counts when the phrase names something, not just a colour or size.
+    let names_something = shared.iter().any(|t| !MODIFIERS.contains(t.as_str()));
+    let coverage = if names_something {
+        shared.len() as f32 / detail_tokens.len() as f32
+    } else {
+        0.0
+    };
+
+    let head_score = match head_token(detail) {
+        Some(head) if phrase_tokens.contains(&head) => {
+            let others = detail_tokens.len().saturating_sub(1);
+            if others == 0 {
+                1.0
+            } else {
+                let shared_others = shared.iter().filter(|t| **t != &head).count();
+                HEAD_WEIGHT + (1.0 - HEAD_WEIGHT) * shared_others as f32 / others as f32
+            }
+        }
+        _ => 0.0,
+    };
+
+    head_score.max(coverage).min(1.0)
+}
+
+/// Splits a child's message into phrases at punctuation and joining words, so that
+/// "a dog and a red ball" can match two details.
+pub fn split_phrases(message: &str) -> Vec<String> {
+    let mut phrases = Vec::new();
+    for sentence in message.split([',', '.', ';', '!', '?', '\n']) {
+        let mut current = Vec::new();
+        for word in sentence.split_whitespace() {
+            let lower = word.to_lowercase();
+            if lower == "and" || lower == "with" || lower == "also" || lower == "plus" {
+                if !current.is_empty() {
+                    phrases.push(current.join(" "));
+                    current.clear();
+                }
+            } else {
+                current.push(word);
+            }
+        }
+        if !current.is_empty() {
+            phrases.push(current.join(" "));
+        }
+    }
+    phrases
+}
+
+/// Finds the detail a phrase matches best, if any scores at least `threshold`.
+pub fn best_match<'a, I>(phrase: &str, details: I, threshold: f32) -> Option<(usize, f32)>
+where
+    I: IntoIterator<Item = &'a str>,
+{
+    details
+        .into_iter()
+        .enumerate()
+        .map(|(i, detail)| (i, match_score(phrase, detail)))
+        .filter(|(_, score)| *score >= threshold)
+        .fold(None, |best: Option<(usize, f32)>, (i, score)| match best {
+            Some((_, best_score)) if best_score >= score => best,
+            _ => Some((i, score)),
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stem() {
+        assert_eq!(stem("puppies"), "puppy");
+        assert_eq!(stem("sitting"), "sit");
+        assert_eq!(stem("boxes"), "box");
+        assert_eq!(stem("dogs"), "dog");
+        assert_eq!(stem("glass"), "glass");
+        assert_eq!(stem("jumped"), "jump");
+        assert_eq!(stem("red"), "red");
+    }
+
+    #[test]
+    fn test_head_noun_matches() {
+        let detail = "brown dog sitting on the grass";
+        assert!(match_score("a dog", detail) >= DEFAULT_MATCH_THRESHOLD);
+        assert!(match_score("there's a puppy", detail) >= DEFAULT_MATCH_THRESHOLD);
+        assert!(match_score("brown dogs", detail) > match_score("a dog", detail));
+        assert!(match_score("the grass", detail) < DEFAULT_MATCH_THRESHOLD);
+        assert_eq!(match_score("a cat", detail), 0.0);
+    }
+
+    #[test]
+    fn test_colour_alone_does_not_match() {
+        assert!(match_score("red", "red ball") < DEFAULT_MATCH_THRESHOLD);
+        assert!(match_score("a ball", "red ball") >= DEFAULT_MATCH_THRESHOLD);
+        assert!(match_score("the girl is happy", "smiling girl with brown hair") > 0.6);
+    }
+
+    #[test]
+    fn test_split_and_best_match() {
+        let details = ["brown dog sitting on the grass", "red ball", "blue sky with clouds"];
+        let phrases = split_phrases("I see a puppy and a ball. The sky!");
+        assert_eq!(phrases, vec!["I see a puppy", "a ball", "The sky"]);
+        let matches: Vec<usize> = phrases
+            .iter()
+            .filter_map(|p| best_match(p, details.iter().copied(), DEFAULT_MATCH_THRESHOLD))
+            .map(|(i, _)| i)
+            .collect();
+        assert_eq!(matches, vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn test_negated_phrases_do_not_match() {
+        let details = ["brown dog sitting on the grass", "red ball"];
+        assert_eq!(match_score("there is no dog", details[0]), 0.0);
+        assert_eq!(match_score("I don\u{2019}t see a puppy", details[0]), 0.0);
+        assert_eq!(match_score("it's not a ball", details[1]), 0.0);
+        // Only the denied part of a message is dropped.
+        let matches: Vec<usize> = split_phrases("There isn't a dog, but there is a ball")
+            .iter()
+            .filter_map(|p| best_match(p, details.iter().copied(), DEFAULT_MATCH_THRESHOLD))
+            .map(|(i, _)| i)
+            .collect();
+        assert_eq!(matches, vec![1]);
+    }
+}
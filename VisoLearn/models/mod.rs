@@ -1,9 +1,19 @@
 // Export modules to make them accessible to the rest of the crate
 pub mod evaluation;
+pub mod hints;
+#[cfg(not(target_arch = "wasm32"))]
 pub mod image_generation;
+pub mod matching;
+pub mod progression;
+#[cfg(not(target_arch = "wasm32"))]
 pub mod prompt_generation;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod vision;
 
 // Re-export commonly used items
-pub use evaluation::evaluate_description;
+#[cfg(not(target_arch = "wasm32"))]
+pub use evaluation::compare_details_chat_fn;
+#[cfg(not(target_arch = "wasm32"))]
 pub use image_generation::{generate_image, ImageGenerationConfig};
-pub use prompt_generation::{generate_educational_prompt, PromptOptions};
\ No newline at end of file
+#[cfg(not(target_arch = "wasm32"))]
+pub use prompt_generation::{generate_prompt_from_options, GenerativeModel};
\ No newline at end of file
@@ -0,0 +1,364 @@
+use crate::config::Difficulty;
+use crate::error::{Result, VisoLearnError};
+use crate::models::hints::UsedHint;
+use crate::utils::session::Session;
+
+// Decides the difficulty of the next image from how the learner did on recent ones.
+// Only sessions at the current level count towards a decision, so after any change the
+// learner has to complete `min_sessions_at_level` images before the level can move again.
+
+/// Thresholds and weights used by the [`ProgressionEngine`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProgressionPolicy {
+    /// How many of the most recent sessions at the current level are averaged.
+    pub window: usize,
+    /// Sessions needed at a level before it may change (hysteresis).
+    pub min_sessions_at_level: usize,
+    /// Move up when the rolling score is at or above this (0.0-1.0).
+    pub raise_at: f32,
+    /// Move down when the rolling score is at or below this (0.0-1.0).
+    pub lower_at: f32,
+    /// Score lost for being given every key detail's answer; scaled by hint cost per detail.
+    pub hint_penalty: f32,
+    /// Score lost for using every attempt; scaled by the share of attempts used.
+    pub attempt_penalty: f32,
+}
+
+impl Default for ProgressionPolicy {
+    fn default() -> Self {
+        Self::standard()
+    }
+}
+
+impl ProgressionPolicy {
+    pub const NAMES: [&'static str; 3] = ["gentle", "standard", "brisk"];
+
+    /// Slow to raise and quick to lower, for learners who find changes unsettling.
+    pub fn gentle() -> Self {
+        Self {
+            window: 4,
+            min_sessions_at_level: 3,
+            raise_at: 0.85,
+            lower_at: 0.45,
+            hint_penalty: 0.3,
+            attempt_penalty: 0.2,
+        }
+    }
+
+    pub fn standard() -> Self {
+        Self {
+            window: 3,
+            min_sessions_at_level: 2,
+            raise_at: 0.8,
+            lower_at: 0.35,
+            hint_penalty: 0.3,
+            attempt_penalty: 0.15,
+        }
+    }
+
+    /// Raises after a single strong session, for confident learners.
+    pub fn brisk() -> Self {
+        Self {
+            window: 2,
+            min_sessions_at_level: 1,
+            raise_at: 0.7,
+            lower_at: 0.3,
+            hint_penalty: 0.2,
+            attempt_penalty: 0.1,
+        }
+    }
+
+    /// Looks up one of the named presets in [`ProgressionPolicy::NAMES`].
+    pub fn from_name(name: &str) -> Result<Self> {
+        match name.trim().to_lowercase().as_str() {
+            "gentle" => Ok(Self::gentle()),
+            "standard" => Ok(Self::standard()),
+            "brisk" => Ok(Self::brisk()),
+            _ => Err(VisoLearnError::InvalidValue {
+                kind: "progression policy",
+                value: name.to_string(),
+                expected: Self::NAMES.join(", "),
+            }),
+        }
+    }
+}
+
+/// What happened on one image, as far as progression is concerned.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SessionOutcome {
+    pub difficulty: Difficulty,
+    pub key_details: usize,
+    pub identified: usize,
+    pub hints_used: usize,
+    /// Sum of [`HintLevel::cost`](crate::models::hints::HintLevel::cost) over the hints used.
+    pub hint_cost: f32,
+    pub attempts_used: u32,
+    pub attempt_limit: u32,
+}
+
+impl SessionOutcome {
+    pub fn from_session(session: &Session) -> Self {
+        Self {
+            difficulty: session.difficulty,
+            key_details: session.key_details.len(),
+            identified: session.identified_details.len(),
+            hints_used: session.used_hints.len(),
+            hint_cost: session.used_hints.iter().map(|hint: &UsedHint| hint.level.cost()).sum(),
+            attempts_used: session.attempt_count,
+            attempt_limit: session.attempt_limit,
+        }
+    }
+
+    /// Share of key details found, less the policy's hint and attempt penalties (0.0-1.0).
+    pub fn score(&self, policy: &ProgressionPolicy) -> f32 {
+        if self.key_details == 0 {
+            return 0.0;
+        }
+        let details = self.key_details as f32;
+        let accuracy = self.identified.min(self.key_details) as f32 / details;
+        let hints = (self.hint_cost / details).min(1.0);
+        let attempts = if self.attempt_limit == 0 {
+            0.0
+        } else {
+            (self.attempts_used as f32 / self.attempt_limit as f32).min(1.0)
+        };
+        (accuracy - policy.hint_penalty * hints - policy.attempt_penalty * attempts).clamp(0.0, 1.0)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProgressionAction {
+    Raise,
+    Hold,
+    Lower,
+}
+
+/// The engine's verdict for the next image.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProgressionDecision {
+    pub action: ProgressionAction,
+    pub from: Difficulty,
+    pub to: Difficulty,
+    /// Rolling score the decision was based on, if enough sessions were available.
+    pub rolling_score: Option<f32>,
+    /// A sentence explaining the decision, suitable for teachers and logs.
+    pub reason: String,
+}
+
+impl ProgressionDecision {
+    pub fn changed(&self) -> bool {
+        self.from != self.to
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ProgressionEngine {
+    pub policy: ProgressionPolicy,
+}
+
+impl ProgressionEngine {
+    pub fn new(policy: ProgressionPolicy) -> Self {
+        Self { policy }
+    }
+
+    /// Decides the next difficulty from completed sessions, oldest first. Incomplete sessions
+    /// and sessions without key details are ignored.
+    pub fn decide(&self, current: Difficulty, history: &[Session]) -> ProgressionDecision {
+        let outcomes: Vec<SessionOutcome> = history
+            .iter()
+            .filter(|session| session.completed && !session.key_details.is_empty())
+            .map(SessionOutcome::from_session)
+            .collect();
+        self.decide_from_outcomes(current, &outcomes)
+    }
+
+    pub fn decide_from_outcomes(&self, current: Difficulty, outcomes: &[SessionOutcome]) -> ProgressionDecision {
+        let policy = &self.policy;
+        let at_level: Vec<&SessionOutcome> = outcomes
+            .iter()
+            .rev()
+            .take_while(|outcome| outcome.difficulty == current)
+            .take(policy.window.max(1))
+            .collect();
+
+        let hold = |rolling_score, reason: String| ProgressionDecision {
+            action: ProgressionAction::Hold,
+            from: current,
+            to: current,
+            rolling_score,
+            reason,
+        };
+
+        if at_level.len() < policy.min_sessions_at_level.max(1) {
+            return hold(
+                None,
+                format!(
+                    "Staying at {}: {} of {} images needed at this level have been completed.",
+                    current,
+                    at_level.len(),
+                    policy.min_sessions_at_level.max(1)
+                ),
+            );
+        }
+
+        let rolling = at_level.iter().map(|outcome| outcome.score(policy)).sum::<f32>() / at_level.len() as f32;
+        let hints: usize = at_level.iter().map(|outcome| outcome.hints_used).sum();
+        let summary = format!(
+            "a score of {:.0}% over the last {} {} image{} ({} hint{} used)",
+            rolling * 100.0,
+            at_level.len(),
+            current,
+            if at_level.len() == 1 { "" } else { "s" },
+            hints,
+            if hints == 1 { "" } else { "s" }
+        );
+
+        if rolling >= policy.raise_at {
+            if current == current.next() {
+                return hold(
+                    Some(rolling),
+                    format!("Staying at {}, the hardest level, after {}.", current, summary),
+                );
+            }
+            return ProgressionDecision {
+                action: ProgressionAction::Raise,
+                from: current,
+                to: current.next(),
+                rolling_score: Some(rolling),
+                reason: format!(
+                    "Moving up to {} after {}, at or above the {:.0}% needed to advance.",
+                    current.next(),
+                    summary,
+                    policy.raise_at * 100.0
+                ),
+            };
+        }
+
+        if rolling <= policy.lower_at {
+            if current == current.prev() {
+                return hold(
+                    Some(rolling),
+                    format!("Staying at {}, the easiest level, after {}.", current, summary),
+                );
+            }
+            return ProgressionDecision {
+                action: ProgressionAction::Lower,
+                from: current,
+                to: current.prev(),
+                rolling_score: Some(rolling),
+                reason: format!(
+                    "Moving down to {} after {}, at or below {:.0}%.",
+                    current.prev(),
+                    summary,
+                    policy.lower_at * 100.0
+                ),
+            };
+        }
+
+        hold(
+            Some(rolling),
+            format!(
+                "Staying at {} after {}, between {:.0}% and {:.0}%.",
+                current,
+                summary,
+                policy.lower_at * 100.0,
+                policy.raise_at * 100.0
+            ),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::hints::HintLevel;
+
+    fn outcome(difficulty: Difficulty, identified: usize, hints_used: usize, attempts_used: u32) -> SessionOutcome {
+        SessionOutcome {
+            difficulty,
+            key_details: 5,
+            identified,
+            hints_used,
+            hint_cost: hints_used as f32 * 0.5,
+            attempts_used,
+            attempt_limit: 3,
+        }
+    }
+
+    #[test]
+    fn test_raises_after_consistent_success() {
+        let engine = ProgressionEngine::default();
+        let history = [outcome(Difficulty::Simple, 5, 0, 0), outcome(Difficulty::Simple, 5, 0, 1)];
+        let decision = engine.decide_from_outcomes(Difficulty::Simple, &history);
+        assert_eq!(decision.action, ProgressionAction::Raise);
+        assert_eq!(decision.to, Difficulty::Moderate);
+        assert!(decision.reason.contains("Moving up to Moderate"), "{}", decision.reason);
+    }
+
+    #[test]
+    fn test_lowers_after_repeated_struggles() {
+        let engine = ProgressionEngine::default();
+        let history = [outcome(Difficulty::Moderate, 1, 2, 3), outcome(Difficulty::Moderate, 2, 3, 3)];
+        let decision = engine.decide_from_outcomes(Difficulty::Moderate, &history);
+        assert_eq!(decision.action, ProgressionAction::Lower);
+        assert_eq!(decision.to, Difficulty::Simple);
+        assert!(decision.reason.contains("5 hints used"), "{}", decision.reason);
+    }
+
+    #[test]
+    fn test_hysteresis_holds_after_a_change() {
+        let engine = ProgressionEngine::default();
+        // Strong results at the previous level do not count towards the new one.
+        let history = [
+            outcome(Difficulty::Simple, 5, 0, 0),
+            outcome(Difficulty::Simple, 5, 0, 0),
+            outcome(Difficulty::Moderate, 5, 0, 0),
+        ];
+        let decision = engine.decide_from_outcomes(Difficulty::Moderate, &history);
+        assert_eq!(decision.action, ProgressionAction::Hold);
+        assert_eq!(decision.rolling_score, None);
+
+        let brisk = ProgressionEngine::new(ProgressionPolicy::brisk());
+        assert_eq!(brisk.decide_from_outcomes(Difficulty::Moderate, &history).action, ProgressionAction::Raise);
+    }
+
+    #[test]
+    fn test_hints_and_attempts_reduce_the_score() {
+        let policy = ProgressionPolicy::standard();
+        let clean = outcome(Difficulty::Simple, 4, 0, 0).score(&policy);
+        let helped = outcome(Difficulty::Simple, 4, 2, 3).score(&policy);
+        assert!((clean - 0.8).abs() < 1e-6);
+        assert!(helped < clean);
+
+        // A first-letter hint costs more than a pointer to the right part of the picture.
+        let region = SessionOutcome { hint_cost: HintLevel::Region.cost(), ..outcome(Difficulty::Simple, 4, 1, 0) };
+        let letter = SessionOutcome { hint_cost: HintLevel::FirstLetter.cost(), ..region.clone() };
+        assert!(letter.score(&policy) < region.score(&policy));
+
+        let engine = ProgressionEngine::new(policy);
+        let history = [outcome(Difficulty::Simple, 4, 2, 3), outcome(Difficulty::Simple, 4, 2, 3)];
+        assert_eq!(engine.decide_from_outcomes(Difficulty::Simple, &history).action, ProgressionAction::Hold);
+    }
+
+    #[test]
+    fn test_stays_within_bounds_and_reads_sessions() {
+        let engine = ProgressionEngine::new(ProgressionPolicy::brisk());
+        let mut session = Session::new();
+        session.difficulty = Difficulty::VeryDetailed;
+        session.key_details = vec!["a".to_string(), "b".to_string()];
+        session.identified_details = session.key_details.clone();
+        session.completed = true;
+        let unfinished = Session {
+            completed: false,
+            ..session.clone()
+        };
+
+        let decision = engine.decide(Difficulty::VeryDetailed, &[session, unfinished]);
+        assert_eq!(decision.action, ProgressionAction::Hold);
+        assert!(!decision.changed());
+        assert!(decision.reason.contains("hardest level"), "{}", decision.reason);
+
+        assert!(ProgressionPolicy::from_name("Gentle").is_ok());
+        assert!(matches!(ProgressionPolicy::from_name("turbo"), Err(VisoLearnError::InvalidValue { .. })));
+    }
+}
@@ -1,90 +1,299 @@
-use std::collections::HashMap;
-use std::error::Error;
-use once_cell::sync::Lazy;
-use lazy_static::lazy_static;
-
-// Configuration module with default treatment plans.
-pub mod config {
-    use std::collections::HashMap;
-    use lazy_static::lazy_static;
-
-    lazy_static! {
-        pub static ref DEFAULT_TREATMENT_PLANS: HashMap<&'static str, &'static str> = {
-            let mut m = HashMap::new();
-            m.insert("Level 1", "Default treatment plan for Level 1");
-            m.insert("Level 2", "Default treatment plan for Level 2");
-            // Add other levels as needed.
-            m
-        };
+use reqwest::Client;
+use serde_json::json;
+
+use crate::config::{self, Difficulty, ImageStyle, SupportLevel, TextConfig};
+use crate::error::{Result, VisoLearnError};
+use crate::models::vision::GEMINI_KEY_HEADER;
+
+const GEMINI_API_BASE: &str = "https://generativelanguage.googleapis.com/v1beta";
+const GEMINI_DEFAULT_MODEL: &str = "gemini-2.0-pro-exp-02-05";
+const OPENAI_API_BASE: &str = "https://api.openai.com/v1";
+const OPENAI_DEFAULT_MODEL: &str = "gpt-4o-mini";
+
+/// Which API a [`GenerativeModel`] talks to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextProvider {
+    /// Google Gemini `generateContent`.
+    Gemini,
+    /// Any OpenAI-compatible `/chat/completions` endpoint.
+    OpenAi,
+}
+
+impl TextProvider {
+    pub fn name(&self) -> &'static str {
+        match self {
+            TextProvider::Gemini => "Gemini",
+            TextProvider::OpenAi => "OpenAI-compatible",
+        }
+    }
+}
+
+/// A Gemini safety filter, e.g. `HARM_CATEGORY_HARASSMENT` at `BLOCK_LOW_AND_ABOVE`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SafetySetting {
+    pub category: String,
+    pub threshold: String,
+}
+
+impl SafetySetting {
+    /// The strictest thresholds for every category, suitable for content shown to children.
+    pub fn child_safe() -> Vec<SafetySetting> {
+        [
+            "HARM_CATEGORY_HARASSMENT",
+            "HARM_CATEGORY_HATE_SPEECH",
+            "HARM_CATEGORY_SEXUALLY_EXPLICIT",
+            "HARM_CATEGORY_DANGEROUS_CONTENT",
+        ]
+        .iter()
+        .map(|category| SafetySetting {
+            category: category.to_string(),
+            threshold: "BLOCK_LOW_AND_ABOVE".to_string(),
+        })
+        .collect()
+    }
+}
+
+/// Sampling and safety options sent with every request.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GenerationSettings {
+    pub system_prompt: Option<String>,
+    pub temperature: Option<f32>,
+    pub max_tokens: Option<u32>,
+    /// Only honoured by Gemini; OpenAI-compatible servers apply their own moderation.
+    pub safety_settings: Vec<SafetySetting>,
+}
+
+impl Default for GenerationSettings {
+    fn default() -> Self {
+        Self {
+            system_prompt: None,
+            temperature: None,
+            max_tokens: None,
+            safety_settings: SafetySetting::child_safe(),
+        }
     }
 }
 
-/// A dummy structure representing the Google Generative AI model.
+/// A client for a hosted text-generation model.
+#[derive(Debug, Clone)]
 pub struct GenerativeModel {
+    provider: TextProvider,
     model_name: String,
+    base_url: String,
+    api_key: Option<String>,
+    settings: GenerationSettings,
+    client: Client,
 }
 
 impl GenerativeModel {
-    pub fn new(model_name: &str) -> Self {
+    pub fn new(provider: TextProvider, model_name: &str, base_url: &str, api_key: Option<String>) -> Self {
         Self {
+            provider,
             model_name: model_name.to_string(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            api_key,
+            settings: GenerationSettings::default(),
+            client: Client::new(),
+        }
+    }
+
+    /// Builds the client described by `config.backend`: "gemini" or "openai".
+    pub fn from_config(config: &TextConfig) -> Result<Self> {
+        match config.backend.trim().to_lowercase().as_str() {
+            "gemini" => {
+                let api_key = config
+                    .api_key
+                    .clone()
+                    .or_else(|| std::env::var("GEMINI_API_KEY").ok())
+                    .or_else(|| Some(config::google_api_key()).filter(|key| !key.is_empty()))
+                    .ok_or_else(|| VisoLearnError::Config("GEMINI_API_KEY is not set".to_string()))?;
+                Ok(Self::new(
+                    TextProvider::Gemini,
+                    config.model.as_deref().unwrap_or(GEMINI_DEFAULT_MODEL),
+                    config.api_base.as_deref().unwrap_or(GEMINI_API_BASE),
+                    Some(api_key),
+                ))
+            }
+            "openai" => Ok(Self::new(
+                TextProvider::OpenAi,
+                config.model.as_deref().unwrap_or(OPENAI_DEFAULT_MODEL),
+                config.api_base.as_deref().unwrap_or(OPENAI_API_BASE),
+                config
+                    .api_key
+                    .clone()
+                    .or_else(|| std::env::var("OPENAI_API_KEY").ok()),
+            )),
+            other => Err(VisoLearnError::Config(format!(
+                "unknown text backend \"{}\" (expected gemini or openai)",
+                other
+            ))),
         }
     }
 
-    /// Dummy API call. Replace with your actual HTTP request to Google’s service.
-    pub async fn generate_content(&self, prompt: &str) -> Result<String, Box<dyn Error>> {
-        // For a real implementation, you might do:
-        // let client = reqwest::Client::new();
-        // let response = client.post("https://api.generativeai.google/v1/...")
-        //     .json(&serde_json::json!({ "prompt": prompt, "model": self.model_name }))
-        //     .send().await?;
-        // let json_response = response.json::<serde_json::Value>().await?;
-        // Extract the generated text from json_response.
-        //
-        // Here we simply return the prompt back for demonstration.
-        Ok(prompt.to_string())
+    pub fn with_settings(mut self, settings: GenerationSettings) -> Self {
+        self.settings = settings;
+        self
+    }
+
+    pub fn with_system_prompt(mut self, system_prompt: &str) -> Self {
+        self.settings.system_prompt = Some(system_prompt.to_string());
+        self
+    }
+
+    pub fn settings(&self) -> &GenerationSettings {
+        &self.settings
+    }
+
+    /// Sends `prompt` to the model and returns its text reply.
+    pub async fn generate_content(&self, prompt: &str) -> Result<String> {
+        match self.provider {
+            TextProvider::Gemini => self.generate_gemini(prompt).await,
+            TextProvider::OpenAi => self.generate_openai(prompt).await,
+        }
+    }
+
+    async fn generate_gemini(&self, prompt: &str) -> Result<String> {
+        let mut body = json!({
+            "contents": [{ "role": "user", "parts": [{ "text": prompt }] }],
+            "safetySettings": self.settings.safety_settings.iter().map(|setting| json!({
+                "category": setting.category,
+                "threshold": setting.threshold
+            })).collect::<Vec<_>>()
+        });
+        if let Some(system_prompt) = &self.settings.system_prompt {
+            body["systemInstruction"] = json!({ "parts": [{ "text": system_prompt }] });
+        }
+        let mut generation_config = serde_json::Map::new();
+        if let Some(temperature) = self.settings.temperature {
+            generation_config.insert("temperature".to_string(), json!(temperature));
+        }
+        if let Some(max_tokens) = self.settings.max_tokens {
+            generation_config.insert("maxOutputTokens".to_string(), json!(max_tokens));
+        }
+        if !generation_config.is_empty() {
+            body["generationConfig"] = serde_json::Value::Object(generation_config);
+        }
+
+        let url = format!("{}/models/{}:generateContent", self.base_url, self.model_name);
+        let mut request = self.client.post(url).json(&body);
+        if let Some(api_key) = &self.api_key {
+            request = request.header(GEMINI_KEY_HEADER, api_key);
+        }
+        let service = self.provider.name();
+        let response = send(service, request).await?;
+
+        if let Some(reason) = response["promptFeedback"]["blockReason"].as_str() {
+            return Err(blocked(service, reason));
+        }
+        let candidate = &response["candidates"][0];
+        if candidate.is_null() {
+            return Err(VisoLearnError::invalid_response(service, "empty response"));
+        }
+        if let Some(reason @ ("SAFETY" | "PROHIBITED_CONTENT" | "BLOCKLIST")) =
+            candidate["finishReason"].as_str()
+        {
+            return Err(blocked(service, reason));
+        }
+
+        let text: String = candidate["content"]["parts"]
+            .as_array()
+            .ok_or_else(|| VisoLearnError::invalid_response(service, "candidate has no parts"))?
+            .iter()
+            .filter_map(|part| part["text"].as_str())
+            .collect();
+        non_empty(service, text)
+    }
+
+    async fn generate_openai(&self, prompt: &str) -> Result<String> {
+        let mut messages = Vec::new();
+        if let Some(system_prompt) = &self.settings.system_prompt {
+            messages.push(json!({ "role": "system", "content": system_prompt }));
+        }
+        messages.push(json!({ "role": "user", "content": prompt }));
+
+        let mut body = json!({ "model": self.model_name, "messages": messages });
+        if let Some(temperature) = self.settings.temperature {
+            body["temperature"] = json!(temperature);
+        }
+        if let Some(max_tokens) = self.settings.max_tokens {
+            body["max_tokens"] = json!(max_tokens);
+        }
+
+        let url = format!("{}/chat/completions", self.base_url);
+        let mut request = self.client.post(url).json(&body);
+        if let Some(api_key) = &self.api_key {
+            request = request.bearer_auth(api_key);
+        }
+        let service = self.provider.name();
+        let response = send(service, request).await?;
+
+        let choice = &response["choices"][0];
+        if choice["finish_reason"].as_str() == Some("content_filter") {
+            return Err(blocked(service, "content_filter"));
+        }
+        let text = choice["message"]["content"]
+            .as_str()
+            .ok_or_else(|| VisoLearnError::invalid_response(service, "empty response"))?;
+        non_empty(service, text.to_string())
+    }
+}
+
+async fn send(service: &str, request: reqwest::RequestBuilder) -> Result<serde_json::Value> {
+    let response = request.send().await?;
+    let status = response.status();
+    if !status.is_success() {
+        let body = response.text().await.unwrap_or_default();
+        return Err(VisoLearnError::from_status(service, status.as_u16(), body));
+    }
+    response
+        .json()
+        .await
+        .map_err(|e| VisoLearnError::invalid_response(service, e.to_string()))
+}
+
+fn blocked(service: &str, reason: &str) -> VisoLearnError {
+    VisoLearnError::SafetyBlocked {
+        service: service.to_string(),
+        reason: reason.to_string(),
     }
 }
 
-/// Generates a detailed prompt from the provided options using the Google Gemini model.
+fn non_empty(service: &str, text: String) -> Result<String> {
+    if text.trim().is_empty() {
+        Err(VisoLearnError::invalid_response(service, "empty response"))
+    } else {
+        Ok(text)
+    }
+}
+
+/// System prompt for models used with [`generate_prompt_from_options`].
+pub const PROMPT_WRITER_SYSTEM_PROMPT: &str = "You write prompts for a text-to-image model that creates calm, clear educational images for autistic learners. Reply with the image prompt only, without commentary or headings.";
+
+/// Generates a detailed image prompt from the provided options using the given text model.
 pub async fn generate_prompt_from_options(
-    difficulty: &str,
+    model: &GenerativeModel,
+    difficulty: Difficulty,
     age: &str,
-    autism_level: &str,
+    autism_level: SupportLevel,
     topic_focus: &str,
     treatment_plan: Option<&str>,
-    image_style: &str,
-) -> Result<String, Box<dyn Error>> {
+    image_style: ImageStyle,
+) -> Result<String> {
     // Use default treatment plan if none provided.
-    let treatment_plan = if let Some(tp) = treatment_plan {
-        if tp.trim().is_empty() {
-            config::DEFAULT_TREATMENT_PLANS
-                .get(autism_level)
-                .unwrap_or(&config::DEFAULT_TREATMENT_PLANS["Level 1"])
-                .to_string()
-        } else {
-            tp.to_string()
+    let treatment_plan = match treatment_plan {
+        Some(tp) if !tp.trim().is_empty() => tp.to_string(),
+        _ => {
+            let default_plan = autism_level.default_treatment_plan();
+            log::info!(
+                "Using default treatment plan for {}: {}",
+                autism_level, default_plan
+            );
+            default_plan.to_string()
         }
-    } else {
-        config::DEFAULT_TREATMENT_PLANS
-            .get(autism_level)
-            .unwrap_or(&config::DEFAULT_TREATMENT_PLANS["Level 1"])
-            .to_string()
     };
-    println!(
-        "Using default treatment plan for {}: {}",
-        autism_level, treatment_plan
-    );
 
-    // Determine style instructions based on the provided image style.
-    let style_instruction = match image_style {
-        "Realistic" => "Create a realistic image with natural lighting and detailed textures, capturing the essence of real-world environments. Ensure the scene has a lifelike feel, with accurate light and shadow play, and textures that convey a true-to-life appearance.",
-        "Illustration" => "Create a clean and colorful illustration in the style of children's books, featuring bold outlines, vibrant colors, and a playful, engaging composition. Ensure the artwork has a soft, friendly feel with well-defined shapes and a sense of warmth and charm.",
-        "Cartoon" => "Create a friendly cartoon-style illustration with simplified shapes, bold outlines, and expressive characters. Ensure the characters have exaggerated facial expressions and dynamic poses to convey emotion and personality in a warm and inviting way.",
-        "Watercolor" => "Create a soft watercolor illustration with gentle color transitions, delicate brushstrokes, and a dreamy, ethereal quality. Ensure the colors blend seamlessly, evoking a sense of warmth and tranquility.",
-        "3D Rendering" => "Create a highly detailed 3D-rendered image with realistic depth, rich textures, and natural lighting effects. Ensure accurate reflections, shadows, and materials to enhance the sense of realism and immersion.",
-        _ => "",
-    };
+    let style_instruction = image_style.style_instruction();
+    let style_lower = image_style.as_str().to_lowercase();
 
     // Build the detailed prompt.
     let query = format!(
@@ -128,32 +337,142 @@ EXAMPLE FORMAT:
 CREATE YOUR DETAILED PROMPT NOW:"#,
         difficulty, age, autism_level, topic_focus,
         treatment_plan, image_style,
-        image_style.to_lowercase(), image_style.to_lowercase(),
+        style_lower, style_lower,
         style_instruction,
-        image_style, image_style.to_lowercase(),
-        image_style, image_style, image_style,
+        image_style, style_lower,
+        image_style, image_style,
         topic_focus, treatment_plan,
-        image_style.to_lowercase(),
-        image_style.to_lowercase()
+        style_lower,
+        style_lower
     );
 
-    // Instantiate the GenerativeModel and generate content.
-    let model = GenerativeModel::new("gemini-2.0-pro-exp-02-05");
     let response_text = model.generate_content(&query).await?;
     Ok(response_text.trim().to_string())
 }
 
-#[tokio::main]
-async fn main() {
-    let difficulty = "Medium";
-    let age = "10";
-    let autism_level = "Level 1";
-    let topic_focus = "Emotional Regulation";
-    let treatment_plan = "";  // Empty treatment plan to trigger the default.
-    let image_style = "Realistic";
-
-    match generate_prompt_from_options(difficulty, age, autism_level, topic_focus, Some(treatment_plan), image_style).await {
-        Ok(prompt) => println!("Generated prompt:\n{}", prompt),
-        Err(e) => eprintln!("Error: {}", e),
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{body_partial_json, body_string_contains, header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[tokio::test]
+    async fn test_gemini_generate_content() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/models/test-model:generateContent"))
+            .and(header(GEMINI_KEY_HEADER, "test-key"))
+            .and(body_partial_json(json!({
+                "systemInstruction": { "parts": [{ "text": "Be brief." }] },
+                "generationConfig": { "temperature": 0.5, "maxOutputTokens": 300 },
+                "safetySettings": [{ "category": "HARM_CATEGORY_HARASSMENT", "threshold": "BLOCK_LOW_AND_ABOVE" }]
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "candidates": [{ "content": { "parts": [{ "text": "A calm " }, { "text": "garden." }] } }]
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let model = GenerativeModel::new(TextProvider::Gemini, "test-model", &server.uri(), Some("test-key".to_string()))
+            .with_settings(GenerationSettings {
+                system_prompt: Some("Be brief.".to_string()),
+                temperature: Some(0.5),
+                max_tokens: Some(300),
+                ..GenerationSettings::default()
+            });
+        assert_eq!(model.generate_content("Write a prompt").await.unwrap(), "A calm garden.");
+    }
+
+    #[tokio::test]
+    async fn test_gemini_safety_block() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "promptFeedback": { "blockReason": "SAFETY" }
+            })))
+            .mount(&server)
+            .await;
+
+        let model = GenerativeModel::new(TextProvider::Gemini, "test-model", &server.uri(), None);
+        assert!(matches!(
+            model.generate_content("anything").await,
+            Err(VisoLearnError::SafetyBlocked { reason, .. }) if reason == "SAFETY"
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_openai_generate_content() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/chat/completions"))
+            .and(header("authorization", "Bearer sk-test"))
+            .and(body_partial_json(json!({
+                "model": "gpt-test",
+                "messages": [
+                    { "role": "system", "content": "Be brief." },
+                    { "role": "user", "content": "Write a prompt" }
+                ],
+                "max_tokens": 200
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "choices": [{ "message": { "content": "A sunny beach." }, "finish_reason": "stop" }]
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let model = GenerativeModel::new(TextProvider::OpenAi, "gpt-test", &server.uri(), Some("sk-test".to_string()))
+            .with_settings(GenerationSettings {
+                system_prompt: Some("Be brief.".to_string()),
+                max_tokens: Some(200),
+                ..GenerationSettings::default()
+            });
+        assert_eq!(model.generate_content("Write a prompt").await.unwrap(), "A sunny beach.");
+    }
+
+    #[tokio::test]
+    async fn test_quota_errors_are_typed() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(429).set_body_string("slow down"))
+            .mount(&server)
+            .await;
+
+        let model = GenerativeModel::new(TextProvider::OpenAi, "gpt-test", &server.uri(), None);
+        assert!(matches!(
+            model.generate_content("anything").await,
+            Err(VisoLearnError::QuotaExceeded { service, detail }) if service == "OpenAI-compatible" && detail == "slow down"
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_prompt_uses_default_plan_for_support_level() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(body_string_contains("Autism Level: Level 2"))
+            .and(body_string_contains(SupportLevel::Level2.default_treatment_plan()))
+            .and(body_string_contains("Image Style: 3D Rendering"))
+            .and(body_string_contains("professional 3d rendering"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "choices": [{ "message": { "content": "  A tidy 3D classroom.  " }, "finish_reason": "stop" }]
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let model = GenerativeModel::new(TextProvider::OpenAi, "gpt-test", &server.uri(), None);
+        let prompt = generate_prompt_from_options(
+            &model,
+            Difficulty::Simple,
+            "7",
+            SupportLevel::Level2,
+            "School",
+            Some("  "),
+            ImageStyle::Rendering3D,
+        )
+        .await
+        .unwrap();
+        assert_eq!(prompt, "A tidy 3D classroom.");
     }
 }
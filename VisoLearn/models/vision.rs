@@ -0,0 +1,742 @@
+use async_trait::async_trait;
+use base64::{engine::general_purpose, Engine as _};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::config::VisionConfig;
+use crate::error::{Result, VisoLearnError};
+use crate::utils::image_store::detect_mime;
+
+// Vision-language backends used to describe images, extract key details and evaluate
+// a child's descriptions. `from_config` picks one based on `VISION_BACKEND`.
+
+const GEMINI_API_BASE: &str = "https://generativelanguage.googleapis.com/v1beta";
+const GEMINI_DEFAULT_MODEL: &str = "gemini-2.0-flash";
+/// Gemini's API key header. The key is never put in the URL, which reqwest errors include.
+pub(crate) const GEMINI_KEY_HEADER: &str = "x-goog-api-key";
+const OPENAI_API_BASE: &str = "https://api.openai.com/v1";
+const OPENAI_DEFAULT_MODEL: &str = "gpt-4o-mini";
+const OLLAMA_API_BASE: &str = "http://localhost:11434";
+const OLLAMA_DEFAULT_MODEL: &str = "llava";
+
+/// An image sent to a vision model.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VisionImage {
+    pub mime_type: String,
+    pub data: Vec<u8>,
+}
+
+impl VisionImage {
+    /// Wraps raw image bytes, guessing the MIME type from their contents (PNG if unknown).
+    pub fn from_bytes(data: Vec<u8>) -> Self {
+        let mime_type = detect_mime(&data).unwrap_or("image/png");
+        Self {
+            mime_type: mime_type.to_string(),
+            data,
+        }
+    }
+
+    /// Decodes a `data:<mime>;base64,<payload>` URL.
+    pub fn from_data_url(data_url: &str) -> Option<Self> {
+        let rest = data_url.strip_prefix("data:")?;
+        let (header, payload) = rest.split_once(',')?;
+        let mime_type = header.strip_suffix(";base64")?;
+        let data = general_purpose::STANDARD.decode(payload.trim()).ok()?;
+        Some(Self {
+            mime_type: mime_type.to_string(),
+            data,
+        })
+    }
+
+    pub fn to_base64(&self) -> String {
+        general_purpose::STANDARD.encode(&self.data)
+    }
+
+    pub fn to_data_url(&self) -> String {
+        format!("data:{};base64,{}", self.mime_type, self.to_base64())
+    }
+}
+
+/// Receives each piece of a streamed reply.
+pub type OnToken<'a> = dyn Fn(&str) + Send + Sync + 'a;
+
+/// A vision-language model that answers a text prompt about an image.
+#[async_trait]
+pub trait VisionModel: Send + Sync {
+    /// Short name for logs and error messages, e.g. "Gemini".
+    fn name(&self) -> &str;
+
+    /// Sends the image and prompt and returns the model's text reply. When `json_output`
+    /// is set the backend is asked to constrain its reply to a JSON document.
+    async fn generate(
+        &self,
+        image: &VisionImage,
+        prompt: &str,
+        json_output: bool,
+    ) -> Result<String>;
+
+    /// Like `generate`, but passes each piece of the reply to `on_token` as it arrives.
+    /// Backends that cannot stream send the whole reply as a single piece.
+    async fn generate_streaming(
+        &self,
+        image: &VisionImage,
+        prompt: &str,
+        json_output: bool,
+        on_token: &OnToken<'_>,
+    ) -> Result<String> {
+        let text = self.generate(image, prompt, json_output).await?;
+        on_token(&text);
+        Ok(text)
+    }
+}
+
+/// Builds the backend named by `config.backend`: "gemini", "openai" or "local".
+pub fn from_config(config: &VisionConfig) -> Result<Box<dyn VisionModel>> {
+    match config.backend.trim().to_lowercase().as_str() {
+        "gemini" => {
+            let api_key = config
+                .api_key
+                .clone()
+                .or_else(|| std::env::var("GEMINI_API_KEY").ok())
+                .ok_or_else(|| VisoLearnError::Config("GEMINI_API_KEY is not set".to_string()))?;
+            Ok(Box::new(GeminiVision {
+                base_url: config.api_base.clone().unwrap_or_else(|| GEMINI_API_BASE.to_string()),
+                api_key,
+                model: config.model.clone().unwrap_or_else(|| GEMINI_DEFAULT_MODEL.to_string()),
+            }))
+        }
+        "openai" => Ok(Box::new(OpenAiVision {
+            base_url: config.api_base.clone().unwrap_or_else(|| OPENAI_API_BASE.to_string()),
+            api_key: config
+                .api_key
+                .clone()
+                .or_else(|| std::env::var("OPENAI_API_KEY").ok()),
+            model: config.model.clone().unwrap_or_else(|| OPENAI_DEFAULT_MODEL.to_string()),
+        })),
+        "local" | "ollama" => Ok(Box::new(LocalVision {
+            base_url: config.api_base.clone().unwrap_or_else(|| OLLAMA_API_BASE.to_string()),
+            model: config.model.clone().unwrap_or_else(|| OLLAMA_DEFAULT_MODEL.to_string()),
+        })),
+        other => Err(VisoLearnError::Config(format!(
+            "unknown vision backend \"{}\" (expected gemini, openai or local)",
+            other
+        ))),
+    }
+}
+
+async fn post_json<T: Serialize + ?Sized>(
+    backend: &str,
+    request: reqwest::RequestBuilder,
+    body: &T,
+) -> Result<serde_json::Value> {
+    let response = request.json(body).send().await?;
+
+    let status = response.status();
+    if !status.is_success() {
+        let body = response.text().await.unwrap_or_default();
+        return Err(VisoLearnError::from_status(backend, status.as_u16(), body));
+    }
+
+    response
+        .json::<serde_json::Value>()
+        .await
+        .map_err(|e| VisoLearnError::invalid_response(backend, e.to_string()))
+}
+
+// Sends a request whose reply is streamed and returns the response once its status is known.
+async fn post_streaming<T: Serialize + ?Sized>(
+    backend: &str,
+    request: reqwest::RequestBuilder,
+    body: &T,
+) -> Result<reqwest::Response> {
+    let response = request.json(body).send().await?;
+    let status = response.status();
+    if !status.is_success() {
+        let body = response.text().await.unwrap_or_default();
+        return Err(VisoLearnError::from_status(backend, status.as_u16(), body));
+    }
+    Ok(response)
+}
+
+// Reads a streamed reply one line at a time. `piece` returns the text carried by a line, if any;
+// each piece is passed to `on_token` and the trimmed whole is returned.
+async fn read_stream<F>(
+    backend: &str,
+    mut response: reqwest::Response,
+    piece: F,
+    on_token: &OnToken<'_>,
+) -> Result<String>
+where
+    F: Fn(&str) -> Result<Option<String>>,
+{
+    let mut text = String::new();
+    let mut buffer: Vec<u8> = Vec::new();
+    let mut handle_line = |line: &[u8]| -> Result<()> {
+        let line = String::from_utf8_lossy(line);
+        if let Some(piece) = piece(line.trim())? {
+            if !piece.is_empty() {
+                on_token(&piece);
+                text.push_str(&piece);
+            }
+        }
+        Ok(())
+    };
+    while let Some(chunk) = response.chunk().await? {
+        buffer.extend_from_slice(&chunk);
+        while let Some(end) = buffer.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = buffer.drain(..=end).collect();
+            handle_line(&line)?;
+        }
+    }
+    handle_line(&buffer)?;
+
+    let text = text.trim().to_string();
+    if text.is_empty() {
+        return Err(VisoLearnError::invalid_response(backend, "no text in the reply"));
+    }
+    Ok(text)
+}
+
+// The JSON payload of a server-sent event line, or `None` for comments, blank lines and `[DONE]`.
+fn sse_data(line: &str) -> Option<&str> {
+    let data = line.strip_prefix("data:")?.trim();
+    (data != "[DONE]").then_some(data)
+}
+
+fn parse_stream_event(backend: &str, data: &str) -> Result<serde_json::Value> {
+    serde_json::from_str(data).map_err(|e| VisoLearnError::invalid_response(backend, e.to_string()))
+}
+
+// --- Gemini ---
+
+#[derive(Serialize)]
+struct GeminiPart {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    inline_data: Option<GeminiInlineData>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    text: Option<String>,
+}
+
+#[derive(Serialize)]
+struct GeminiInlineData {
+    mime_type: String,
+    data: String,
+}
+
+#[derive(Serialize)]
+struct GeminiContent {
+    parts: Vec<GeminiPart>,
+}
+
+#[derive(Serialize)]
+struct GeminiGenerationConfig {
+    response_mime_type: String,
+}
+
+#[derive(Serialize)]
+struct GeminiRequest {
+    contents: Vec<GeminiContent>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    generation_config: Option<GeminiGenerationConfig>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct GeminiResponse {
+    candidates: Option<Vec<GeminiCandidate>>,
+    prompt_feedback: Option<GeminiPromptFeedback>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct GeminiPromptFeedback {
+    block_reason: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct GeminiCandidate {
+    // Missing when the reply was blocked.
+    content: Option<GeminiContentResponse>,
+    finish_reason: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct GeminiContentResponse {
+    #[serde(default)]
+    parts: Vec<GeminiPartResponse>,
+}
+
+#[derive(Deserialize, Debug)]
+struct GeminiPartResponse {
+    text: Option<String>,
+}
+
+/// Google Gemini `generateContent`.
+#[derive(Debug, Clone)]
+pub struct GeminiVision {
+    pub base_url: String,
+    pub api_key: String,
+    pub model: String,
+}
+
+#[async_trait]
+impl VisionModel for GeminiVision {
+    fn name(&self) -> &str {
+        "Gemini"
+    }
+
+    async fn generate(
+        &self,
+        image: &VisionImage,
+        prompt: &str,
+        json_output: bool,
+    ) -> Result<String> {
+        let gemini_request = self.request(image, prompt, json_output);
+        let url = format!(
+            "{}/models/{}:generateContent",
+            self.base_url.trim_end_matches('/'),
+            self.model
+        );
+        let value = post_json(self.name(), self.post(url), &gemini_request).await?;
+        let gemini_response: GeminiResponse = serde_json::from_value(value)
+            .map_err(|e| VisoLearnError::invalid_response(self.name(), e.to_string()))?;
+
+        gemini_response
+            .text(self.name())?
+            .map(|text| text.trim().to_string())
+            .ok_or_else(|| VisoLearnError::invalid_response(self.name(), "no text in the reply"))
+    }
+
+    async fn generate_streaming(
+        &self,
+        image: &VisionImage,
+        prompt: &str,
+        json_output: bool,
+        on_token: &OnToken<'_>,
+    ) -> Result<String> {
+        let gemini_request = self.request(image, prompt, json_output);
+        let url = format!(
+            "{}/models/{}:streamGenerateContent?alt=sse",
+            self.base_url.trim_end_matches('/'),
+            self.model
+        );
+        let response = post_streaming(self.name(), self.post(url), &gemini_request).await?;
+        read_stream(
+            self.name(),
+            response,
+            |line| {
+                let Some(data) = sse_data(line) else {
+                    return Ok(None);
+                };
+                let event: GeminiResponse = serde_json::from_value(parse_stream_event(self.name(), data)?)
+                    .map_err(|e| VisoLearnError::invalid_response(self.name(), e.to_string()))?;
+                event.text(self.name())
+            },
+            on_token,
+        )
+        .await
+    }
+}
+
+impl GeminiResponse {
+    /// The reply's text, or `SafetyBlocked` if Gemini refused the prompt or withheld the reply.
+    fn text(self, service: &str) -> Result<Option<String>> {
+        if let Some(reason) = self.prompt_feedback.and_then(|feedback| feedback.block_reason) {
+            return Err(VisoLearnError::SafetyBlocked {
+                service: service.to_string(),
+                reason,
+            });
+        }
+        let Some(candidate) = self.candidates.and_then(|candidates| candidates.into_iter().next()) else {
+            return Ok(None);
+        };
+        let text = candidate
+            .content
+            .and_then(|content| content.parts.into_iter().find_map(|part| part.text));
+        match (text, candidate.finish_reason) {
+            (None, Some(reason)) if matches!(reason.as_str(), "SAFETY" | "PROHIBITED_CONTENT" | "BLOCKLIST") => {
+                Err(VisoLearnError::SafetyBlocked {
+                    service: service.to_string(),
+                    reason,
+                })
+            }
+            (text, _) => Ok(text),
+        }
+    }
+}
+
+impl GeminiVision {
+    fn post(&self, url: String) -> reqwest::RequestBuilder {
+        Client::new().post(url).header(GEMINI_KEY_HEADER, &self.api_key)
+    }
+
+    fn request(&self, image: &VisionImage, prompt: &str, json_output: bool) -> GeminiRequest {
+        GeminiRequest {
+            contents: vec![GeminiContent {
+                parts: vec![
+                    GeminiPart {
+                        inline_data: Some(GeminiInlineData {
+                            mime_type: image.mime_type.clone(),
+                            data: image.to_base64(),
+                        }),
+                        text: None,
+                    },
+                    GeminiPart {
+                        inline_data: None,
+                        text: Some(prompt.to_string()),
+                    },
+                ],
+            }],
+            generation_config: json_output.then(|| GeminiGenerationConfig {
+                response_mime_type: "application/json".to_string(),
+            }),
+        }
+    }
+}
+
+// --- OpenAI-compatible chat completions ---
+
+/// Any OpenAI-compatible `/chat/completions` endpoint that accepts `image_url` content,
+/// including OpenAI itself, vLLM and llama.cpp's server.
+#[derive(Debug, Clone)]
+pub struct OpenAiVision {
+    pub base_url: String,
+    pub api_key: Option<String>,
+    pub model: String,
+}
+
+#[async_trait]
+impl VisionModel for OpenAiVision {
+    fn name(&self) -> &str {
+        "OpenAI-compatible"
+    }
+
+    async fn generate(
+        &self,
+        image: &VisionImage,
+        prompt: &str,
+        json_output: bool,
+    ) -> Result<String> {
+        let body = self.body(image, prompt, json_output);
+        let value = post_json(self.name(), self.request(), &body).await?;
+
+        value["choices"][0]["message"]["content"]
+            .as_str()
+            .map(|text| text.trim().to_string())
+            .ok_or_else(|| VisoLearnError::invalid_response(self.name(), "no text in the reply"))
+    }
+
+    async fn generate_streaming(
+        &self,
+        image: &VisionImage,
+        prompt: &str,
+        json_output: bool,
+        on_token: &OnToken<'_>,
+    ) -> Result<String> {
+        let mut body = self.body(image, prompt, json_output);
+        body["stream"] = json!(true);
+        let response = post_streaming(self.name(), self.request(), &body).await?;
+        read_stream(
+            self.name(),
+            response,
+            |line| {
+                let Some(data) = sse_data(line) else {
+                    return Ok(None);
+                };
+                let event = parse_stream_event(self.name(), data)?;
+                Ok(event["choices"][0]["delta"]["content"].as_str().map(str::to_string))
+            },
+            on_token,
+        )
+        .await
+    }
+}
+
+impl OpenAiVision {
+    fn body(&self, image: &VisionImage, prompt: &str, json_output: bool) -> serde_json::Value {
+        let mut body = json!({
+            "model": self.model,
+            "messages": [{
+                "role": "user",
+                "content": [
+                    { "type": "text", "text": prompt },
+                    { "type": "image_url", "image_url": { "url": image.to_data_url() } }
+                ]
+            }]
+        });
+        if json_output {
+            body["response_format"] = json!({ "type": "json_object" });
+        }
+        body
+    }
+
+    fn request(&self) -> reqwest::RequestBuilder {
+        let url = format!("{}/chat/completions", self.base_url.trim_end_matches('/'));
+        let request = Client::new().post(url);
+        match &self.api_key {
+            Some(api_key) => request.bearer_auth(api_key),
+            None => request,
+        }
+    }
+}
+
+// --- Local (Ollama) ---
+
+/// A locally served model using Ollama's `/api/generate`, e.g. `llava`.
+#[derive(Debug, Clone)]
+pub struct LocalVision {
+    pub base_url: String,
+    pub model: String,
+}
+
+#[async_trait]
+impl VisionModel for LocalVision {
+    fn name(&self) -> &str {
+        "Local"
+    }
+
+    async fn generate(
+        &self,
+        image: &VisionImage,
+        prompt: &str,
+        json_output: bool,
+    ) -> Result<String> {
+        let body = self.body(image, prompt, json_output, false);
+        let value = post_json(self.name(), Client::new().post(self.url()), &body).await?;
+
+        value["response"]
+            .as_str()
+            .map(|text| text.trim().to_string())
+            .ok_or_else(|| VisoLearnError::invalid_response(self.name(), "no text in the reply"))
+    }
+
+    async fn generate_streaming(
+        &self,
+        image: &VisionImage,
+        prompt: &str,
+        json_output: bool,
+        on_token: &OnToken<'_>,
+    ) -> Result<String> {
+        // Ollama streams one JSON object per line.
+        let body = self.body(image, prompt, json_output, true);
+        let response = post_streaming(self.name(), Client::new().post(self.url()), &body).await?;
+        read_stream(
+            self.name(),
+            response,
+            |line| {
+                if line.is_empty() {
+                    return Ok(None);
+                }
+                let event = parse_stream_event(self.name(), line)?;
+                Ok(event["response"].as_str().map(str::to_string))
+            },
+            on_token,
+        )
+        .await
+    }
+}
+
+impl LocalVision {
+    fn body(&self, image: &VisionImage, prompt: &str, json_output: bool, stream: bool) -> serde_json::Value {
+        let mut body = json!({
+            "model": self.model,
+            "prompt": prompt,
+            "images": [image.to_base64()],
+            "stream": stream
+        });
+        if json_output {
+            body["format"] = json!("json");
+        }
+        body
+    }
+
+    fn url(&self) -> String {
+        format!("{}/api/generate", self.base_url.trim_end_matches('/'))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{body_partial_json, body_string_contains, header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn test_image() -> VisionImage {
+        VisionImage {
+            mime_type: "image/png".to_string(),
+            data: vec![0x89, b'P', b'N', b'G'],
+        }
+    }
+
+    #[test]
+    fn test_data_url_round_trip() {
+        let image = test_image();
+        assert_eq!(VisionImage::from_data_url(&image.to_data_url()), Some(image));
+        assert_eq!(VisionImage::from_data_url("https://example.com/a.png"), None);
+    }
+
+    #[tokio::test]
+    async fn test_gemini_vision() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/models/test-model:generateContent"))
+            .and(header(GEMINI_KEY_HEADER, "test-key"))
+            .and(body_partial_json(json!({
+                "generation_config": { "response_mime_type": "application/json" }
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "candidates": [{ "content": { "parts": [{ "text": " {\"ok\": true} " }] } }]
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let model = GeminiVision {
+            base_url: server.uri(),
+            api_key: "test-key".to_string(),
+            model: "test-model".to_string(),
+        };
+        let reply = model.generate(&test_image(), "describe", true).await.unwrap();
+        assert_eq!(reply, "{\"ok\": true}");
+    }
+
+    #[tokio::test]
+    async fn test_gemini_vision_reports_safety_blocks() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/models/blocked-prompt:generateContent"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "promptFeedback": { "blockReason": "SAFETY" }
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/models/blocked-reply:generateContent"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "candidates": [{ "finishReason": "SAFETY", "safetyRatings": [] }]
+            })))
+            .mount(&server)
+            .await;
+
+        for model in ["blocked-prompt", "blocked-reply"] {
+            let model = GeminiVision {
+                base_url: server.uri(),
+                api_key: "test-key".to_string(),
+                model: model.to_string(),
+            };
+            assert!(matches!(
+                model.generate(&test_image(), "describe", false).await,
+                Err(VisoLearnError::SafetyBlocked { reason, .. }) if reason == "SAFETY"
+            ));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_openai_vision() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/chat/completions"))
+            .and(header("authorization", "Bearer sk-test"))
+            .and(body_string_contains("data:image/png;base64,"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "choices": [{ "message": { "role": "assistant", "content": "A red ball." } }]
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let model = OpenAiVision {
+            base_url: server.uri(),
+            api_key: Some("sk-test".to_string()),
+            model: "gpt-test".to_string(),
+        };
+        let reply = model.generate(&test_image(), "describe", false).await.unwrap();
+        assert_eq!(reply, "A red ball.");
+    }
+
+    #[tokio::test]
+    async fn test_openai_vision_streams_tokens() {
+        let server = MockServer::start().await;
+        let events = [
+            r#"{"choices":[{"delta":{"role":"assistant"}}]}"#,
+            r#"{"choices":[{"delta":{"content":"A red"}}]}"#,
+            r#"{"choices":[{"delta":{"content":" ball."}}]}"#,
+            "[DONE]",
+        ];
+        let body: String = events.iter().map(|event| format!("data: {}\n\n", event)).collect();
+        Mock::given(method("POST"))
+            .and(path("/chat/completions"))
+            .and(body_partial_json(json!({ "stream": true })))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(body, "text/event-stream"))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let model = OpenAiVision {
+            base_url: server.uri(),
+            api_key: None,
+            model: "gpt-test".to_string(),
+        };
+        let tokens = std::sync::Mutex::new(Vec::new());
+        let reply = model
+            .generate_streaming(&test_image(), "describe", false, &|token| {
+                tokens.lock().unwrap().push(token.to_string())
+            })
+            .await
+            .unwrap();
+        assert_eq!(reply, "A red ball.");
+        assert_eq!(tokens.into_inner().unwrap(), ["A red", " ball."]);
+    }
+
+    #[tokio::test]
+    async fn test_local_vision() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/api/generate"))
+            .and(body_partial_json(json!({ "model": "llava", "stream": false, "format": "json" })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "response": "[\"red ball\"]" })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let model = LocalVision {
+            base_url: server.uri(),
+            model: "llava".to_string(),
+        };
+        let reply = model.generate(&test_image(), "details", true).await.unwrap();
+        assert_eq!(reply, "[\"red ball\"]");
+    }
+
+    #[tokio::test]
+    async fn test_http_errors_are_reported() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(429).set_body_string("quota exceeded"))
+            .mount(&server)
+            .await;
+
+        let model = LocalVision {
+            base_url: server.uri(),
+            model: "llava".to_string(),
+        };
+        let err = model.generate(&test_image(), "details", false).await.unwrap_err();
+        assert!(
+            matches!(&err, VisoLearnError::QuotaExceeded { service, detail } if service == "Local" && detail == "quota exceeded"),
+            "unexpected error: {}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_from_config_rejects_unknown_backend() {
+        let config = VisionConfig {
+            backend: "carrier-pigeon".to_string(),
+            model: None,
+            api_base: None,
+            api_key: None,
+        };
+        assert!(matches!(from_config(&config), Err(VisoLearnError::Config(_))));
+    }
+}
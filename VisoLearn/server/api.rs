@@ -0,0 +1,642 @@
+use std::sync::Arc;
+
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::extract::{Path, State};
+use axum::http::{header, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use serde_json::json;
+use tokio::sync::broadcast::error::RecvError;
+use tower_http::services::ServeDir;
+
+use crate::config;
+use crate::error::{Result, VisoLearnError};
+use crate::models::progression::{ProgressionEngine, ProgressionPolicy};
+use crate::utils::events::SessionEvent;
+use crate::utils::image_store::{detect_mime, ImageRef, ImageStore};
+use crate::utils::learner_db::LearnerDatabase;
+use crate::utils::session::{ChecklistItem, Session};
+use crate::utils::session_store::SessionStore;
+use crate::utils::state_management::Backends;
+
+use super::state::AppState;
+use super::types::{Attempts, CreateSessionRequest, DescriptionRequest, HintResponse, ImportRequest, Progress, SaveResult, SessionStatus};
+
+// JSON endpoints for the practice loop. A client creates a session with the learner's settings,
+// asks for an image, then posts the child's descriptions (or "hint" requests) until it is told
+// a new image has been generated. Every write returns the full session status, so a client never
+// needs a second request to redraw.
+//
+//   POST   /sessions                    create a session            -> SessionStatus
+//   POST   /imports                     resume a saved log or       -> SessionStatus
+//                                       bundle from {"file": ...}
+//   GET    /sessions/:id                status                      -> SessionStatus
+//   DELETE /sessions/:id                end the session
+//   POST   /sessions/:id/image          generate a new image, with  -> SessionStatus
+//                                       optional new settings
+//   POST   /sessions/:id/descriptions   submit {"message": ...}     -> SessionStatus
+//   POST   /sessions/:id/hints          ask for the next hint       -> HintResponse
+//   GET    /sessions/:id/checklist                                  -> [ChecklistItem]
+//   GET    /sessions/:id/progress                                   -> Progress
+//   GET    /sessions/:id/attempts                                   -> Attempts
+//   GET    /sessions/:id/history        finished sessions + active  -> [Session]
+//   POST   /sessions/:id/saved-images   save every image to disk    -> SaveResult
+//   POST   /sessions/:id/saved-logs     save the session log        -> SaveResult
+//   POST   /sessions/:id/saved-bundles  save a .visolearn bundle    -> SaveResult
+//   GET    /sessions/:id/events         WebSocket of SessionEvent JSON messages
+//   GET    /images/:hash                the image bytes
+//
+// The events socket lets a client redraw while a request is still running: each stage of image
+// generation, the evaluation as the model writes it, and difficulty changes arrive as they happen.
+
+pub fn router(state: Arc<AppState>) -> Router {
+    Router::new()
+        .route("/sessions", post(create_session))
+        .route("/imports", post(import_session))
+        .route("/sessions/:id", get(get_session).delete(end_session))
+        .route("/sessions/:id/image", post(generate_image))
+        .route("/sessions/:id/descriptions", post(submit_description))
+        .route("/sessions/:id/hints", post(request_hint))
+        .route("/sessions/:id/checklist", get(get_checklist))
+        .route("/sessions/:id/progress", get(get_progress))
+        .route("/sessions/:id/attempts", get(get_attempts))
+        .route("/sessions/:id/history", get(get_history))
+        .route("/sessions/:id/saved-images", post(save_images))
+        .route("/sessions/:id/saved-logs", post(save_log))
+        .route("/sessions/:id/saved-bundles", post(save_bundle))
+        .route("/sessions/:id/events", get(session_events))
+        .route("/images/:hash", get(get_image))
+        .with_state(state)
+}
+
+/// Builds the backends, image store and progression engine from the environment, clears out
+/// unused images, and serves the API, and the web UI from `config::ui_dir()`, on `addr` until the
+/// process is stopped.
+pub async fn serve(addr: &str) -> Result<()> {
+    let backends = Backends::from_env()?;
+    let engine = ProgressionEngine::new(ProgressionPolicy::from_name(&config::progression_policy())?);
+    let store = SessionStore::with_images(ImageStore::open(config::image_store_dir())?);
+    let state = Arc::new(AppState::new(backends, store, engine));
+    // Images of sessions that ended before the last shutdown are no longer needed.
+    let report = state.collect_garbage(&LearnerDatabase::open(config::learner_db_path())?).await?;
+    log::info!("Removed {} unused images ({} bytes)", report.removed, report.bytes_freed);
+    // Anything that isn't an API route is a file from the built web UI.
+    let app = router(state).fallback_service(ServeDir::new(config::ui_dir()));
+
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    log::info!("VisoLearn API listening on http://{}", listener.local_addr()?);
+    axum::serve(listener, app).await?;
+    Ok(())
+}
+
+/// A `VisoLearnError` returned from a handler, sent as `{"error": <user message>}`. The full error
+/// can name backend URLs, file paths and database messages, so it is only logged.
+#[derive(Debug)]
+pub struct ApiError(pub VisoLearnError);
+
+impl From<VisoLearnError> for ApiError {
+    fn from(e: VisoLearnError) -> Self {
+        ApiError(e)
+    }
+}
+
+impl ApiError {
+    pub fn status(&self) -> StatusCode {
+        match &self.0 {
+            VisoLearnError::NotFound(_) => StatusCode::NOT_FOUND,
+            VisoLearnError::InvalidValue { .. } | VisoLearnError::NoImage => StatusCode::BAD_REQUEST,
+            VisoLearnError::SafetyBlocked { .. } => StatusCode::UNPROCESSABLE_ENTITY,
+            VisoLearnError::QuotaExceeded { .. } => StatusCode::TOO_MANY_REQUESTS,
+            VisoLearnError::Http(_) | VisoLearnError::Api { .. } | VisoLearnError::InvalidResponse { .. } => {
+                StatusCode::BAD_GATEWAY
+            }
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        log::error!("Request failed: {}", self.0);
+        let body = json!({ "error": self.0.user_message() });
+        (self.status(), Json(body)).into_response()
+    }
+}
+
+type ApiResult<T> = std::result::Result<T, ApiError>;
+
+async fn create_session(
+    State(state): State<Arc<AppState>>,
+    Json(request): Json<CreateSessionRequest>,
+) -> ApiResult<(StatusCode, Json<SessionStatus>)> {
+    Ok((StatusCode::CREATED, Json(state.create_session(request)?)))
+}
+
+async fn import_session(
+    State(state): State<Arc<AppState>>,
+    Json(request): Json<ImportRequest>,
+) -> ApiResult<(StatusCode, Json<SessionStatus>)> {
+    Ok((StatusCode::CREATED, Json(state.import(&request.file)?)))
+}
+
+async fn get_session(State(state): State<Arc<AppState>>, Path(id): Path<String>) -> ApiResult<Json<SessionStatus>> {
+    Ok(Json(state.status(&id).await?))
+}
+
+async fn end_session(State(state): State<Arc<AppState>>, Path(id): Path<String>) -> ApiResult<StatusCode> {
+    state.remove(&id)?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn generate_image(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+    settings: Option<Json<CreateSessionRequest>>,
+) -> ApiResult<Json<SessionStatus>> {
+    let settings = settings.map(|Json(settings)| settings);
+    Ok(Json(state.generate_image(&id, settings).await?))
+}
+
+async fn submit_description(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+    Json(request): Json<DescriptionRequest>,
+) -> ApiResult<Json<SessionStatus>> {
+    Ok(Json(state.describe(&id, &request.message).await?))
+}
+
+async fn request_hint(State(state): State<Arc<AppState>>, Path(id): Path<String>) -> ApiResult<Json<HintResponse>> {
+    Ok(Json(state.hint(&id).await?))
+}
+
+async fn get_checklist(State(state): State<Arc<AppState>>, Path(id): Path<String>) -> ApiResult<Json<Vec<ChecklistItem>>> {
+    let practice = state.get(&id)?;
+    let practice = practice.lock().await;
+    Ok(Json(practice.checklist.clone()))
+}
+
+async fn get_progress(State(state): State<Arc<AppState>>, Path(id): Path<String>) -> ApiResult<Json<Progress>> {
+    let practice = state.get(&id)?;
+    let practice = practice.lock().await;
+    Ok(Json(Progress::new(&practice.checklist, &practice.active)))
+}
+
+async fn get_attempts(State(state): State<Arc<AppState>>, Path(id): Path<String>) -> ApiResult<Json<Attempts>> {
+    let practice = state.get(&id)?;
+    let practice = practice.lock().await;
+    Ok(Json(Attempts::new(&practice.active)))
+}
+
+async fn get_history(State(state): State<Arc<AppState>>, Path(id): Path<String>) -> ApiResult<Json<Vec<Session>>> {
+    Ok(Json(state.practice(&id).await?.history()))
+}
+
+async fn save_images(State(state): State<Arc<AppState>>, Path(id): Path<String>) -> ApiResult<Json<SaveResult>> {
+    Ok(Json(state.save_images(&id).await?))
+}
+
+async fn save_log(State(state): State<Arc<AppState>>, Path(id): Path<String>) -> ApiResult<Json<SaveResult>> {
+    Ok(Json(state.save_log(&id).await?))
+}
+
+async fn save_bundle(State(state): State<Arc<AppState>>, Path(id): Path<String>) -> ApiResult<Json<SaveResult>> {
+    Ok(Json(state.save_bundle(&id).await?))
+}
+
+async fn session_events(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+    upgrade: WebSocketUpgrade,
+) -> ApiResult<Response> {
+    state.get(&id)?;
+    let events = state.store.events().subscribe(&id)?;
+    Ok(upgrade.on_upgrade(move |socket| forward_events(socket, events)))
+}
+
+// Sends each event as a JSON text message until the client disconnects or the session ends.
+async fn forward_events(mut socket: WebSocket, mut events: tokio::sync::broadcast::Receiver<SessionEvent>) {
+    loop {
+        tokio::select! {
+            event = events.recv() => {
+                let event = match event {
+                    Ok(event) => event,
+                    // The client missed some events; the next status request catches it up.
+                    Err(RecvError::Lagged(skipped)) => {
+                        log::warn!("Events client fell behind and skipped {} events", skipped);
+                        continue;
+                    }
+                    Err(RecvError::Closed) => break,
+                };
+                let Ok(text) = serde_json::to_string(&event) else {
+                    continue;
+                };
+                if socket.send(Message::Text(text)).await.is_err() {
+                    break;
+                }
+            }
+            message = socket.recv() => match message {
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                Some(Ok(_)) => {}
+            },
+        }
+    }
+}
+
+async fn get_image(State(state): State<Arc<AppState>>, Path(hash): Path<String>) -> ApiResult<Response> {
+    // Only well-formed hashes reach the store, so a request can never name another file.
+    if hash.len() != 64 || !hash.bytes().all(|b| b.is_ascii_hexdigit() && !b.is_ascii_uppercase()) {
+        return Err(VisoLearnError::NotFound(format!("image {}", hash)).into());
+    }
+    let image = ImageRef {
+        hash: hash.clone(),
+        mime_type: String::new(),
+    };
+    let bytes = match state.store.images().load(&image) {
+        Ok(bytes) => bytes,
+        Err(VisoLearnError::NoImage) => return Err(VisoLearnError::NotFound(format!("image {}", hash)).into()),
+        Err(e) => return Err(e.into()),
+    };
+    let mime_type = detect_mime(&bytes).unwrap_or("application/octet-stream");
+    Ok((
+        [
+            (header::CONTENT_TYPE, mime_type),
+            // Content-addressed, so an image at a given URL never changes.
+            (header::CACHE_CONTROL, "public, max-age=31536000, immutable"),
+        ],
+        bytes.to_vec(),
+    )
+        .into_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+    use axum::body::{to_bytes, Body};
+    use axum::http::Request;
+    use serde_json::Value;
+    use std::fs;
+    use tower::ServiceExt;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    use crate::error::Result;
+    use crate::models::image_generation::{ImageGenerationConfig, PlaceholderGenerator};
+    use crate::models::progression::{ProgressionEngine, ProgressionPolicy};
+    use crate::models::prompt_generation::{GenerativeModel, TextProvider};
+    use crate::models::vision::{VisionImage, VisionModel};
+    use crate::utils::bundle::{checklist_from_session, export_bundle, SessionBundle, BUNDLE_EXTENSION};
+    use crate::utils::file_operations::write_session_log;
+    use crate::utils::learner_profile::LearnerProfile;
+    use crate::utils::session_store::{new_session_id, SessionStore};
+    use crate::utils::state_management::Backends;
+
+    /// Answers each of the practice loop's vision questions with a fixed reply.
+    struct ScriptedVision;
+
+    #[async_trait]
+    impl VisionModel for ScriptedVision {
+        fn name(&self) -> &str {
+            "Scripted"
+        }
+
+        async fn generate(&self, _image: &VisionImage, prompt: &str, json_output: bool) -> Result<String> {
+            Ok(if prompt.contains("JSON array of strings") {
+                r#"["red ball", "green tree"]"#.to_string()
+            } else if json_output {
+                json!({
+                    "feedback": "Yes, there is a red ball!",
+                    "matched_details": [{ "index": 1, "confidence": 0.9 }],
+                    // Ignored: only the checklist and attempts decide when to move on.
+                    "should_advance": true
+                })
+                .to_string()
+            } else {
+                "A red ball under a green tree.".to_string()
+            })
+        }
+    }
+
+    /// Like `ScriptedVision`, but with no usable list of key details.
+    struct NoDetailsVision;
+
+    #[async_trait]
+    impl VisionModel for NoDetailsVision {
+        fn name(&self) -> &str {
+            "No details"
+        }
+
+        async fn generate(&self, image: &VisionImage, prompt: &str, json_output: bool) -> Result<String> {
+            if prompt.contains("JSON array of strings") {
+                return Ok("I can't tell what is in this picture.".to_string());
+            }
+            ScriptedVision.generate(image, prompt, json_output).await
+        }
+    }
+
+    fn scripted_backends(text_server: &MockServer) -> Backends {
+        Backends {
+            text: GenerativeModel::new(TextProvider::OpenAi, "gpt-test", &text_server.uri(), None),
+            vision: Box::new(ScriptedVision),
+            images: Box::new(PlaceholderGenerator),
+            image_config: ImageGenerationConfig::default(),
+        }
+    }
+
+    fn app_state(text_server: &MockServer) -> Arc<AppState> {
+        let engine = ProgressionEngine::new(ProgressionPolicy::default());
+        Arc::new(AppState::new(scripted_backends(text_server), SessionStore::new(), engine))
+    }
+
+    async fn app(text_server: &MockServer) -> Router {
+        router(app_state(text_server))
+    }
+
+    async fn prompt_server() -> MockServer {
+        let text_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/chat/completions"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "choices": [{ "message": { "content": "A red ball under a green tree." }, "finish_reason": "stop" }]
+            })))
+            .mount(&text_server)
+            .await;
+        text_server
+    }
+
+    async fn call(app: &Router, method: &str, uri: &str, body: Option<Value>) -> (StatusCode, Value) {
+        let request = Request::builder()
+            .method(method)
+            .uri(uri)
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(body.map_or_else(Body::empty, |body| Body::from(body.to_string())))
+            .unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        let status = response.status();
+        let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, serde_json::from_slice(&bytes).unwrap_or(Value::Null))
+    }
+
+    #[tokio::test]
+    async fn test_practice_loop_over_http() {
+        let app = app(&prompt_server().await).await;
+
+        let (status, created) = call(
+            &app,
+            "POST",
+            "/sessions",
+            Some(json!({ "age": "6", "support_level": "Level 2", "topic_focus": "Toys", "attempt_limit": 4 })),
+        )
+        .await;
+        assert_eq!(status, StatusCode::CREATED);
+        let id = created["session_id"].as_str().unwrap().to_string();
+        assert_eq!(created["attempts"]["limit"], 4);
+
+        // Hints need an image first.
+        let (status, error) = call(&app, "POST", &format!("/sessions/{}/hints", id), None).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(error["error"].as_str().unwrap().contains("No image"));
+        assert_eq!(error.as_object().unwrap().len(), 1, "only the user message is sent: {}", error);
+
+        // Settings sent with the image request apply from this image on.
+        let (status, generated) = call(
+            &app,
+            "POST",
+            &format!("/sessions/{}/image", id),
+            Some(json!({ "topic_focus": "Animals" })),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK, "{}", generated);
+        assert_eq!(generated["checklist"].as_array().unwrap().len(), 2);
+        let (_, history) = call(&app, "GET", &format!("/sessions/{}/history", id), None).await;
+        assert_eq!(history.as_array().unwrap().len(), 1);
+        assert_eq!(history[0]["topic_focus"], "Animals");
+        assert_eq!(history[0]["attempt_limit"], 4);
+        let image_url = generated["image"]["url"].as_str().unwrap().to_string();
+
+        let response = app
+            .clone()
+            .oneshot(Request::builder().uri(&image_url).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "image/png");
+
+        let (status, described) = call(
+            &app,
+            "POST",
+            &format!("/sessions/{}/descriptions", id),
+            Some(json!({ "message": "a red ball" })),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK, "{}", described);
+        assert_eq!(described["progress"]["identified"], 1);
+        assert_eq!(described["chat"][0], json!({ "speaker": "Child", "text": "a red ball" }));
+
+        let (_, hinted) = call(&app, "POST", &format!("/sessions/{}/hints", id), None).await;
+        assert_eq!(hinted["hint"]["detail"], "green tree");
+        assert_eq!(hinted["status"]["attempts"]["used"], 0);
+
+        let (_, progress) = call(&app, "GET", &format!("/sessions/{}/progress", id), None).await;
+        assert_eq!(progress, json!({ "identified": 1, "total": 2, "needed": 2, "threshold_reached": false }));
+
+        let (status, _) = call(&app, "DELETE", &format!("/sessions/{}", id), None).await;
+        assert_eq!(status, StatusCode::NO_CONTENT);
+        let (status, _) = call(&app, "GET", &format!("/sessions/{}", id), None).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_publishes_session_events() {
+        let state = app_state(&prompt_server().await);
+        let app = router(Arc::clone(&state));
+        let (_, created) = call(&app, "POST", "/sessions", Some(json!({}))).await;
+        let id = created["session_id"].as_str().unwrap().to_string();
+        let mut events = state.store.events().subscribe(&id).unwrap();
+
+        call(&app, "POST", &format!("/sessions/{}/image", id), None).await;
+        call(&app, "POST", &format!("/sessions/{}/descriptions", id), Some(json!({ "message": "a red ball" }))).await;
+
+        let mut kinds = Vec::new();
+        while let Ok(event) = events.try_recv() {
+            kinds.push(serde_json::to_value(event).unwrap()["type"].as_str().unwrap().to_string());
+        }
+        assert_eq!(
+            kinds,
+            [
+                "generation_started",
+                "prompt_ready",
+                "image_ready",
+                "description_ready",
+                "checklist_ready",
+                "evaluation_token",
+                "evaluation_done",
+            ]
+        );
+
+        // Ending the session closes the channel.
+        call(&app, "DELETE", &format!("/sessions/{}", id), None).await;
+        assert!(matches!(
+            events.try_recv(),
+            Err(tokio::sync::broadcast::error::TryRecvError::Closed)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_failed_image_leaves_the_store_alone() {
+        let text_server = prompt_server().await;
+        let mut backends = scripted_backends(&text_server);
+        backends.vision = Box::new(NoDetailsVision);
+        let engine = ProgressionEngine::new(ProgressionPolicy::default());
+        let state = Arc::new(AppState::new(backends, SessionStore::new(), engine));
+        let app = router(Arc::clone(&state));
+        let (_, created) = call(&app, "POST", "/sessions", Some(json!({}))).await;
+        let id = created["session_id"].as_str().unwrap().to_string();
+
+        let (status, _) = call(&app, "POST", &format!("/sessions/{}/image", id), None).await;
+        assert_ne!(status, StatusCode::OK);
+        let assets = state.store.get(&id).unwrap().unwrap_or_default();
+        assert!(assets.image.is_none() && assets.description.is_none());
+        let (_, status) = call(&app, "GET", &format!("/sessions/{}", id), None).await;
+        assert!(status["image"].is_null(), "{}", status);
+    }
+
+    #[tokio::test]
+    async fn test_imports_a_saved_log() {
+        let save_dir = std::env::temp_dir().join(format!("visolearn-saves-{}", new_session_id()));
+        fs::create_dir_all(&save_dir).unwrap();
+        let engine = ProgressionEngine::new(ProgressionPolicy::default());
+        let mut state = AppState::new(scripted_backends(&MockServer::start().await), SessionStore::new(), engine);
+        state.save_dir = save_dir.clone();
+        let app = router(Arc::new(state));
+        let path = save_dir.join("session_log_20240101_120000.json");
+        let finished = Session {
+            prompt: Some("A cat on a mat".to_string()),
+            completed: true,
+            ..Session::default()
+        };
+        let active = Session {
+            prompt: Some("A dog in the rain".to_string()),
+            key_details: vec!["dog".to_string(), "rain".to_string()],
+            identified_details: vec!["dog".to_string()],
+            ..Session::default()
+        };
+        write_session_log(&path, &[finished], Some(&active)).unwrap();
+
+        let (status, imported) =
+            call(&app, "POST", "/imports", Some(json!({ "file": "session_log_20240101_120000.json" }))).await;
+        assert_eq!(status, StatusCode::CREATED, "{}", imported);
+        assert_eq!(imported["prompt"], "A dog in the rain");
+        assert_eq!(imported["progress"]["identified"], 1);
+        assert_ne!(imported["session_id"], active.id.as_str());
+        let id = imported["session_id"].as_str().unwrap();
+        let (_, history) = call(&app, "GET", &format!("/sessions/{}/history", id), None).await;
+        assert_eq!(history.as_array().unwrap().len(), 2);
+        fs::remove_file(&path).unwrap();
+
+        let (status, _) = call(&app, "POST", "/imports", Some(json!({ "file": "session_log_20240101_120000.json" }))).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        // Nothing outside the save folder can be named.
+        for file in ["../secret.json", "/etc/passwd", "saved/log.json", ".."] {
+            let (status, _) = call(&app, "POST", "/imports", Some(json!({ "file": file }))).await;
+            assert_eq!(status, StatusCode::BAD_REQUEST, "{}", file);
+        }
+
+        // A bundle keeps the active session's checklist as it was.
+        let bundle_name = format!("session_20240101_120000.{}", BUNDLE_EXTENSION);
+        let path = save_dir.join(&bundle_name);
+        let mut checklist = checklist_from_session(&active);
+        checklist[1].match_score = 0.5;
+        export_bundle(&path, &SessionBundle::new(&[], Some(&active), &checklist), &ImageStore::in_memory()).unwrap();
+        let (status, imported) = call(&app, "POST", "/imports", Some(json!({ "file": bundle_name }))).await;
+        assert_eq!(status, StatusCode::CREATED, "{}", imported);
+        assert_eq!(imported["prompt"], "A dog in the rain");
+        assert_eq!(imported["checklist"][1]["match_score"], 0.5);
+        fs::remove_dir_all(&save_dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_saves_can_be_imported_and_report_failures() {
+        let save_dir = std::env::temp_dir().join(format!("visolearn-saves-{}", new_session_id()));
+        let engine = ProgressionEngine::new(ProgressionPolicy::default());
+        let mut state = AppState::new(scripted_backends(&prompt_server().await), SessionStore::new(), engine);
+        state.save_dir = save_dir.clone();
+        let app = router(Arc::new(state));
+        let (_, created) = call(&app, "POST", "/sessions", Some(json!({}))).await;
+        let id = created["session_id"].as_str().unwrap().to_string();
+        call(&app, "POST", &format!("/sessions/{}/image", id), None).await;
+
+        let (status, saved) = call(&app, "POST", &format!("/sessions/{}/saved-bundles", id), None).await;
+        assert_eq!(status, StatusCode::OK, "{}", saved);
+        let (status, imported) = call(&app, "POST", "/imports", Some(json!({ "file": saved["file"] }))).await;
+        assert_eq!(status, StatusCode::CREATED, "{}", imported);
+        let (_, checklist) = call(&app, "GET", &format!("/sessions/{}/checklist", id), None).await;
+        assert_eq!(imported["checklist"], checklist);
+
+        // A save folder that cannot be created fails the request instead of reporting "❌".
+        fs::remove_dir_all(&save_dir).unwrap();
+        fs::write(&save_dir, "not a folder").unwrap();
+        let (status, error) = call(&app, "POST", &format!("/sessions/{}/saved-logs", id), None).await;
+        assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR, "{}", error);
+        assert!(error["error"].as_str().unwrap().contains("file"), "{}", error);
+        fs::remove_file(&save_dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_garbage_collection_keeps_used_images() {
+        let state = app_state(&prompt_server().await);
+        let app = router(Arc::clone(&state));
+        let (_, created) = call(&app, "POST", "/sessions", Some(json!({}))).await;
+        let id = created["session_id"].as_str().unwrap().to_string();
+        let (_, generated) = call(&app, "POST", &format!("/sessions/{}/image", id), None).await;
+        let live = generated["image"]["hash"].as_str().unwrap().to_string();
+
+        let images = state.store.images();
+        let png = |width| {
+            let mut bytes = Vec::new();
+            image::DynamicImage::new_rgb8(width, 1)
+                .write_to(&mut std::io::Cursor::new(&mut bytes), image::ImageOutputFormat::Png)
+                .unwrap();
+            bytes
+        };
+        let stored = images.put(png(5)).unwrap();
+        let orphan = images.put(png(6)).unwrap();
+        let db = LearnerDatabase::open_in_memory().unwrap();
+        let profile = LearnerProfile::new("Sam");
+        db.save_profile(&profile).unwrap();
+        let played = Session {
+            image: Some(stored.clone()),
+            ..profile.new_session()
+        };
+        db.save_program(&profile.id, &[played], None).unwrap();
+
+        let report = state.collect_garbage(&db).await.unwrap();
+        assert_eq!(report.removed, 1);
+        assert!(!images.contains(&orphan).unwrap());
+        assert!(images.contains(&stored).unwrap());
+        assert_eq!(call(&app, "GET", &format!("/images/{}", live), None).await.0, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_rejects_bad_requests() {
+        let app = app(&MockServer::start().await).await;
+
+        let (status, error) = call(&app, "POST", "/sessions", Some(json!({ "support_level": "Level 9" }))).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY, "{}", error);
+
+        let (status, _) = call(&app, "GET", "/images/..%2F..%2Fetc%2Fpasswd", None).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        let (_, created) = call(&app, "POST", "/sessions", Some(json!({}))).await;
+        let id = created["session_id"].as_str().unwrap();
+        let (status, _) = call(
+            &app,
+            "POST",
+            &format!("/sessions/{}/descriptions", id),
+            Some(json!({ "message": "  " })),
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+}
@@ -0,0 +1,12 @@
+// Headless HTTP server for the practice loop. Only the request and response types build for
+// the browser, where the web UI uses them.
+#[cfg(not(target_arch = "wasm32"))]
+pub mod api;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod state;
+pub mod types;
+
+#[cfg(not(target_arch = "wasm32"))]
+pub use api::{router, serve};
+#[cfg(not(target_arch = "wasm32"))]
+pub use state::AppState;
@@ -0,0 +1,318 @@
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use tokio::sync::Mutex as AsyncMutex;
+
+use crate::config;
+use crate::error::{Result, VisoLearnError};
+use crate::models::progression::ProgressionEngine;
+use crate::utils::bundle::{checklist_from_session, import_bundle, save_session_bundle, BUNDLE_EXTENSION};
+use crate::utils::file_operations::{load_project, next_session, save_all_session_images, save_session_log};
+use crate::utils::image_store::GcReport;
+use crate::utils::learner_db::LearnerDatabase;
+use crate::utils::session::{ChecklistItem, Session};
+use crate::utils::session_store::{new_session_id, SessionStore};
+use crate::utils::state_management::{chat_respond, generate_image_and_reset_chat, Backends};
+
+use super::types::{Attempts, ChatMessage, CreateSessionRequest, HintResponse, ImageInfo, Progress, SaveResult, SessionStatus};
+
+/// Everything the practice loop keeps between requests for one learner: the same values
+/// `chat_respond` takes and returns.
+#[derive(Debug, Clone, Default)]
+pub struct Practice {
+    pub active: Session,
+    pub saved: Vec<Session>,
+    pub checklist: Vec<ChecklistItem>,
+}
+
+impl Practice {
+    /// The finished sessions followed by the active one, if it has an image yet.
+    pub fn history(&self) -> Vec<Session> {
+        let mut sessions = self.saved.clone();
+        if self.active.prompt.is_some() {
+            sessions.push(self.active.clone());
+        }
+        sessions
+    }
+}
+
+impl From<&Practice> for SessionStatus {
+    fn from(practice: &Practice) -> Self {
+        let session = &practice.active;
+        Self {
+            session_id: session.id.clone(),
+            prompt: session.prompt.clone(),
+            image: session.image.as_ref().map(ImageInfo::from),
+            difficulty: session.difficulty,
+            difficulty_reason: session.difficulty_reason.clone(),
+            chat: session
+                .chat
+                .iter()
+                .map(|(speaker, text)| ChatMessage {
+                    speaker: speaker.clone(),
+                    text: text.clone(),
+                })
+                .collect(),
+            checklist: practice.checklist.clone(),
+            progress: Progress::new(&practice.checklist, session),
+            attempts: Attempts::new(session),
+            completed_sessions: practice.saved.iter().filter(|s| s.completed).count(),
+        }
+    }
+}
+
+/// Shared by every request. Each practice has its own lock, held for the whole of a request,
+/// so two requests for the same session run one after the other while different sessions
+/// never wait on each other's model calls.
+///
+/// The methods below are the whole practice loop; the HTTP handlers and the desktop app both
+/// call them, so a session behaves the same whichever one drives it.
+pub struct AppState {
+    pub backends: Backends,
+    pub store: SessionStore,
+    pub engine: ProgressionEngine,
+    /// Where the saves below write, and the only folder `import` reads from.
+    pub save_dir: PathBuf,
+    practices: Mutex<HashMap<String, Arc<AsyncMutex<Practice>>>>,
+}
+
+impl AppState {
+    pub fn new(backends: Backends, store: SessionStore, engine: ProgressionEngine) -> Self {
+        Self {
+            backends,
+            store,
+            engine,
+            save_dir: PathBuf::from(config::save_dir()),
+            practices: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Starts tracking `session` and returns its id.
+    pub fn insert(&self, session: Session) -> Result<String> {
+        self.insert_practice(Practice {
+            active: session,
+            ..Practice::default()
+        })
+    }
+
+    fn insert_practice(&self, practice: Practice) -> Result<String> {
+        let id = practice.active.id.clone();
+        self.practices.lock()?.insert(id.clone(), Arc::new(AsyncMutex::new(practice)));
+        Ok(id)
+    }
+
+    pub fn get(&self, session_id: &str) -> Result<Arc<AsyncMutex<Practice>>> {
+        self.practices
+            .lock()?
+            .get(session_id)
+            .cloned()
+            .ok_or_else(|| VisoLearnError::NotFound(format!("session {}", session_id)))
+    }
+
+    /// Stops tracking a session, dropping its generated assets too.
+    pub fn remove(&self, session_id: &str) -> Result<()> {
+        self.practices
+            .lock()?
+            .remove(session_id)
+            .ok_or_else(|| VisoLearnError::NotFound(format!("session {}", session_id)))?;
+        self.store.remove(session_id)?;
+        Ok(())
+    }
+
+    pub fn create_session(&self, settings: CreateSessionRequest) -> Result<SessionStatus> {
+        let practice = Practice {
+            active: settings.into_session(),
+            ..Practice::default()
+        };
+        let status = SessionStatus::from(&practice);
+        self.insert_practice(practice)?;
+        Ok(status)
+    }
+
+    /// Picks up a stored program where it was left, rebuilding the active checklist.
+    pub fn resume(&self, active: Session, saved: Vec<Session>) -> Result<SessionStatus> {
+        let practice = Practice {
+            checklist: checklist_from_session(&active),
+            active,
+            saved,
+        };
+        let status = SessionStatus::from(&practice);
+        self.insert_practice(practice)?;
+        Ok(status)
+    }
+
+    /// Resumes a practice saved by `save_log` or `save_bundle`, read from the file `name` in
+    /// `save_dir`. A `.visolearn` bundle brings its images and checklists with it. The practice
+    /// gets a new id, so importing a file twice, or while its session is still open, starts a
+    /// separate practice.
+    pub fn import(&self, name: &str) -> Result<SessionStatus> {
+        // Only a bare file name, so a client cannot reach anything outside the save folder.
+        let mut components = Path::new(name).components();
+        if !matches!((components.next(), components.next()), (Some(Component::Normal(_)), None)) {
+            return Err(VisoLearnError::InvalidValue {
+                kind: "saved file name",
+                value: name.to_string(),
+                expected: "the name of a session log or bundle in the save folder".to_string(),
+            });
+        }
+        let path = self.save_dir.join(name);
+        if !path.is_file() {
+            return Err(VisoLearnError::NotFound(format!("saved file {}", name)));
+        }
+        let (saved, active, checklist) = if path.extension().is_some_and(|extension| extension == BUNDLE_EXTENSION) {
+            let (_, mut bundle) = import_bundle(&path, self.store.images())?;
+            match bundle.active_session.take() {
+                // The active session's checklist is the bundle's last.
+                Some(active) => (bundle.saved_sessions, active, bundle.checklists.pop().unwrap_or_default()),
+                None => {
+                    let active = next_session(&bundle.saved_sessions);
+                    (bundle.saved_sessions, active, Vec::new())
+                }
+            }
+        } else {
+            let (saved, active) = load_project(&path, self.store.images())?;
+            let checklist = checklist_from_session(&active);
+            (saved, active, checklist)
+        };
+        let practice = Practice {
+            active: Session {
+                id: new_session_id(),
+                ..active
+            },
+            saved,
+            checklist,
+        };
+        let status = SessionStatus::from(&practice);
+        self.insert_practice(practice)?;
+        Ok(status)
+    }
+
+    /// Deletes every stored image that neither a learner in `db` nor a practice open here still
+    /// uses. Sessions saved only as a session log lose their images, which `load_session_log`
+    /// re-links from the log's `saved_images_*` folder.
+    pub async fn collect_garbage(&self, db: &LearnerDatabase) -> Result<GcReport> {
+        let mut referenced = db.referenced_images()?;
+        referenced.extend(self.store.referenced_images()?);
+        let practices: Vec<_> = self.practices.lock()?.values().cloned().collect();
+        for practice in practices {
+            let practice = practice.lock().await;
+            let sessions = practice.saved.iter().chain([&practice.active]);
+            referenced.extend(sessions.filter_map(|session| session.image.as_ref()).map(|image| image.hash.clone()));
+        }
+        self.store.images().collect_garbage(&referenced)
+    }
+
+    /// A copy of the session's current state.
+    pub async fn practice(&self, session_id: &str) -> Result<Practice> {
+        Ok(self.get(session_id)?.lock().await.clone())
+    }
+
+    pub async fn status(&self, session_id: &str) -> Result<SessionStatus> {
+        Ok(SessionStatus::from(&*self.get(session_id)?.lock().await))
+    }
+
+    /// Generates the next image, first applying `settings` if given.
+    pub async fn generate_image(&self, session_id: &str, settings: Option<CreateSessionRequest>) -> Result<SessionStatus> {
+        let practice = self.get(session_id)?;
+        let mut practice = practice.lock().await;
+        let mut active = practice.active.clone();
+        if let Some(settings) = settings {
+            active = settings.apply_to(active);
+        }
+        let settings = active.settings();
+        let (_, new_active, new_saved, checklist) =
+            generate_image_and_reset_chat(&self.backends, &self.store, &settings, active, &practice.saved).await?;
+        *practice = Practice {
+            active: new_active,
+            saved: new_saved,
+            checklist,
+        };
+        Ok(SessionStatus::from(&*practice))
+    }
+
+    /// Evaluates one of the child's descriptions.
+    pub async fn describe(&self, session_id: &str, message: &str) -> Result<SessionStatus> {
+        if message.trim().is_empty() {
+            return Err(VisoLearnError::InvalidValue {
+                kind: "description",
+                value: message.to_string(),
+                expected: "a non-empty message".to_string(),
+            });
+        }
+        let practice = self.get(session_id)?;
+        let mut practice = practice.lock().await;
+        self.respond(&mut practice, message).await?;
+        Ok(SessionStatus::from(&*practice))
+    }
+
+    pub async fn hint(&self, session_id: &str) -> Result<HintResponse> {
+        let practice = self.get(session_id)?;
+        let mut practice = practice.lock().await;
+        if practice.active.image.is_none() {
+            return Err(VisoLearnError::NoImage);
+        }
+        let hints_before = practice.active.used_hints.len();
+        self.respond(&mut practice, "hint").await?;
+        let hint = practice.active.used_hints.get(hints_before).cloned();
+        Ok(HintResponse {
+            hint,
+            status: SessionStatus::from(&*practice),
+        })
+    }
+
+    // Runs one turn of `chat_respond` and stores the result.
+    async fn respond(&self, practice: &mut Practice, message: &str) -> Result<()> {
+        let (_, _, saved, active, checklist, _) = chat_respond(
+            &self.backends,
+            &self.store,
+            &self.engine,
+            message,
+            practice.active.clone(),
+            practice.saved.clone(),
+            practice.checklist.clone(),
+        )
+        .await?;
+        *practice = Practice { active, saved, checklist };
+        Ok(())
+    }
+
+    pub async fn save_images(&self, session_id: &str) -> Result<SaveResult> {
+        let practice = self.get(session_id)?;
+        let practice = practice.lock().await;
+        let (folder, count) = save_all_session_images(self.store.images(), &self.save_dir, &practice.saved, &practice.active)?;
+        let file = saved_name(&folder);
+        Ok(SaveResult {
+            status: format!("✅ Successfully saved {} images to folder: {}", count, file),
+            file,
+        })
+    }
+
+    pub async fn save_log(&self, session_id: &str) -> Result<SaveResult> {
+        let practice = self.get(session_id)?;
+        let practice = practice.lock().await;
+        let file = saved_name(&save_session_log(&self.save_dir, &practice.saved, &practice.active)?);
+        Ok(SaveResult {
+            status: format!("✅ Session log saved to: {}", file),
+            file,
+        })
+    }
+
+    pub async fn save_bundle(&self, session_id: &str) -> Result<SaveResult> {
+        let practice = self.get(session_id)?;
+        let practice = practice.lock().await;
+        let (path, manifest) =
+            save_session_bundle(self.store.images(), &self.save_dir, &practice.saved, &practice.active, &practice.checklist)?;
+        let file = saved_name(&path);
+        Ok(SaveResult {
+            status: format!("✅ Saved {} sessions and {} images to: {}", manifest.session_count, manifest.images.len(), file),
+            file,
+        })
+    }
+}
+
+// Saves are reported by name only; where the save folder is on the server is none of a
+// client's business.
+fn saved_name(path: &Path) -> String {
+    path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default()
+}
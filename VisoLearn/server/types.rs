@@ -0,0 +1,165 @@
+use serde::{Deserialize, Serialize};
+
+use crate::config::{Difficulty, ImageStyle, SupportLevel};
+use crate::models::hints::UsedHint;
+use crate::utils::image_store::ImageRef;
+use crate::utils::session::{ChecklistItem, Session};
+
+// The JSON bodies of the API's requests and replies. The web UI's client decodes the same
+// types, so this module also builds for the browser, without the server around it.
+
+/// Settings for a new session; anything left out gets the same default as the UI. Sent with a
+/// new image request, they replace the session's settings from that image on.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CreateSessionRequest {
+    pub age: Option<String>,
+    pub support_level: Option<SupportLevel>,
+    pub topic_focus: Option<String>,
+    /// Defaults to the support level's plan.
+    pub treatment_plan: Option<String>,
+    pub attempt_limit: Option<u32>,
+    /// A fraction (0.1-1.0) or a percentage.
+    pub details_threshold: Option<f32>,
+    pub image_style: Option<ImageStyle>,
+    pub difficulty: Option<Difficulty>,
+}
+
+impl CreateSessionRequest {
+    pub fn into_session(self) -> Session {
+        self.apply_to(Session::default())
+    }
+
+    /// Applies these settings to `session`, keeping its own values for anything left out. A
+    /// missing treatment plan becomes the support level's plan if the level has changed.
+    pub fn apply_to(self, session: Session) -> Session {
+        let support_level = self.support_level.unwrap_or(session.autism_level);
+        let mut details_threshold = self.details_threshold.unwrap_or(session.details_threshold);
+        if details_threshold > 1.0 {
+            details_threshold /= 100.0;
+        }
+        let keep_plan = support_level == session.autism_level;
+        let treatment_plan = self
+            .treatment_plan
+            .or_else(|| session.treatment_plan.clone().filter(|_| keep_plan))
+            .unwrap_or_else(|| support_level.default_treatment_plan().to_string());
+        Session {
+            age: self.age.unwrap_or(session.age.clone()),
+            autism_level: support_level,
+            topic_focus: self.topic_focus.or(session.topic_focus.clone()),
+            treatment_plan: Some(treatment_plan),
+            attempt_limit: self.attempt_limit.unwrap_or(session.attempt_limit).max(1),
+            details_threshold: details_threshold.clamp(0.1, 1.0),
+            image_style: self.image_style.unwrap_or(session.image_style),
+            difficulty: self.difficulty.unwrap_or(session.difficulty),
+            ..session
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DescriptionRequest {
+    pub message: String,
+}
+
+/// A saved file to resume a practice from: the name of a session log or bundle in the server's
+/// save folder (`SAVE_DIR`), as the save endpoints report it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportRequest {
+    pub file: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChatMessage {
+    pub speaker: String,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Progress {
+    pub identified: usize,
+    pub total: usize,
+    /// How many details must be identified to move on to a new image.
+    pub needed: usize,
+    pub threshold_reached: bool,
+}
+
+impl Progress {
+    pub fn new(checklist: &[ChecklistItem], session: &Session) -> Self {
+        let identified = checklist.iter().filter(|item| item.identified).count();
+        let needed = ((checklist.len() as f32) * session.details_threshold).ceil() as usize;
+        Self {
+            identified,
+            total: checklist.len(),
+            needed,
+            threshold_reached: !checklist.is_empty() && identified >= needed,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Attempts {
+    pub used: u32,
+    pub limit: u32,
+    pub remaining: u32,
+}
+
+impl Attempts {
+    pub fn new(session: &Session) -> Self {
+        Self {
+            used: session.attempt_count,
+            limit: session.attempt_limit,
+            remaining: session.attempt_limit.saturating_sub(session.attempt_count),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ImageInfo {
+    pub hash: String,
+    pub mime_type: String,
+    /// Path of the image on this server.
+    pub url: String,
+}
+
+impl From<&ImageRef> for ImageInfo {
+    fn from(image: &ImageRef) -> Self {
+        Self {
+            hash: image.hash.clone(),
+            mime_type: image.mime_type.clone(),
+            url: format!("/images/{}", image.hash),
+        }
+    }
+}
+
+/// Everything a client needs to draw a session.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SessionStatus {
+    pub session_id: String,
+    pub prompt: Option<String>,
+    pub image: Option<ImageInfo>,
+    pub difficulty: Difficulty,
+    pub difficulty_reason: Option<String>,
+    pub chat: Vec<ChatMessage>,
+    pub checklist: Vec<ChecklistItem>,
+    pub progress: Progress,
+    pub attempts: Attempts,
+    pub completed_sessions: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HintResponse {
+    /// `None` when every detail has already been found.
+    pub hint: Option<UsedHint>,
+    pub status: SessionStatus,
+}
+
+/// A finished save. A failed one is an error reply instead.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SaveResult {
+    /// The file or folder written, by name in the save folder; a log or bundle can be passed
+    /// to `POST /imports` as is.
+    pub file: String,
+    /// The line shown next to the save buttons.
+    pub status: String,
+}
@@ -1,73 +1,387 @@
-use dotenv::dotenv;
+use serde::{Deserialize, Serialize};
+#[cfg(not(target_arch = "wasm32"))]
 use std::env;
-use std::collections::HashMap;
-use lazy_static::lazy_static;
+use std::fmt;
+use std::str::FromStr;
 
+use crate::error::VisoLearnError;
+
+#[cfg(not(target_arch = "wasm32"))]
 pub fn hf_token() -> String {
     env::var("HF_TOKEN").unwrap_or_default()
 }
 
+#[cfg(not(target_arch = "wasm32"))]
 pub fn google_api_key() -> String {
     env::var("GOOGLE_API_KEY").unwrap_or_default()
 }
 
+/// Settings for the vision-language backend (see `models::vision::from_config`).
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug, Clone)]
+pub struct VisionConfig {
+    pub backend: String, // "gemini", "openai" or "local"
+    pub model: Option<String>,
+    pub api_base: Option<String>,
+    pub api_key: Option<String>,
+}
+
+/// Settings for the text-generation backend (see `models::prompt_generation::GenerativeModel::from_config`).
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug, Clone)]
+pub struct TextConfig {
+    pub backend: String, // "gemini" or "openai"
+    pub model: Option<String>,
+    pub api_base: Option<String>,
+    pub api_key: Option<String>,
+}
+
+/// Reads `TEXT_BACKEND` (default "gemini"), `TEXT_MODEL`, `TEXT_API_BASE` and `TEXT_API_KEY`.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn text_config() -> TextConfig {
+    TextConfig {
+        backend: env::var("TEXT_BACKEND").unwrap_or_else(|_| "gemini".to_string()),
+        model: env::var("TEXT_MODEL").ok(),
+        api_base: env::var("TEXT_API_BASE").ok(),
+        api_key: env::var("TEXT_API_KEY").ok(),
+    }
+}
+
+/// Settings for the image generation backend (see `models::image_generation::from_config`
+/// and `models::image_generation::generation_config`).
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug, Clone)]
+pub struct ImageBackendConfig {
+    pub backend: String, // "huggingface", "webui", "comfyui" or "placeholder"
+    pub api_base: Option<String>,
+    pub api_key: Option<String>,
+    pub model: Option<String>, // Hugging Face repo id or local checkpoint name
+    pub seed: Option<String>,
+    pub steps: Option<String>,
+    pub size: Option<String>, // "<width>x<height>"
+}
+
+/// Reads `IMAGE_BACKEND` (default "huggingface"), `IMAGE_API_BASE`, `IMAGE_API_KEY`,
+/// `IMAGE_MODEL`, `IMAGE_SEED`, `IMAGE_STEPS` and `IMAGE_SIZE`.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn image_backend_config() -> ImageBackendConfig {
+    ImageBackendConfig {
+        backend: env::var("IMAGE_BACKEND").unwrap_or_else(|_| "huggingface".to_string()),
+        api_base: env::var("IMAGE_API_BASE").ok(),
+        api_key: env::var("IMAGE_API_KEY").ok(),
+        model: env::var("IMAGE_MODEL").ok(),
+        seed: env::var("IMAGE_SEED").ok(),
+        steps: env::var("IMAGE_STEPS").ok(),
+        size: env::var("IMAGE_SIZE").ok(),
+    }
+}
+
+/// Reads `VISION_BACKEND` (default "gemini"), `VISION_MODEL`, `VISION_API_BASE` and `VISION_API_KEY`.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn vision_config() -> VisionConfig {
+    VisionConfig {
+        backend: env::var("VISION_BACKEND").unwrap_or_else(|_| "gemini".to_string()),
+        model: env::var("VISION_MODEL").ok(),
+        api_base: env::var("VISION_API_BASE").ok(),
+        api_key: env::var("VISION_API_KEY").ok(),
+    }
+}
+
+/// Reads `PROGRESSION_POLICY` (default "standard"); see `models::progression::ProgressionPolicy::from_name`.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn progression_policy() -> String {
+    env::var("PROGRESSION_POLICY").unwrap_or_else(|_| "standard".to_string())
+}
+
+/// Reads `LEARNER_DB_PATH` (default "visolearn.db"); see `utils::learner_db::LearnerDatabase::open`.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn learner_db_path() -> String {
+    env::var("LEARNER_DB_PATH").unwrap_or_else(|_| "visolearn.db".to_string())
+}
+
+/// Reads `IMAGE_STORE_DIR` (default "images"); see `utils::image_store::ImageStore::open`.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn image_store_dir() -> String {
+    env::var("IMAGE_STORE_DIR").unwrap_or_else(|_| "images".to_string())
+}
+
+/// Reads `SAVE_DIR` (default "saved"), where session logs, images and bundles are saved and the
+/// only place `POST /imports` reads from.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn save_dir() -> String {
+    env::var("SAVE_DIR").unwrap_or_else(|_| "saved".to_string())
+}
+
+/// Reads `SERVER_ADDR` (default "127.0.0.1:8080"), the address `server::serve` listens on.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn server_addr() -> String {
+    env::var("SERVER_ADDR").unwrap_or_else(|_| "127.0.0.1:8080".to_string())
+}
+
+/// Reads `UI_DIR` (default "dist", where `trunk build` run in the crate directory writes the web
+/// UI), served alongside the API.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn ui_dir() -> String {
+    env::var("UI_DIR").unwrap_or_else(|_| "dist".to_string())
+}
+
+/// Reads `KIOSK_PIN` (default none), which the therapist enters to leave the desktop app's kiosk
+/// mode. Kiosk mode will not start without one.
+#[cfg(feature = "desktop")]
+pub fn kiosk_pin() -> String {
+    env::var("KIOSK_PIN").unwrap_or_default()
+}
+
 pub static DIFFICULTY_LEVELS: [&str; 5] = [
     "Very Simple", "Simple", "Moderate", "Detailed", "Very Detailed",
 ];
 
-lazy_static! {
-    pub static ref DEFAULT_TREATMENT_PLANS: HashMap<&'static str, &'static str> = {
-        let mut m = HashMap::new();
-        m.insert("Level 1", "Develop social communication skills and manage specific interests while maintaining independence.");
-        m.insert("Level 2", "Focus on structured learning environments with visual supports and consistent routines.");
-        m.insert("Level 3", "Provide highly structured support with simplified visual information and sensory-appropriate environments.");
-        m
-    };
-}
+pub static SUPPORT_LEVELS: [&str; 3] = ["Level 1", "Level 2", "Level 3"];
 
 pub static IMAGE_STYLES: [&str; 5] = [
     "Realistic", "Illustration", "Cartoon", "Watercolor", "3D Rendering",
 ];
 
-#[derive(Debug, Clone)]
-pub struct DefaultSession {
-    pub prompt: Option<String>,
-    pub image: Option<String>,
-    pub image_description: Option<String>,
-    pub chat: Vec<(String, String)>,
-    pub treatment_plan: String,
-    pub topic_focus: String,
-    pub key_details: Vec<String>,
-    pub identified_details: Vec<String>,
-    pub used_hints: Vec<String>,
-    pub difficulty: String,
-    pub age: String,
-    pub autism_level: String,
-    pub attempt_limit: u32,
-    pub attempt_count: u32,
-    pub details_threshold: f32,
-    pub image_style: String,
-}
-
-impl Default for DefaultSession {
-    fn default() -> Self {
-        Self {
-            prompt: None,
-            image: None,
-            image_description: None,
-            chat: Vec::new(),
-            treatment_plan: String::new(),
-            topic_focus: String::new(),
-            key_details: Vec::new(),
-            identified_details: Vec::new(),
-            used_hints: Vec::new(),
-            difficulty: "Very Simple".to_string(),
-            age: "3".to_string(),
-            autism_level: "Level 1".to_string(),
-            attempt_limit: 3,
-            attempt_count: 0,
-            details_threshold: 0.7,
-            image_style: "Realistic".to_string(),
+pub static THEME_PALETTES: [&str; 3] = ["Dark", "High Contrast", "Muted Pastel"];
+
+// Labels are matched ignoring case, surrounding whitespace and `_`/`-` separators.
+fn normalize_label(label: &str) -> String {
+    label.trim().to_lowercase().replace(['_', '-'], " ")
+}
+
+// Display, FromStr and serde for the label-backed enums below. They are (de)serialized as
+// their UI labels, so saved sessions read "Very Simple" rather than "VerySimple".
+macro_rules! impl_label_traits {
+    ($ty:ident, $kind:expr, $labels:ident) => {
+        impl fmt::Display for $ty {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str(self.as_str())
+            }
         }
+
+        impl FromStr for $ty {
+            type Err = VisoLearnError;
+
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                Self::from_label(s).ok_or_else(|| VisoLearnError::InvalidValue {
+                    kind: $kind,
+                    value: s.to_string(),
+                    expected: $labels.join(", "),
+                })
+            }
+        }
+
+        impl TryFrom<String> for $ty {
+            type Error = VisoLearnError;
+
+            fn try_from(s: String) -> Result<Self, Self::Error> {
+                s.parse()
+            }
+        }
+
+        impl From<$ty> for &'static str {
+            fn from(value: $ty) -> Self {
+                value.as_str()
+            }
+        }
+    };
+}
+
+/// The difficulty levels in `DIFFICULTY_LEVELS`, from easiest to hardest.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "&'static str")]
+pub enum Difficulty {
+    #[default]
+    VerySimple,
+    Simple,
+    Moderate,
+    Detailed,
+    VeryDetailed,
+}
+
+impl Difficulty {
+    pub const ALL: [Difficulty; 5] = [
+        Difficulty::VerySimple,
+        Difficulty::Simple,
+        Difficulty::Moderate,
+        Difficulty::Detailed,
+        Difficulty::VeryDetailed,
+    ];
+
+    /// The label shown in the UI and used in prompts, e.g. "Very Simple".
+    pub fn as_str(&self) -> &'static str {
+        DIFFICULTY_LEVELS[*self as usize]
+    }
+
+    /// Looks up a level by label, ignoring case and `_`/`-` separators.
+    pub fn from_label(label: &str) -> Option<Self> {
+        let normalized = normalize_label(label);
+        Self::ALL
+            .into_iter()
+            .find(|level| level.as_str().to_lowercase() == normalized)
+    }
+
+    /// The next harder level, staying at `VeryDetailed` once reached.
+    pub fn next(&self) -> Self {
+        Self::ALL[(*self as usize + 1).min(Self::ALL.len() - 1)]
+    }
+
+    /// The next easier level, staying at `VerySimple` once reached.
+    pub fn prev(&self) -> Self {
+        Self::ALL[(*self as usize).saturating_sub(1)]
+    }
+}
+
+impl_label_traits!(Difficulty, "difficulty level", DIFFICULTY_LEVELS);
+
+/// How much support the learner needs, following the DSM-5 autism levels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "&'static str")]
+pub enum SupportLevel {
+    #[default]
+    Level1,
+    Level2,
+    Level3,
+}
+
+impl SupportLevel {
+    pub const ALL: [SupportLevel; 3] = [SupportLevel::Level1, SupportLevel::Level2, SupportLevel::Level3];
+
+    /// The label shown in the UI and used in prompts, e.g. "Level 2".
+    pub fn as_str(&self) -> &'static str {
+        SUPPORT_LEVELS[*self as usize]
+    }
+
+    /// Looks up a level by label, also accepting "level2" and a bare "2".
+    pub fn from_label(label: &str) -> Option<Self> {
+        let compact = normalize_label(label).replace(' ', "");
+        let number = compact.strip_prefix("level").unwrap_or(&compact);
+        Self::ALL
+            .into_iter()
+            .find(|level| level.as_str().strip_prefix("Level ") == Some(number))
+    }
+
+    /// The treatment plan used when the teacher has not written one.
+    pub fn default_treatment_plan(&self) -> &'static str {
+        match self {
+            SupportLevel::Level1 => "Develop social communication skills and manage specific interests while maintaining independence.",
+            SupportLevel::Level2 => "Focus on structured learning environments with visual supports and consistent routines.",
+            SupportLevel::Level3 => "Provide highly structured support with simplified visual information and sensory-appropriate environments.",
+        }
+    }
+}
+
+impl_label_traits!(SupportLevel, "autism support level", SUPPORT_LEVELS);
+
+/// The art styles in `IMAGE_STYLES`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "&'static str")]
+pub enum ImageStyle {
+    #[default]
+    Realistic,
+    Illustration,
+    Cartoon,
+    Watercolor,
+    Rendering3D,
+}
+
+impl ImageStyle {
+    pub const ALL: [ImageStyle; 5] = [
+        ImageStyle::Realistic,
+        ImageStyle::Illustration,
+        ImageStyle::Cartoon,
+        ImageStyle::Watercolor,
+        ImageStyle::Rendering3D,
+    ];
+
+    /// The label shown in the UI and used in prompts, e.g. "3D Rendering".
+    pub fn as_str(&self) -> &'static str {
+        IMAGE_STYLES[*self as usize]
+    }
+
+    /// Looks up a style by label, ignoring case and `_`/`-` separators.
+    pub fn from_label(label: &str) -> Option<Self> {
+        let normalized = normalize_label(label);
+        Self::ALL
+            .into_iter()
+            .find(|style| style.as_str().to_lowercase() == normalized)
+    }
+
+    /// Instructions passed to the prompt writer describing how this style should look.
+    pub fn style_instruction(&self) -> &'static str {
+        match self {
+            ImageStyle::Realistic => "Create a realistic image with natural lighting and detailed textures, capturing the essence of real-world environments. Ensure the scene has a lifelike feel, with accurate light and shadow play, and textures that convey a true-to-life appearance.",
+            ImageStyle::Illustration => "Create a clean and colorful illustration in the style of children's books, featuring bold outlines, vibrant colors, and a playful, engaging composition. Ensure the artwork has a soft, friendly feel with well-defined shapes and a sense of warmth and charm.",
+            ImageStyle::Cartoon => "Create a friendly cartoon-style illustration with simplified shapes, bold outlines, and expressive characters. Ensure the characters have exaggerated facial expressions and dynamic poses to convey emotion and personality in a warm and inviting way.",
+            ImageStyle::Watercolor => "Create a soft watercolor illustration with gentle color transitions, delicate brushstrokes, and a dreamy, ethereal quality. Ensure the colors blend seamlessly, evoking a sense of warmth and tranquility.",
+            ImageStyle::Rendering3D => "Create a highly detailed 3D-rendered image with realistic depth, rich textures, and natural lighting effects. Ensure accurate reflections, shadows, and materials to enhance the sense of realism and immersion.",
+        }
+    }
+}
+
+impl_label_traits!(ImageStyle, "image style", IMAGE_STYLES);
+
+/// The colour schemes in `THEME_PALETTES`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "&'static str")]
+pub enum Palette {
+    #[default]
+    Dark,
+    HighContrast,
+    MutedPastel,
+}
+
+impl Palette {
+    pub const ALL: [Palette; 3] = [Palette::Dark, Palette::HighContrast, Palette::MutedPastel];
+
+    pub fn as_str(&self) -> &'static str {
+        THEME_PALETTES[*self as usize]
+    }
+
+    /// Looks up a palette by label, ignoring case and `_`/`-` separators.
+    pub fn from_label(label: &str) -> Option<Self> {
+        let normalized = normalize_label(label);
+        Self::ALL
+            .into_iter()
+            .find(|palette| palette.as_str().to_lowercase() == normalized)
+    }
+}
+
+impl_label_traits!(Palette, "theme palette", THEME_PALETTES);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_difficulty_progression() {
+        assert_eq!(Difficulty::VerySimple.next(), Difficulty::Simple);
+        assert_eq!(Difficulty::VeryDetailed.next(), Difficulty::VeryDetailed);
+        assert_eq!(Difficulty::Moderate.prev(), Difficulty::Simple);
+        assert_eq!(Difficulty::VerySimple.prev(), Difficulty::VerySimple);
+    }
+
+    #[test]
+    fn test_labels_round_trip() {
+        for level in Difficulty::ALL {
+            assert_eq!(level.to_string().parse::<Difficulty>().unwrap(), level);
+        }
+        assert_eq!("very_detailed".parse::<Difficulty>().unwrap(), Difficulty::VeryDetailed);
+        assert_eq!("level2".parse::<SupportLevel>().unwrap(), SupportLevel::Level2);
+        assert_eq!(" 3 ".parse::<SupportLevel>().unwrap(), SupportLevel::Level3);
+        assert_eq!("3d-rendering".parse::<ImageStyle>().unwrap(), ImageStyle::Rendering3D);
+        assert_eq!("high_contrast".parse::<Palette>().unwrap(), Palette::HighContrast);
+
+        assert_eq!(serde_json::to_string(&ImageStyle::Rendering3D).unwrap(), "\"3D Rendering\"");
+        assert_eq!(serde_json::from_str::<SupportLevel>("\"Level 1\"").unwrap(), SupportLevel::Level1);
+    }
+
+    #[test]
+    fn test_unknown_values_are_rejected() {
+        let err = "Medium".parse::<Difficulty>().unwrap_err();
+        assert!(matches!(&err, VisoLearnError::InvalidValue { kind: "difficulty level", value, .. } if value == "Medium"));
+        assert!("Level 4".parse::<SupportLevel>().is_err());
+        assert!("".parse::<SupportLevel>().is_err());
+        assert!(serde_json::from_str::<ImageStyle>("\"Oil Painting\"").is_err());
     }
 }
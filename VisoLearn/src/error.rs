@@ -0,0 +1,230 @@
+use std::sync::PoisonError;
+
+use thiserror::Error;
+
+use crate::models::evaluation::EvaluationParseError;
+
+/// Every way a VisoLearn operation can fail.
+#[derive(Debug, Error)]
+pub enum VisoLearnError {
+    #[error("configuration error: {0}")]
+    Config(String),
+    #[error("HTTP request failed: {0}")]
+    Http(reqwest::Error),
+    #[error("{service} returned HTTP {status}: {body}")]
+    Api {
+        service: String,
+        status: u16,
+        body: String,
+    },
+    #[error("{service} quota or rate limit exceeded: {detail}")]
+    QuotaExceeded { service: String, detail: String },
+    #[error("{service} blocked the request or response ({reason})")]
+    SafetyBlocked { service: String, reason: String },
+    #[error("unexpected response from {service}: {detail}")]
+    InvalidResponse { service: String, detail: String },
+    #[error("no image provided")]
+    NoImage,
+    #[error("could not decode image: {0}")]
+    ImageDecode(String),
+    #[error("could not parse {0}")]
+    Parse(String),
+    #[error("file error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("database error: {0}")]
+    #[cfg(not(target_arch = "wasm32"))]
+    Database(#[from] rusqlite::Error),
+    #[error("session state error: {0}")]
+    State(String),
+    #[error("{0} not found")]
+    NotFound(String),
+    /// An error reply from the VisoLearn API server; `message` is already meant for the user.
+    #[error("VisoLearn server returned HTTP {status}: {message}")]
+    Server { status: u16, message: String },
+    #[error("could not create report: {0}")]
+    Report(String),
+    #[error("invalid {kind} \"{value}\" (expected one of: {expected})")]
+    InvalidValue {
+        kind: &'static str,
+        value: String,
+        expected: String,
+    },
+}
+
+pub type Result<T> = std::result::Result<T, VisoLearnError>;
+
+impl VisoLearnError {
+    /// Classifies a non-success HTTP reply from `service`, treating 429 as a quota error.
+    pub fn from_status(service: &str, status: u16, body: String) -> Self {
+        if status == 429 {
+            VisoLearnError::QuotaExceeded {
+                service: service.to_string(),
+                detail: body,
+            }
+        } else {
+            VisoLearnError::Api {
+                service: service.to_string(),
+                status,
+                body,
+            }
+        }
+    }
+
+    pub fn invalid_response(service: &str, detail: impl Into<String>) -> Self {
+        VisoLearnError::InvalidResponse {
+            service: service.to_string(),
+            detail: detail.into(),
+        }
+    }
+
+    /// A short explanation suitable for showing to the person using the app.
+    pub fn user_message(&self) -> String {
+        match self {
+            VisoLearnError::Config(detail) => format!(
+                "VisoLearn is not set up correctly: {}. Check your .env settings.",
+                detail
+            ),
+            VisoLearnError::Http(_) => {
+                "Could not reach the AI service. Check your internet connection and try again."
+                    .to_string()
+            }
+            VisoLearnError::Api { service, status, .. } => format!(
+                "{} returned an error (HTTP {}). Please try again in a moment.",
+                service, status
+            ),
+            VisoLearnError::QuotaExceeded { service, .. } => format!(
+                "{} usage limit reached. Please wait a few minutes before trying again.",
+                service
+            ),
+            VisoLearnError::SafetyBlocked { .. } => {
+                "That request was blocked by the content safety filter. Try a different topic or description."
+                    .to_string()
+            }
+            VisoLearnError::InvalidResponse { service, .. } => format!(
+                "{} sent an answer VisoLearn could not understand. Please try again.",
+                service
+            ),
+            VisoLearnError::NoImage => {
+                "No image provided. Please make sure an image is generated or uploaded first."
+                    .to_string()
+            }
+            VisoLearnError::ImageDecode(_) => {
+                "The image could not be read. Please generate a new one.".to_string()
+            }
+            VisoLearnError::Parse(_) => {
+                "The AI's answer could not be understood. Please try again.".to_string()
+            }
+            VisoLearnError::Io(e) => format!("Could not read or write a file: {}", e),
+            #[cfg(not(target_arch = "wasm32"))]
+            VisoLearnError::Database(_) => {
+                "Could not read or save learner records. Check that the database file is available."
+                    .to_string()
+            }
+            VisoLearnError::State(_) => {
+                "Something went wrong with the current session. Please start a new one."
+                    .to_string()
+            }
+            VisoLearnError::NotFound(what) => format!(
+                "The {} could not be found. It may have ended or been removed.",
+                what
+            ),
+            VisoLearnError::Server { message, .. } => message.clone(),
+            VisoLearnError::Report(_) => {
+                "The progress report could not be created. Please try again.".to_string()
+            }
+            VisoLearnError::InvalidValue { kind, value, expected } => format!(
+                "\"{}\" is not a valid {}. Please choose one of: {}.",
+                value, kind, expected
+            ),
+        }
+    }
+}
+
+impl From<EvaluationParseError> for VisoLearnError {
+    fn from(e: EvaluationParseError) -> Self {
+        VisoLearnError::Parse(format!("evaluation: {}", e))
+    }
+}
+
+impl From<reqwest::Error> for VisoLearnError {
+    // Dropping the URL keeps query strings (and any credentials in them) out of error text.
+    fn from(e: reqwest::Error) -> Self {
+        VisoLearnError::Http(e.without_url())
+    }
+}
+
+impl From<serde_json::Error> for VisoLearnError {
+    fn from(e: serde_json::Error) -> Self {
+        VisoLearnError::Parse(format!("JSON: {}", e))
+    }
+}
+
+impl From<image::ImageError> for VisoLearnError {
+    fn from(e: image::ImageError) -> Self {
+        VisoLearnError::ImageDecode(e.to_string())
+    }
+}
+
+impl From<base64::DecodeError> for VisoLearnError {
+    fn from(e: base64::DecodeError) -> Self {
+        VisoLearnError::ImageDecode(format!("invalid base64 data: {}", e))
+    }
+}
+
+impl From<zip::result::ZipError> for VisoLearnError {
+    fn from(e: zip::result::ZipError) -> Self {
+        match e {
+            zip::result::ZipError::Io(e) => VisoLearnError::Io(e),
+            e => VisoLearnError::Parse(format!("archive: {}", e)),
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl From<printpdf::Error> for VisoLearnError {
+    fn from(e: printpdf::Error) -> Self {
+        VisoLearnError::Report(format!("PDF: {}", e))
+    }
+}
+
+impl<T> From<PoisonError<T>> for VisoLearnError {
+    fn from(e: PoisonError<T>) -> Self {
+        VisoLearnError::State(format!("a lock was poisoned: {}", e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_user_messages_distinguish_failures() {
+        let quota = VisoLearnError::QuotaExceeded {
+            service: "Gemini".to_string(),
+            detail: "429".to_string(),
+        };
+        assert!(quota.user_message().contains("usage limit"));
+        assert!(quota.to_string().contains("Gemini"));
+
+        let blocked = VisoLearnError::SafetyBlocked {
+            service: "Gemini".to_string(),
+            reason: "SAFETY".to_string(),
+        };
+        assert!(blocked.user_message().contains("safety filter"));
+
+        let parse: VisoLearnError = EvaluationParseError::NoJson.into();
+        assert!(matches!(parse, VisoLearnError::Parse(_)));
+        assert_ne!(parse.user_message(), quota.user_message());
+    }
+
+    #[test]
+    fn test_poisoned_lock_becomes_state_error() {
+        let lock = std::sync::Mutex::new(0);
+        let _ = std::panic::catch_unwind(|| {
+            let _guard = lock.lock().unwrap();
+            panic!("poison");
+        });
+        let err: VisoLearnError = lock.lock().unwrap_err().into();
+        assert!(matches!(err, VisoLearnError::State(_)));
+    }
+}
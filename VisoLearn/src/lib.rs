@@ -0,0 +1,20 @@
+// The modules live next to `src/`, one directory each. The browser build (the web UI) compiles
+// only the shared types and views; everything that talks to models, files or the network is
+// gated on `not(target_arch = "wasm32")`.
+
+#[cfg(not(target_arch = "wasm32"))]
+#[path = "../cli/mod.rs"]
+pub mod cli;
+pub mod config;
+#[cfg(feature = "desktop")]
+#[path = "../desktop/mod.rs"]
+pub mod desktop;
+pub mod error;
+#[path = "../models/mod.rs"]
+pub mod models;
+#[path = "../server/mod.rs"]
+pub mod server;
+#[path = "../ui/mod.rs"]
+pub mod ui;
+#[path = "../utils/mod.rs"]
+pub mod utils;
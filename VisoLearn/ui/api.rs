@@ -0,0 +1,148 @@
+use reqwest::{Client, RequestBuilder};
+use serde::de::DeserializeOwned;
+
+use crate::error::{Result, VisoLearnError};
+use crate::server::types::{
+    CreateSessionRequest, DescriptionRequest, HintResponse, ImageInfo, SaveResult, SessionStatus,
+};
+use crate::utils::session::Session;
+
+// The web UI's client for the endpoints in `server::api`. It shares that module's request and
+// response types, so the two cannot drift apart.
+
+pub struct ApiClient {
+    base_url: String,
+    client: Client,
+}
+
+impl ApiClient {
+    pub fn new(base_url: &str) -> Self {
+        Self {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            client: Client::new(),
+        }
+    }
+
+    /// A client for the server that served the page.
+    #[cfg(target_arch = "wasm32")]
+    pub fn from_window() -> Self {
+        let origin = web_sys::window()
+            .and_then(|window| window.location().origin().ok())
+            .unwrap_or_default();
+        Self::new(&origin)
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}{}", self.base_url, path)
+    }
+
+    // Sends a request and decodes the JSON reply. Error replies carry the server's user message.
+    async fn send<T: DeserializeOwned>(&self, request: RequestBuilder) -> Result<T> {
+        let response = request.send().await?;
+        let status = response.status();
+        if !status.is_success() {
+            let body: serde_json::Value = response.json().await.unwrap_or_default();
+            let message = body["error"]
+                .as_str()
+                .unwrap_or("The VisoLearn server could not complete the request. Please try again.")
+                .to_string();
+            return Err(VisoLearnError::Server {
+                status: status.as_u16(),
+                message,
+            });
+        }
+        Ok(response.json().await?)
+    }
+
+    pub async fn create_session(&self, request: &CreateSessionRequest) -> Result<SessionStatus> {
+        self.send(self.client.post(self.url("/sessions")).json(request)).await
+    }
+
+    /// Generates the session's next image using `settings`.
+    pub async fn generate_image(&self, session_id: &str, settings: &CreateSessionRequest) -> Result<SessionStatus> {
+        let url = self.url(&format!("/sessions/{}/image", session_id));
+        self.send(self.client.post(url).json(settings)).await
+    }
+
+    pub async fn describe(&self, session_id: &str, message: &str) -> Result<SessionStatus> {
+        let url = self.url(&format!("/sessions/{}/descriptions", session_id));
+        let request = DescriptionRequest {
+            message: message.to_string(),
+        };
+        self.send(self.client.post(url).json(&request)).await
+    }
+
+    pub async fn hint(&self, session_id: &str) -> Result<HintResponse> {
+        let url = self.url(&format!("/sessions/{}/hints", session_id));
+        self.send(self.client.post(url)).await
+    }
+
+    /// The finished sessions followed by the active one.
+    pub async fn history(&self, session_id: &str) -> Result<Vec<Session>> {
+        let url = self.url(&format!("/sessions/{}/history", session_id));
+        self.send(self.client.get(url)).await
+    }
+
+    pub async fn save_images(&self, session_id: &str) -> Result<SaveResult> {
+        let url = self.url(&format!("/sessions/{}/saved-images", session_id));
+        self.send(self.client.post(url)).await
+    }
+
+    pub async fn save_log(&self, session_id: &str) -> Result<SaveResult> {
+        let url = self.url(&format!("/sessions/{}/saved-logs", session_id));
+        self.send(self.client.post(url)).await
+    }
+
+    pub fn image_url(&self, image: &ImageInfo) -> String {
+        self.url(&image.url)
+    }
+
+    /// The WebSocket address of the session's live events.
+    pub fn events_url(&self, session_id: &str) -> String {
+        let url = self.url(&format!("/sessions/{}/events", session_id));
+        match url.split_once("://") {
+            Some(("https", rest)) => format!("wss://{}", rest),
+            Some((_, rest)) => format!("ws://{}", rest),
+            None => url,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use wiremock::matchers::{body_json, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    use crate::server::state::Practice;
+
+    #[tokio::test]
+    async fn test_client_decodes_replies_and_server_errors() {
+        let server = MockServer::start().await;
+        let status = SessionStatus::from(&Practice::default());
+        Mock::given(method("POST"))
+            .and(path("/sessions/abc/descriptions"))
+            .and(body_json(json!({ "message": "a red ball" })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(&status))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/sessions/abc/hints"))
+            .respond_with(ResponseTemplate::new(400).set_body_json(json!({ "error": "No image provided." })))
+            .mount(&server)
+            .await;
+
+        let client = ApiClient::new(&format!("{}/", server.uri()));
+        assert_eq!(client.describe("abc", "a red ball").await.unwrap(), status);
+
+        let err = client.hint("abc").await.unwrap_err();
+        assert!(matches!(&err, VisoLearnError::Server { status: 400, .. }), "unexpected error: {}", err);
+        assert_eq!(err.user_message(), "No image provided.");
+
+        assert_eq!(
+            ApiClient::new("https://visolearn.example").events_url("abc"),
+            "wss://visolearn.example/sessions/abc/events"
+        );
+    }
+}
@@ -0,0 +1,73 @@
+use crate::utils::theme::ThemeOptions;
+
+// The words on the practice screen, shared by the web UI and the desktop app.
+
+pub const COLOURS_LABEL: &str = "Colours";
+
+/// One of the display settings' checkboxes.
+#[derive(Clone, Copy)]
+pub struct DisplaySwitch {
+    pub label: &'static str,
+    pub get: fn(&ThemeOptions) -> bool,
+    pub set: fn(&mut ThemeOptions, bool),
+}
+
+pub const DISPLAY_SWITCHES: [DisplaySwitch; 4] = [
+    DisplaySwitch {
+        label: "Dyslexia-friendly font",
+        get: |options| options.dyslexia_font,
+        set: |options, on| options.dyslexia_font = on,
+    },
+    DisplaySwitch {
+        label: "Large text",
+        get: |options| options.large_text,
+        set: |options, on| options.large_text = on,
+    },
+    DisplaySwitch {
+        label: "Reduce motion",
+        get: |options| options.reduced_motion,
+        set: |options, on| options.reduced_motion = on,
+    },
+    DisplaySwitch {
+        label: "Colour-blind-safe cues",
+        get: |options| options.colour_blind_safe,
+        set: |options, on| options.colour_blind_safe = on,
+    },
+];
+
+pub const TITLE: &str = "Autism Education Image Description Tool";
+pub const GENERATE_HEADING: &str = "Generate Image";
+pub const GENERATE_INTRO: &str = "Enter the child's details to generate an appropriate educational image.";
+pub const AGE_PLACEHOLDER: &str = "Child's Age";
+pub const TOPIC_PLACEHOLDER: &str = "Topic Focus (e.g., 'animals', 'emotions')";
+pub const PLAN_PLACEHOLDER: &str = "Treatment Plan";
+pub const GENERATE_BUTTON: &str = "Generate Image";
+pub const IMAGE_ALT: &str = "Generated Image";
+pub const PRACTICE_HEADING: &str = "Image Description Practice";
+pub const PRACTICE_INTRO: &str = "After generating an image, ask the child to describe what they see. Type their description below. The system will provide supportive feedback and track their progress.";
+pub const DESCRIPTION_PLACEHOLDER: &str = "Child's Description";
+pub const SUBMIT_BUTTON: &str = "Submit";
+pub const HINT_BUTTON: &str = "Hint";
+
+/// The copy for one of the save boxes under the practice screen.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SaveBox {
+    pub heading: &'static str,
+    pub intro: &'static str,
+    pub button: &'static str,
+    pub placeholder: &'static str,
+}
+
+pub const SAVE_IMAGES: SaveBox = SaveBox {
+    heading: "Save Images",
+    intro: "Click the button below to save all images from all sessions to disk.",
+    button: "💾 Save All Session Images",
+    placeholder: "Save Result",
+};
+
+pub const SAVE_LOG: SaveBox = SaveBox {
+    heading: "Save Session Log",
+    intro: "Click the button below to save the complete session log as a JSON file.",
+    button: "📝 Save Session Log",
+    placeholder: "Save Log Result",
+};
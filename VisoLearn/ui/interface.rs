@@ -1,131 +1,323 @@
+use std::rc::Rc;
+
+use futures::StreamExt;
+use gloo_net::websocket::futures::WebSocket;
+use gloo_net::websocket::Message;
+use wasm_bindgen_futures::spawn_local;
 use yew::prelude::*;
 use web_sys::HtmlInputElement;
-use wasm_bindgen::JsCast;
 
-/// A stub for the default session value.
-const DEFAULT_SESSION: &str = "default_session";
-/// A stub list of image styles.
-const IMAGE_STYLES: &[&str] = &["Realistic", "Illustration", "Cartoon", "Watercolor", "3D Rendering"];
+use crate::config::{Palette, IMAGE_STYLES, SUPPORT_LEVELS, THEME_PALETTES};
+use crate::error::VisoLearnError;
+use crate::server::types::{ImageInfo, SessionStatus};
+use crate::ui::api::ApiClient;
+use crate::ui::copy::{self, SaveBox, DISPLAY_SWITCHES, SAVE_IMAGES, SAVE_LOG};
+use crate::ui::view::{page_style, PracticeAction, PracticeView, SettingsForm};
+use crate::utils::events::SessionEvent;
+use crate::utils::theme::{Theme, ThemeOptions};
+
+/// Records `error` so the banner shows its user-facing message.
+fn show_error(view: &UseReducerHandle<PracticeView>, error: &VisoLearnError) {
+    web_sys::console::error_1(&error.to_string().into());
+    view.dispatch(PracticeAction::Failed(error.user_message()));
+}
+
+/// HTML built by the `visualization` views, which escape everything they are given.
+fn trusted_html(html: String) -> Html {
+    Html::from_html_unchecked(AttrValue::from(html))
+}
+
+/// A save button with the box its outcome is reported in.
+fn save_box(text: SaveBox, onclick: Callback<MouseEvent>, disabled: bool, result: &str) -> Html {
+    html! {
+        <div style="flex: 1;">
+            <h2>{ text.heading }</h2>
+            <p>{ text.intro }</p>
+            <button onclick={onclick} disabled={disabled}>{ text.button }</button>
+            <input type="text" placeholder={text.placeholder} readonly=true value={result.to_string()} />
+        </div>
+    }
+}
+
+/// Updates one field of the settings form from the input that fired the event.
+fn edit<E: TargetCast + 'static>(settings: &UseStateHandle<SettingsForm>, set: fn(&mut SettingsForm, String)) -> Callback<E> {
+    let settings = settings.clone();
+    Callback::from(move |e: E| {
+        let input: HtmlInputElement = e.target_unchecked_into();
+        let mut form = (*settings).clone();
+        set(&mut form, input.value());
+        settings.set(form);
+    })
+}
+
+impl Reducible for PracticeView {
+    type Action = PracticeAction;
+
+    fn reduce(self: Rc<Self>, action: PracticeAction) -> Rc<Self> {
+        let mut view = (*self).clone();
+        view.apply(action);
+        Rc::new(view)
+    }
+}
+
+/// What requests and live events need once the page has rendered.
+#[derive(Clone)]
+struct Handles {
+    client: Rc<ApiClient>,
+    view: UseReducerHandle<PracticeView>,
+}
+
+impl Handles {
+    /// Redraws from a finished request, then fetches the session history for the panels.
+    async fn finish(&self, status: SessionStatus) {
+        let session_id = status.session_id.clone();
+        self.view.dispatch(PracticeAction::Status(status));
+        match self.client.history(&session_id).await {
+            Ok(history) => self.view.dispatch(PracticeAction::History(history)),
+            Err(e) => show_error(&self.view, &e),
+        }
+    }
+
+    // Follows the session's live events until the server closes the socket. Every request
+    // still returns the full status, so a missed event only delays a redraw.
+    async fn follow_events(self, session_id: String) {
+        let socket = match WebSocket::open(&self.client.events_url(&session_id)) {
+            Ok(socket) => socket,
+            Err(e) => {
+                web_sys::console::warn_1(&format!("Live updates unavailable: {}", e).into());
+                return;
+            }
+        };
+        let (_, mut events) = socket.split();
+        while let Some(message) = events.next().await {
+            let text = match message {
+                Ok(Message::Text(text)) => text,
+                Ok(Message::Bytes(_)) => continue,
+                Err(_) => break,
+            };
+            if let Ok(event) = serde_json::from_str::<SessionEvent>(&text) {
+                self.view.dispatch(PracticeAction::Event(event));
+            }
+        }
+    }
+}
 
 #[function_component(App)]
-fn app() -> Html {
+pub fn app() -> Html {
     // Define application state.
-    let active_session = use_state(|| DEFAULT_SESSION.to_string());
-    let saved_sessions = use_state(|| Vec::<String>::new());
-    let checklist_state = use_state(|| Vec::<String>::new());
-
-    // Input states.
-    let age_input = use_state(|| "3".to_string());
-    let autism_level = use_state(|| "Level 1".to_string());
-    let topic_focus = use_state(|| "".to_string());
-    let treatment_plan = use_state(|| "".to_string());
-    let attempt_limit = use_state(|| 3);
-    let details_threshold = use_state(|| 70);
-    let image_style = use_state(|| IMAGE_STYLES[0].to_string());
+    let client = use_memo(|_| ApiClient::from_window(), ());
+    let view = use_reducer(PracticeView::default);
+    let settings = use_state(SettingsForm::default);
     let chat_input = use_state(|| "".to_string());
-    let chatbot_history = use_state(|| Vec::<String>::new());
-    let img_src = use_state(|| "".to_string());
+    let save_images_result = use_state(String::new);
+    let save_log_result = use_state(String::new);
+    let theme_options = use_state(ThemeOptions::default);
+    let theme = Theme::from_options(&theme_options);
 
-    // Event handler stubs.
-    let on_generate_click = {
-        let age_input = age_input.clone();
-        let autism_level = autism_level.clone();
-        let topic_focus = topic_focus.clone();
-        let treatment_plan = treatment_plan.clone();
-        let attempt_limit = attempt_limit.clone();
-        let details_threshold = details_threshold.clone();
-        let active_session = active_session.clone();
-        let saved_sessions = saved_sessions.clone();
-        let image_style = image_style.clone();
-        let img_src = img_src.clone();
+    let handles = Handles {
+        client: client.clone(),
+        view: view.clone(),
+    };
+    let busy = view.is_busy();
+
+    // Display settings each update one switch in `theme_options`.
+    let toggle = |set: fn(&mut ThemeOptions, bool)| {
+        let theme_options = theme_options.clone();
+        Callback::from(move |e: Event| {
+            let input: HtmlInputElement = e.target_unchecked_into();
+            let mut options = *theme_options;
+            set(&mut options, input.checked());
+            theme_options.set(options);
+        })
+    };
+    let on_palette_change = {
+        let theme_options = theme_options.clone();
+        Callback::from(move |e: Event| {
+            let select: HtmlInputElement = e.target_unchecked_into();
+            if let Ok(palette) = select.value().parse::<Palette>() {
+                theme_options.set(ThemeOptions { palette, ..*theme_options });
+            }
+        })
+    };
 
+    // Creates the session on first use, then generates an image with the current settings.
+    let on_generate_click = {
+        let handles = handles.clone();
+        let settings = settings.clone();
         Callback::from(move |_| {
-            // Here you would call your backend function (e.g. generate_image_and_reset_chat)
-            // and update the state accordingly. For demo purposes we just log and update a dummy image.
-            web_sys::console::log_1(&"Generate Image clicked".into());
-            // Simulate generating an image (in practice, call an async API)
-            img_src.set("https://via.placeholder.com/400x300.png?text=Generated+Image".to_string());
-            // You could also update active_session, saved_sessions, checklist_state, etc.
+            // Validate the selections before anything is sent to the models.
+            let request = match settings.to_request() {
+                Ok(request) => request,
+                Err(e) => return show_error(&handles.view, &e),
+            };
+            handles.view.dispatch(PracticeAction::Busy("Starting a new picture...".to_string()));
+            let handles = handles.clone();
+            spawn_local(async move {
+                let session_id = match handles.view.session_id.clone() {
+                    Some(session_id) => session_id,
+                    None => match handles.client.create_session(&request).await {
+                        Ok(status) => {
+                            let session_id = status.session_id.clone();
+                            handles.view.dispatch(PracticeAction::Status(status));
+                            spawn_local(handles.clone().follow_events(session_id.clone()));
+                            session_id
+                        }
+                        Err(e) => return show_error(&handles.view, &e),
+                    },
+                };
+                match handles.client.generate_image(&session_id, &request).await {
+                    Ok(status) => handles.finish(status).await,
+                    Err(e) => show_error(&handles.view, &e),
+                }
+            });
         })
     };
 
     let on_chat_send = {
+        let handles = handles.clone();
         let chat_input = chat_input.clone();
-        let chatbot_history = chatbot_history.clone();
         Callback::from(move |_| {
-            web_sys::console::log_1(&"Chat Send clicked".into());
-            let input = (*chat_input).clone();
-            if !input.is_empty() {
-                // Append chat input to chatbot history.
-                let mut history = (*chatbot_history).clone();
-                history.push(input.clone());
-                chatbot_history.set(history);
-                chat_input.set("".to_string());
+            let Some(session_id) = handles.view.session_id.clone().filter(|_| handles.view.image.is_some()) else {
+                return show_error(&handles.view, &VisoLearnError::NoImage);
+            };
+            let input = chat_input.trim().to_string();
+            if input.is_empty() {
+                return;
             }
+            handles.view.dispatch(PracticeAction::Busy("Checking the description...".to_string()));
+            // Show the child's words straight away; the reply replaces the whole transcript.
+            handles.view.dispatch(PracticeAction::ChildSaid(input.clone()));
+            chat_input.set("".to_string());
+
+            let handles = handles.clone();
+            spawn_local(async move {
+                match handles.client.describe(&session_id, &input).await {
+                    Ok(status) => handles.finish(status).await,
+                    Err(e) => show_error(&handles.view, &e),
+                }
+            });
+        })
+    };
+
+    // The hint is added to the transcript with the rest of the reply.
+    let on_hint_click = {
+        let handles = handles.clone();
+        Callback::from(move |_| {
+            let Some(session_id) = handles.view.session_id.clone().filter(|_| handles.view.image.is_some()) else {
+                return show_error(&handles.view, &VisoLearnError::NoImage);
+            };
+            handles.view.dispatch(PracticeAction::Busy("Finding a hint...".to_string()));
+            let handles = handles.clone();
+            spawn_local(async move {
+                match handles.client.hint(&session_id).await {
+                    Ok(hinted) => handles.finish(hinted.status).await,
+                    Err(e) => show_error(&handles.view, &e),
+                }
+            });
         })
     };
 
-    // Below we build a layout similar to your Gradio interface.
+    // Each save reports its outcome in the box next to its button.
+    let save = |result: &UseStateHandle<String>, log: bool| {
+        let handles = handles.clone();
+        let result = result.clone();
+        Callback::from(move |_| {
+            let Some(session_id) = handles.view.session_id.clone() else {
+                return;
+            };
+            let handles = handles.clone();
+            let result = result.clone();
+            spawn_local(async move {
+                let saved = if log {
+                    handles.client.save_log(&session_id).await
+                } else {
+                    handles.client.save_images(&session_id).await
+                };
+                match saved {
+                    Ok(saved) => result.set(saved.status),
+                    Err(e) => {
+                        result.set(format!("❌ {}", e.user_message()));
+                        show_error(&handles.view, &e);
+                    }
+                }
+            });
+        })
+    };
+    let on_save_images = save(&save_images_result, false);
+    let on_save_log = save(&save_log_result, true);
+
+    let image_url = view.image.as_ref().map(|image| client.image_url(&ImageInfo::from(image)));
+    let no_session = view.session_id.is_none();
+
     html! {
-        <div style="display: flex; flex-direction: column; gap: 2rem; padding: 1rem;">
+        <div style={page_style(&theme)}>
             // Main header.
-            <h1>{ "Autism Education Image Description Tool" }</h1>
-            // Display current difficulty (stubbed as “Very Simple”).
+            <h1>{ copy::TITLE }</h1>
+            { trusted_html(view.error_html(&theme)) }
+            // What the app is doing right now, for screen readers too.
+            <div id="loading-status" role="status" aria-live="polite">
+                if let Some(message) = &view.loading {
+                    <p style={format!("color: {};", theme.muted_text)}>{ message }</p>
+                }
+            </div>
+            // Display settings.
+            <section id="display-settings" style="display: flex; gap: 1rem; align-items: center; flex-wrap: wrap;">
+                <label>
+                    { format!("{} ", copy::COLOURS_LABEL) }
+                    <select onchange={on_palette_change}>
+                        { for THEME_PALETTES.iter().map(|palette| html! {
+                            <option value={palette.to_string()} selected={*palette == theme_options.palette.as_str()}>{ palette }</option>
+                        })}
+                    </select>
+                </label>
+                { for DISPLAY_SWITCHES.iter().map(|switch| html! {
+                    <label>
+                        <input type="checkbox" checked={(switch.get)(&theme_options)} onchange={toggle(switch.set)} />
+                        { format!(" {}", switch.label) }
+                    </label>
+                })}
+            </section>
             <div id="difficulty-label">
                 <strong>{ "Current Difficulty: " }</strong>
-                { "Very Simple" }
+                { view.difficulty.to_string() }
             </div>
-            // Main content area with two columns.
             <div style="display: flex; gap: 2rem;">
-                // Left Column (Image Generation & Chat)
                 <div style="flex: 2;">
                     <section>
-                        <h2>{ "Generate Image" }</h2>
-                        <p>{ "Enter the child's details to generate an appropriate educational image." }</p>
+                        <h2>{ copy::GENERATE_HEADING }</h2>
+                        <p>{ copy::GENERATE_INTRO }</p>
                         <div style="display: flex; gap: 1rem;">
                             <input
                                 type="text"
-                                placeholder="Child's Age"
-                                value={(*age_input).clone()}
-                                oninput={Callback::from(move |e: InputEvent| {
-                                    let input: HtmlInputElement = e.target_unchecked_into();
-                                    age_input.set(input.value());
-                                })}
+                                placeholder={copy::AGE_PLACEHOLDER}
+                                value={settings.age.clone()}
+                                oninput={edit::<InputEvent>(&settings, |form, value| form.age = value)}
                             />
-                            <select onchange={Callback::from(move |e: Event| {
-                                let select: HtmlInputElement = e.target_unchecked_into();
-                                autism_level.set(select.value());
-                            })}>
-                                <option value="Level 1">{ "Level 1" }</option>
-                                <option value="Level 2">{ "Level 2" }</option>
-                                <option value="Level 3">{ "Level 3" }</option>
+                            <select onchange={edit::<Event>(&settings, |form, value| form.support_level = value)}>
+                                { for SUPPORT_LEVELS.iter().map(|level| html! {
+                                    <option value={level.to_string()} selected={*level == settings.support_level}>{ level }</option>
+                                })}
                             </select>
                         </div>
                         <input
                             type="text"
-                            placeholder="Topic Focus (e.g., 'animals', 'emotions')"
-                            value={(*topic_focus).clone()}
-                            oninput={Callback::from(move |e: InputEvent| {
-                                let input: HtmlInputElement = e.target_unchecked_into();
-                                topic_focus.set(input.value());
-                            })}
+                            placeholder={copy::TOPIC_PLACEHOLDER}
+                            value={settings.topic_focus.clone()}
+                            oninput={edit::<InputEvent>(&settings, |form, value| form.topic_focus = value)}
                         />
                         <textarea
-                            placeholder="Treatment Plan"
-                            value={(*treatment_plan).clone()}
-                            oninput={Callback::from(move |e: InputEvent| {
-                                let input: HtmlInputElement = e.target_unchecked_into();
-                                treatment_plan.set(input.value());
-                            })}
+                            placeholder={copy::PLAN_PLACEHOLDER}
+                            value={settings.treatment_plan.clone()}
+                            oninput={edit::<InputEvent>(&settings, |form, value| form.treatment_plan = value)}
                         />
                         <div style="display: flex; gap: 1rem;">
                             <input
                                 type="number"
-                                value={attempt_limit.to_string()}
-                                oninput={Callback::from(move |e: InputEvent| {
-                                    let input: HtmlInputElement = e.target_unchecked_into();
-                                    if let Ok(val) = input.value().parse::<i32>() {
-                                        attempt_limit.set(val);
+                                min="1"
+                                value={settings.attempt_limit.to_string()}
+                                oninput={edit::<InputEvent>(&settings, |form, value| {
+                                    if let Ok(limit) = value.parse() {
+                                        form.attempt_limit = limit;
                                     }
                                 })}
                             />
@@ -134,92 +326,61 @@ fn app() -> Html {
                                 min="10"
                                 max="100"
                                 step="5"
-                                value={details_threshold.to_string()}
-                                oninput={Callback::from(move |e: InputEvent| {
-                                    let input: HtmlInputElement = e.target_unchecked_into();
-                                    if let Ok(val) = input.value().parse::<i32>() {
-                                        details_threshold.set(val);
+                                value={settings.details_threshold.to_string()}
+                                oninput={edit::<InputEvent>(&settings, |form, value| {
+                                    if let Ok(threshold) = value.parse() {
+                                        form.details_threshold = threshold;
                                     }
                                 })}
                             />
                         </div>
-                        <select onchange={Callback::from(move |e: Event| {
-                            let select: HtmlInputElement = e.target_unchecked_into();
-                            image_style.set(select.value());
-                        })}>
+                        <select onchange={edit::<Event>(&settings, |form, value| form.image_style = value)}>
                             { for IMAGE_STYLES.iter().map(|style| html! {
-                                <option value={style.to_string()}>{ style }</option>
+                                <option value={style.to_string()} selected={*style == settings.image_style}>{ style }</option>
                             })}
                         </select>
                         <div>
-                            <button onclick={on_generate_click}>{ "Generate Image" }</button>
+                            <button onclick={on_generate_click} disabled={busy}>{ copy::GENERATE_BUTTON }</button>
                         </div>
                         <div>
-                            if !(*img_src).is_empty() {
-                                <img src={(*img_src).clone()} alt="Generated Image" style="max-width: 100%;" />
+                            if let Some(src) = image_url {
+                                <img src={src} alt={copy::IMAGE_ALT} style="max-width: 100%;" />
                             }
                         </div>
                     </section>
                     <section>
-                        <h2>{ "Image Description Practice" }</h2>
-                        <p>{ "After generating an image, ask the child to describe what they see. Type their description below. The system will provide supportive feedback and track their progress." }</p>
-                        <div id="chatbot" style="border: 1px solid #ccc; padding: 1rem; min-height: 100px;">
-                            { for (*chatbot_history).iter().map(|msg| html! { <p>{ msg }</p> } ) }
-                        </div>
+                        <h2>{ copy::PRACTICE_HEADING }</h2>
+                        <p>{ copy::PRACTICE_INTRO }</p>
+                        { trusted_html(view.transcript_html(&theme)) }
                         <div style="display: flex; gap: 1rem;">
                             <input
                                 type="text"
-                                placeholder="Child's Description"
+                                placeholder={copy::DESCRIPTION_PLACEHOLDER}
                                 value={(*chat_input).clone()}
                                 oninput={Callback::from(move |e: InputEvent| {
                                     let input: HtmlInputElement = e.target_unchecked_into();
                                     chat_input.set(input.value());
                                 })}
                             />
-                            <button onclick={on_chat_send}>{ "Submit" }</button>
+                            <button onclick={on_chat_send} disabled={busy}>{ copy::SUBMIT_BUTTON }</button>
+                            <button onclick={on_hint_click} disabled={busy}>{ copy::HINT_BUTTON }</button>
                         </div>
                     </section>
                 </div>
-                // Right Column (Details to Identify)
                 <div style="flex: 1;">
-                    <h2>{ "Details to Identify" }</h2>
-                    <p>{ "The child should try to identify these elements in the image:" }</p>
-                    <div id="checklist-container" style="background-color: #000; color: #fff; padding: 15px; border-radius: 8px;">
-                        <p>{ "Generate an image to see details to identify." }</p>
-                    </div>
-                    <div id="attempt-counter" style="margin-top: 10px; padding: 10px; background-color: #000; color: #fff; border-radius: 5px; border: 1px solid #444;">
-                        <p style="margin: 0; font-weight: bold;">{ "Attempts: 0/3" }</p>
-                    </div>
-                    <div id="progress-container" style="background-color: #000; color: #fff; padding: 15px; border-radius: 8px;">
-                        <p>{ "No active session." }</p>
-                    </div>
+                    { trusted_html(view.details_html(&theme)) }
                 </div>
             </div>
-            // Progress Tracking section.
-            <section>
-                <h2>{ "Progress Tracking" }</h2>
-                <p>{ "This section tracks the child's progress across sessions. Each session includes the difficulty level, identified details, and the full conversation history." }</p>
-                <pre>{ "{}" }</pre>
-            </section>
-            // Save Images and Save Session Log section.
+            { trusted_html(view.tracking_html(&theme)) }
             <section style="display: flex; gap: 2rem;">
-                <div style="flex: 1;">
-                    <h2>{ "Save Images" }</h2>
-                    <p>{ "Click the button below to save all images from all sessions to disk." }</p>
-                    <button>{ "💾 Save All Session Images" }</button>
-                    <input type="text" placeholder="Save Result" readonly=true />
-                </div>
-                <div style="flex: 1;">
-                    <h2>{ "Save Session Log" }</h2>
-                    <p>{ "Click the button below to save the complete session log as a JSON file." }</p>
-                    <button>{ "📝 Save Session Log" }</button>
-                    <input type="text" placeholder="Save Log Result" readonly=true />
-                </div>
+                { save_box(SAVE_IMAGES, on_save_images, no_session, &save_images_result) }
+                { save_box(SAVE_LOG, on_save_log, no_session, &save_log_result) }
             </section>
         </div>
     }
 }
 
-fn main() {
-    yew::start_app::<App>();
+/// Mounts the app on the page's body.
+pub fn start() {
+    yew::Renderer::<App>::new().render();
 }
@@ -1,5 +1,10 @@
 // Export modules
+pub mod api;
+pub mod copy;
+#[cfg(target_arch = "wasm32")]
 pub mod interface;
+pub mod view;
 
 // Re-export commonly used items from interface
-pub use interface::{AppState, UIConfig, initialize_ui, render_main_view};
\ No newline at end of file
+#[cfg(target_arch = "wasm32")]
+pub use interface::{start, App};
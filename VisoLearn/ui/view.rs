@@ -0,0 +1,361 @@
+use maud::{html, PreEscaped};
+use serde::{Deserialize, Serialize};
+
+use crate::config::{Difficulty, ImageStyle, SupportLevel};
+use crate::error::Result;
+use crate::models::evaluation::partial_feedback;
+use crate::server::types::{ChatMessage, CreateSessionRequest, SessionStatus};
+use crate::utils::events::SessionEvent;
+use crate::utils::image_store::ImageRef;
+use crate::utils::session::{ChecklistItem, Session};
+use crate::utils::theme::Theme;
+use crate::utils::visualization::{display_metrics, update_attempt_counter, update_checklist_html, update_progress_html};
+
+// What the practice screen shows, independent of the framework drawing it. The web UI (Yew,
+// talking to the API) and the desktop app (dioxus, running the engine in-process) both keep a
+// `PracticeView` and feed it the same `PracticeAction`s. Every part of the screen that takes
+// no input (the error banner, the transcript, the details panel and progress tracking) is
+// rendered here as HTML, and the controls' headings, instructions and labels come from
+// `ui::copy`. Each framework writes only its own form controls and how their events are wired.
+
+/// Inline style for the whole screen.
+pub fn page_style(theme: &Theme) -> String {
+    format!(
+        "display: flex; flex-direction: column; gap: 2rem; padding: 1rem; background-color: {}; color: {}; {}",
+        theme.background, theme.text, theme.font_style()
+    )
+}
+
+/// Shows a failed operation to the user, or nothing. The message is already meant for the user.
+pub fn error_banner_html(message: Option<&str>, theme: &Theme) -> String {
+    let markup = html! {
+        @if let Some(message) = message {
+            div id="error-banner" role="alert" style={ (theme.panel_style()) " border: 2px solid " (theme.threshold) ";" } {
+                strong { (if theme.emoji { "⚠️ " } else { "Problem: " }) }
+                (message)
+            }
+        }
+    };
+    markup.into_string()
+}
+
+/// The settings form, as typed. Values are checked only when an image is requested.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SettingsForm {
+    pub age: String,
+    pub support_level: String,
+    pub topic_focus: String,
+    /// Empty for the support level's default plan.
+    pub treatment_plan: String,
+    pub attempt_limit: u32,
+    /// A percentage, 10-100.
+    pub details_threshold: u32,
+    pub image_style: String,
+}
+
+impl Default for SettingsForm {
+    fn default() -> Self {
+        Self::from_session(&Session::default())
+    }
+}
+
+impl SettingsForm {
+    /// The form filled in with a session's settings, e.g. when a learner's program is resumed.
+    pub fn from_session(session: &Session) -> Self {
+        Self {
+            age: session.age.clone(),
+            support_level: session.autism_level.to_string(),
+            topic_focus: session.topic_focus.clone().unwrap_or_default(),
+            treatment_plan: String::new(),
+            attempt_limit: session.attempt_limit,
+            details_threshold: (session.details_threshold * 100.0).round() as u32,
+            image_style: session.image_style.to_string(),
+        }
+    }
+
+    /// Validates the selections and builds the settings for the next image. Difficulty is
+    /// left to the progression engine.
+    pub fn to_request(&self) -> Result<CreateSessionRequest> {
+        let support_level = self.support_level.parse::<SupportLevel>()?;
+        let image_style = self.image_style.parse::<ImageStyle>()?;
+        Ok(CreateSessionRequest {
+            age: Some(self.age.trim().to_string()),
+            support_level: Some(support_level),
+            topic_focus: Some(self.topic_focus.trim().to_string()),
+            treatment_plan: Some(self.treatment_plan.trim().to_string()).filter(|plan| !plan.is_empty()),
+            attempt_limit: Some(self.attempt_limit),
+            details_threshold: Some(self.details_threshold as f32),
+            image_style: Some(image_style),
+            difficulty: None,
+        })
+    }
+}
+
+/// Something that changes the practice screen.
+#[derive(Clone, Debug, PartialEq)]
+pub enum PracticeAction {
+    /// A request has started; the message says what is happening.
+    Busy(String),
+    /// A request finished with the session's full status.
+    Status(SessionStatus),
+    History(Vec<Session>),
+    Event(SessionEvent),
+    /// Shows the child's words before the evaluation comes back.
+    ChildSaid(String),
+    /// A request failed; the message is already meant for the user.
+    Failed(String),
+    DismissError,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PracticeView {
+    pub session_id: Option<String>,
+    pub image: Option<ImageRef>,
+    pub chat: Vec<ChatMessage>,
+    pub checklist: Vec<ChecklistItem>,
+    pub difficulty: Difficulty,
+    pub attempt_limit: u32,
+    /// Finished sessions followed by the active one.
+    pub history: Vec<Session>,
+    /// What the app is doing, while a request is running.
+    pub loading: Option<String>,
+    pub error: Option<String>,
+    // The model's raw evaluation reply streamed so far.
+    reply: String,
+}
+
+impl PracticeView {
+    pub fn apply(&mut self, action: PracticeAction) {
+        match action {
+            PracticeAction::Busy(message) => {
+                self.loading = Some(message);
+                self.error = None;
+            }
+            PracticeAction::Status(status) => {
+                self.session_id = Some(status.session_id);
+                self.image = status.image.map(|image| ImageRef {
+                    hash: image.hash,
+                    mime_type: image.mime_type,
+                });
+                self.chat = status.chat;
+                self.checklist = status.checklist;
+                self.difficulty = status.difficulty;
+                self.attempt_limit = status.attempts.limit;
+                self.reply.clear();
+                self.loading = None;
+            }
+            PracticeAction::History(history) => self.history = history,
+            PracticeAction::Event(event) => self.apply_event(event),
+            PracticeAction::ChildSaid(text) => self.chat.push(ChatMessage {
+                speaker: "Child".to_string(),
+                text,
+            }),
+            PracticeAction::Failed(message) => {
+                self.loading = None;
+                self.reply.clear();
+                self.error = Some(message);
+            }
+            PracticeAction::DismissError => self.error = None,
+        }
+    }
+
+    fn apply_event(&mut self, event: SessionEvent) {
+        match event {
+            SessionEvent::GenerationStarted { difficulty } => {
+                self.difficulty = difficulty;
+                self.loading = Some(format!("Creating a new {} picture...", difficulty));
+            }
+            SessionEvent::PromptReady { .. } => self.loading = Some("Drawing the picture...".to_string()),
+            SessionEvent::ImageReady { hash, mime_type } => {
+                self.image = Some(ImageRef { hash, mime_type });
+                self.loading = Some("Looking at the picture...".to_string());
+            }
+            SessionEvent::DescriptionReady { .. } => {
+                self.loading = Some("Choosing details to look for...".to_string())
+            }
+            SessionEvent::ChecklistReady { checklist } => self.checklist = checklist,
+            SessionEvent::EvaluationToken { text } => self.reply.push_str(&text),
+            SessionEvent::EvaluationDone { .. } => {}
+            SessionEvent::DifficultyChanged { to, .. } => self.difficulty = to,
+            SessionEvent::Error { message } => self.error = Some(message),
+        }
+    }
+
+    pub fn is_busy(&self) -> bool {
+        self.loading.is_some()
+    }
+
+    /// The teacher's feedback as it is being written, or nothing between evaluations.
+    pub fn live_feedback(&self) -> String {
+        partial_feedback(&self.reply)
+    }
+
+    // The panels are drawn from the active session, once the first image exists.
+    fn active_session(&self) -> Session {
+        self.history
+            .last()
+            .filter(|session| !session.completed && self.image.is_some())
+            .cloned()
+            .unwrap_or_else(|| Session {
+                attempt_limit: self.attempt_limit.max(1),
+                ..Session::default()
+            })
+    }
+
+    fn checklist_html(&self, theme: &Theme) -> String {
+        update_checklist_html(&self.checklist, theme)
+    }
+
+    fn attempts_html(&self, theme: &Theme) -> String {
+        update_attempt_counter(&self.active_session(), theme)
+    }
+
+    fn progress_html(&self, theme: &Theme) -> String {
+        update_progress_html(&self.checklist, &self.active_session(), theme)
+    }
+
+    // Charts across every session so far, or nothing before the first image.
+    fn metrics_html(&self, theme: &Theme) -> String {
+        if self.history.is_empty() {
+            String::new()
+        } else {
+            display_metrics(&self.history, theme)
+        }
+    }
+
+    fn history_json(&self) -> String {
+        serde_json::to_string_pretty(&self.history).unwrap_or_default()
+    }
+
+    pub fn error_html(&self, theme: &Theme) -> String {
+        error_banner_html(self.error.as_deref(), theme)
+    }
+
+    /// The conversation so far, ending with the teacher's feedback while it is being written.
+    pub fn transcript_html(&self, theme: &Theme) -> String {
+        let live_feedback = self.live_feedback();
+        let markup = html! {
+            div id="chatbot" aria-live="polite" style={ "display: flex; flex-direction: column; gap: 0.5rem; border: 1px solid " (theme.border) "; padding: 1rem; min-height: 100px;" } {
+                @for message in &self.chat {
+                    (PreEscaped(chat_bubble_html(&message.speaker, &message.text, theme)))
+                }
+                @if !live_feedback.is_empty() {
+                    (PreEscaped(chat_bubble_html("Teacher", &live_feedback, theme)))
+                }
+            }
+        };
+        markup.into_string()
+    }
+
+    /// The column beside the image: the checklist, attempts and progress.
+    pub fn details_html(&self, theme: &Theme) -> String {
+        let markup = html! {
+            h2 { "Details to Identify" }
+            p { "The child should try to identify these elements in the image:" }
+            (PreEscaped(self.checklist_html(theme)))
+            (PreEscaped(self.attempts_html(theme)))
+            (PreEscaped(self.progress_html(theme)))
+        };
+        markup.into_string()
+    }
+
+    /// Charts and the full history of every session so far.
+    pub fn tracking_html(&self, theme: &Theme) -> String {
+        let markup = html! {
+            section {
+                h2 { "Progress Tracking" }
+                p { "This section tracks the child's progress across sessions. Each session includes the difficulty level, identified details, and the full conversation history." }
+                (PreEscaped(self.metrics_html(theme)))
+                pre { (self.history_json()) }
+            }
+        };
+        markup.into_string()
+    }
+}
+
+// One line of the transcript, laid out by who said it.
+fn chat_bubble_html(speaker: &str, text: &str, theme: &Theme) -> String {
+    let markup = html! {
+        div class={ "chat-message " (speaker.to_lowercase()) } style=(chat_bubble_style(speaker, theme)) {
+            strong { (speaker) ": " }
+            (text)
+        }
+    };
+    markup.into_string()
+}
+
+// Inline style for one line of the transcript, placed by who said it.
+fn chat_bubble_style(speaker: &str, theme: &Theme) -> String {
+    let (align, background) = match speaker {
+        "Child" => ("flex-end", theme.identified_background.as_str()),
+        "Teacher" => ("flex-start", theme.item_background.as_str()),
+        _ => ("center", theme.background.as_str()),
+    };
+    format!(
+        "align-self: {}; max-width: 80%; padding: 8px 12px; border-radius: 8px; border: 1px solid {}; background-color: {}; white-space: pre-wrap;",
+        align, theme.border, background
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::state::Practice;
+
+    #[test]
+    fn test_view_follows_status_and_events() {
+        let mut view = PracticeView::default();
+        view.apply(PracticeAction::Busy("Starting a new picture...".to_string()));
+        assert!(view.is_busy());
+
+        view.apply(PracticeAction::Event(SessionEvent::ImageReady {
+            hash: "ab12".to_string(),
+            mime_type: "image/png".to_string(),
+        }));
+        assert_eq!(view.image.as_ref().unwrap().hash, "ab12");
+
+        for text in [r#"{"feedback": "Yes, a "#, r#"red ball!", "score": 50}"#] {
+            view.apply(PracticeAction::Event(SessionEvent::EvaluationToken { text: text.to_string() }));
+        }
+        assert_eq!(view.live_feedback(), "Yes, a red ball!");
+
+        let practice = Practice {
+            active: Session {
+                attempt_limit: 5,
+                ..Session::default()
+            },
+            ..Practice::default()
+        };
+        view.apply(PracticeAction::Status(SessionStatus::from(&practice)));
+        assert!(!view.is_busy());
+        assert_eq!(view.live_feedback(), "");
+        assert!(view.attempts_html(&Theme::default()).contains("Attempts: 0/5"));
+
+        view.apply(PracticeAction::ChildSaid("<b>a red ball</b>".to_string()));
+        assert!(view.transcript_html(&Theme::default()).contains("&lt;b&gt;a red ball&lt;/b&gt;"));
+
+        view.apply(PracticeAction::Failed("No image provided.".to_string()));
+        assert_eq!(view.error.as_deref(), Some("No image provided."));
+        assert!(view.error_html(&Theme::default()).contains("No image provided."));
+        view.apply(PracticeAction::DismissError);
+        assert_eq!(view.error_html(&Theme::default()), "");
+    }
+
+    #[test]
+    fn test_settings_form_validates_selections() {
+        let form = SettingsForm {
+            treatment_plan: "  ".to_string(),
+            details_threshold: 80,
+            ..SettingsForm::default()
+        };
+        let request = form.to_request().unwrap();
+        assert_eq!(request.treatment_plan, None);
+        assert_eq!(request.details_threshold, Some(80.0));
+
+        let form = SettingsForm {
+            support_level: "Level 9".to_string(),
+            ..SettingsForm::default()
+        };
+        assert!(form.to_request().is_err());
+    }
+}
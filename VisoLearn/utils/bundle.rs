@@ -0,0 +1,364 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use zip::write::FileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+use crate::error::{Result, VisoLearnError};
+use crate::utils::image_store::{sha256_hex, ImageRef, ImageStore};
+use crate::utils::session::{ChecklistItem, Session, SESSION_SCHEMA_VERSION};
+
+// A `.visolearn` bundle is a zip archive holding everything needed to review or resume a run:
+//
+//   manifest.json    - format and schema versions, app version, creation time, image list
+//   sessions.json    - the sessions, exactly as serialized, image references included
+//   checklists.json  - one checklist per session, in the same order
+//   images/<sha256>.<ext>
+//
+// Images are named by the same SHA-256 the `ImageStore` uses, so an image shared by several
+// sessions is stored once, and the importer can check that nothing was damaged. Version 1
+// bundles wrote each session's image as its archive path instead of an `ImageRef`.
+
+pub const BUNDLE_EXTENSION: &str = "visolearn";
+pub const BUNDLE_FORMAT: &str = "visolearn-bundle";
+pub const BUNDLE_FORMAT_VERSION: u32 = 2;
+
+const MANIFEST: &str = "manifest.json";
+const SESSIONS: &str = "sessions.json";
+const CHECKLISTS: &str = "checklists.json";
+const IMAGE_DIR: &str = "images/";
+
+/// Describes a bundle's contents; written as `manifest.json`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct BundleManifest {
+    pub format: String,
+    pub format_version: u32,
+    pub app_version: String,
+    pub session_schema_version: u32,
+    pub created_at: String,
+    pub session_count: usize,
+    pub has_active_session: bool,
+    /// Archive path of every image, e.g. `images/3f2a...9c.png`.
+    pub images: Vec<String>,
+}
+
+/// The sessions and checklists stored in a bundle.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SessionBundle {
+    pub saved_sessions: Vec<Session>,
+    pub active_session: Option<Session>,
+    /// One checklist per session: the saved sessions' first, then the active session's.
+    pub checklists: Vec<Vec<ChecklistItem>>,
+}
+
+impl SessionBundle {
+    /// Builds a bundle from the app state. Saved sessions' checklists are rebuilt from their
+    /// key and identified details; the active session keeps its live `checklist`.
+    pub fn new(saved_sessions: &[Session], active_session: Option<&Session>, checklist: &[ChecklistItem]) -> Self {
+        let mut checklists: Vec<Vec<ChecklistItem>> = saved_sessions.iter().map(checklist_from_session).collect();
+        if active_session.is_some() {
+            checklists.push(checklist.to_vec());
+        }
+        Self {
+            saved_sessions: saved_sessions.to_vec(),
+            active_session: active_session.cloned(),
+            checklists,
+        }
+    }
+
+    fn sessions(&self) -> impl Iterator<Item = &Session> {
+        self.saved_sessions.iter().chain(self.active_session.iter())
+    }
+}
+
+/// Rebuilds a session's checklist from its key and identified details.
+pub fn checklist_from_session(session: &Session) -> Vec<ChecklistItem> {
+    session
+        .key_details
+        .iter()
+        .enumerate()
+        .map(|(id, detail)| {
+            let identified = session.identified_details.contains(detail);
+            ChecklistItem {
+                detail: detail.clone(),
+                identified,
+                id,
+                match_score: if identified { 1.0 } else { 0.0 },
+            }
+        })
+        .collect()
+}
+
+fn archive_name(image: &ImageRef) -> String {
+    format!("{}{}.{}", IMAGE_DIR, image.hash, image.extension())
+}
+
+/// Writes `bundle`, with its images from `images`, to `path` as a `.visolearn` archive and
+/// returns its manifest.
+pub fn export_bundle<P: AsRef<Path>>(path: P, bundle: &SessionBundle, images: &ImageStore) -> Result<BundleManifest> {
+    if bundle.checklists.len() != bundle.sessions().count() {
+        return Err(VisoLearnError::State(format!(
+            "bundle has {} checklists for {} sessions",
+            bundle.checklists.len(),
+            bundle.sessions().count()
+        )));
+    }
+
+    // Images by archive path; a BTreeMap keeps the archive order stable.
+    let mut files = BTreeMap::new();
+    let mut sessions = Vec::new();
+    for session in bundle.sessions() {
+        if let Some(image) = &session.image {
+            files.insert(archive_name(image), images.load(image)?);
+        }
+        sessions.push(session.to_json()?);
+    }
+
+    let manifest = BundleManifest {
+        format: BUNDLE_FORMAT.to_string(),
+        format_version: BUNDLE_FORMAT_VERSION,
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        session_schema_version: SESSION_SCHEMA_VERSION,
+        created_at: Local::now().to_rfc3339(),
+        session_count: sessions.len(),
+        has_active_session: bundle.active_session.is_some(),
+        images: files.keys().cloned().collect(),
+    };
+
+    let mut zip = ZipWriter::new(fs::File::create(path)?);
+    let text = FileOptions::default().compression_method(CompressionMethod::Deflated);
+    // Image formats are already compressed.
+    let stored = FileOptions::default().compression_method(CompressionMethod::Stored);
+
+    zip.start_file(MANIFEST, text)?;
+    zip.write_all(&serde_json::to_vec_pretty(&manifest)?)?;
+    zip.start_file(SESSIONS, text)?;
+    zip.write_all(&serde_json::to_vec_pretty(&sessions)?)?;
+    zip.start_file(CHECKLISTS, text)?;
+    zip.write_all(&serde_json::to_vec_pretty(&bundle.checklists)?)?;
+    for (name, bytes) in &files {
+        zip.start_file(name.as_str(), stored)?;
+        zip.write_all(bytes)?;
+    }
+    zip.finish()?;
+    Ok(manifest)
+}
+
+fn read_entry(archive: &mut ZipArchive<fs::File>, name: &str) -> Result<Vec<u8>> {
+    let mut entry = archive
+        .by_name(name)
+        .map_err(|_| VisoLearnError::Parse(format!("bundle: missing {}", name)))?;
+    let mut bytes = Vec::new();
+    entry.read_to_end(&mut bytes)?;
+    Ok(bytes)
+}
+
+/// Reads a `.visolearn` archive written by [`export_bundle`], adding its images to `images`.
+/// Sessions are migrated to the current schema, and every image is checked against the hash
+/// in its name.
+pub fn import_bundle<P: AsRef<Path>>(path: P, images: &ImageStore) -> Result<(BundleManifest, SessionBundle)> {
+    let mut archive = ZipArchive::new(fs::File::open(path)?)?;
+
+    let manifest: BundleManifest = serde_json::from_slice(&read_entry(&mut archive, MANIFEST)?)?;
+    if manifest.format != BUNDLE_FORMAT {
+        return Err(VisoLearnError::Parse(format!("bundle: unknown format \"{}\"", manifest.format)));
+    }
+    if manifest.format_version > BUNDLE_FORMAT_VERSION {
+        return Err(VisoLearnError::Parse(format!(
+            "bundle: format version {} is newer than this app supports ({})",
+            manifest.format_version, BUNDLE_FORMAT_VERSION
+        )));
+    }
+
+    let values: Vec<Value> = serde_json::from_slice(&read_entry(&mut archive, SESSIONS)?)?;
+    let checklists: Vec<Vec<ChecklistItem>> = serde_json::from_slice(&read_entry(&mut archive, CHECKLISTS)?)?;
+    if values.len() != manifest.session_count || checklists.len() != values.len() {
+        return Err(VisoLearnError::Parse(format!(
+            "bundle: manifest lists {} sessions but the archive has {} sessions and {} checklists",
+            manifest.session_count,
+            values.len(),
+            checklists.len()
+        )));
+    }
+
+    // Every listed image must be intact before any session refers to it. Version 1 sessions
+    // name their image by archive path, so remember where each one was stored.
+    let mut archive_refs: BTreeMap<String, ImageRef> = BTreeMap::new();
+    for name in &manifest.images {
+        let bytes = read_entry(&mut archive, name)?;
+        let hash = name
+            .strip_prefix(IMAGE_DIR)
+            .and_then(|file| file.split('.').next())
+            .ok_or_else(|| VisoLearnError::Parse(format!("bundle: bad image name {}", name)))?;
+        if sha256_hex(&bytes) != hash {
+            return Err(VisoLearnError::Parse(format!("bundle: {} is damaged (hash mismatch)", name)));
+        }
+        let image = images.put(bytes)?;
+        if manifest.format_version < 2 {
+            archive_refs.insert(name.clone(), image);
+        }
+    }
+
+    let mut sessions = Vec::with_capacity(values.len());
+    for mut value in values {
+        if let Some(image) = value.get("image").and_then(Value::as_str).and_then(|name| archive_refs.get(name)) {
+            value["image"] = serde_json::to_value(image)?;
+        }
+        let session = Session::from_json(value)?;
+        if let Some(image) = &session.image {
+            if !images.contains(image)? {
+                return Err(VisoLearnError::Parse(format!("bundle: missing image {}", archive_name(image))));
+            }
+        }
+        sessions.push(session);
+    }
+
+    let active_session = if manifest.has_active_session { sessions.pop() } else { None };
+    Ok((
+        manifest,
+        SessionBundle {
+            saved_sessions: sessions,
+            active_session,
+            checklists,
+        },
+    ))
+}
+
+/// Saves everything to `session_<ts>.visolearn` in `dir` and returns its path and manifest.
+pub fn save_session_bundle(
+    images: &ImageStore,
+    dir: &Path,
+    saved_sessions: &[Session],
+    active_session: &Session,
+    checklist: &[ChecklistItem],
+) -> Result<(PathBuf, BundleManifest)> {
+    let timestamp = Local::now().format("%Y%m%d_%H%M%S").to_string();
+    let filename = dir.join(format!("session_{}.{}", timestamp, BUNDLE_EXTENSION));
+    let active = active_session.prompt.is_some().then_some(active_session);
+
+    fs::create_dir_all(dir)?;
+    let manifest = export_bundle(&filename, &SessionBundle::new(saved_sessions, active, checklist), images)?;
+    Ok((filename, manifest))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Difficulty;
+    use crate::utils::session_store::new_session_id;
+
+    const PNG: &[u8] = b"\x89PNG\r\n\x1a\nnot really a png";
+    const JPEG: &[u8] = &[0xFF, 0xD8, 0xFF, 0xE0, 1, 2, 3];
+
+    fn bundle_path() -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("visolearn-{}.{}", new_session_id(), BUNDLE_EXTENSION))
+    }
+
+    // Writes an archive by hand, for bundles `export_bundle` would never produce.
+    fn write_archive(path: &Path, manifest: &BundleManifest, sessions: &[Value], files: &[(&str, &[u8])]) {
+        let mut zip = ZipWriter::new(fs::File::create(path).unwrap());
+        let options = FileOptions::default();
+        zip.start_file(MANIFEST, options).unwrap();
+        zip.write_all(&serde_json::to_vec(manifest).unwrap()).unwrap();
+        zip.start_file(SESSIONS, options).unwrap();
+        zip.write_all(&serde_json::to_vec(sessions).unwrap()).unwrap();
+        zip.start_file(CHECKLISTS, options).unwrap();
+        zip.write_all(&serde_json::to_vec(&vec![Vec::<ChecklistItem>::new(); sessions.len()]).unwrap())
+            .unwrap();
+        for (name, bytes) in files {
+            zip.start_file(*name, options).unwrap();
+            zip.write_all(bytes).unwrap();
+        }
+        zip.finish().unwrap();
+    }
+
+    #[test]
+    fn test_bundle_round_trips_exactly() {
+        let images = ImageStore::in_memory();
+        let mut first = Session::new();
+        first.prompt = Some("A red kite".to_string());
+        first.image = Some(images.put(PNG.to_vec()).unwrap());
+        first.key_details = vec!["red kite".to_string(), "blue sky".to_string()];
+        first.identified_details = vec!["red kite".to_string()];
+        first.completed = true;
+        let mut second = first.clone();
+        second.difficulty = Difficulty::Simple;
+        second.completed = false;
+        let mut active = Session::new();
+        active.prompt = Some("A cat".to_string());
+        active.image = Some(images.put(JPEG.to_vec()).unwrap());
+        active.chat.push(("Child".to_string(), "cat".to_string()));
+        let checklist = vec![ChecklistItem {
+            detail: "cat".to_string(),
+            identified: true,
+            id: 0,
+            match_score: 0.82,
+        }];
+
+        let bundle = SessionBundle::new(&[first, second], Some(&active), &checklist);
+        let path = bundle_path();
+        let manifest = export_bundle(&path, &bundle, &images).unwrap();
+        // Both saved sessions share one image file.
+        assert_eq!(manifest.images.len(), 2);
+        assert!(manifest.images.iter().any(|name| name.ends_with(".jpg")));
+
+        let fresh = ImageStore::in_memory();
+        let (read_manifest, imported) = import_bundle(&path, &fresh).unwrap();
+        assert_eq!(read_manifest, manifest);
+        assert_eq!(imported, bundle);
+        assert_eq!(fresh.load(active.image.as_ref().unwrap()).unwrap().as_slice(), JPEG);
+        assert_eq!(imported.checklists[0][1].detail, "blue sky");
+        assert!(!imported.checklists[0][1].identified);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_imports_version_1_bundles() {
+        let name = format!("{}{}.png", IMAGE_DIR, sha256_hex(PNG));
+        let mut session = Session::new().to_json().unwrap();
+        session["schema_version"] = Value::from(2);
+        session["image"] = Value::from(name.clone());
+        let manifest = BundleManifest {
+            format: BUNDLE_FORMAT.to_string(),
+            format_version: 1,
+            app_version: "0.1.0".to_string(),
+            session_schema_version: 2,
+            created_at: String::new(),
+            session_count: 1,
+            has_active_session: false,
+            images: vec![name.clone()],
+        };
+        let path = bundle_path();
+        write_archive(&path, &manifest, &[session], &[(name.as_str(), PNG)]);
+
+        let images = ImageStore::in_memory();
+        let (_, bundle) = import_bundle(&path, &images).unwrap();
+        let image = bundle.saved_sessions[0].image.clone().unwrap();
+        assert_eq!(images.load(&image).unwrap().as_slice(), PNG);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_rejects_damaged_images() {
+        let images = ImageStore::in_memory();
+        let mut session = Session::new();
+        session.image = Some(images.put(PNG.to_vec()).unwrap());
+        let path = bundle_path();
+        let manifest = export_bundle(&path, &SessionBundle::new(&[session.clone()], None, &[]), &images).unwrap();
+
+        // Rewrite the archive with different bytes under the same image name.
+        let tampered = b"\x89PNG\r\n\x1a\ntampered";
+        write_archive(&path, &manifest, &[session.to_json().unwrap()], &[(manifest.images[0].as_str(), tampered)]);
+
+        match import_bundle(&path, &ImageStore::in_memory()) {
+            Err(VisoLearnError::Parse(detail)) => assert!(detail.contains("hash mismatch"), "{}", detail),
+            other => panic!("expected a parse error, got {:?}", other.map(|(manifest, _)| manifest)),
+        }
+        fs::remove_file(&path).unwrap();
+    }
+}
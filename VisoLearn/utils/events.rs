@@ -0,0 +1,108 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast;
+
+use crate::config::Difficulty;
+use crate::error::Result;
+use crate::utils::session::ChecklistItem;
+
+// Each session has its own broadcast channel, created when the first client subscribes. The
+// practice loop publishes as it goes without knowing who, if anyone, is listening: publishing
+// to a session nobody watches does nothing, and a client that falls too far behind skips the
+// events it missed rather than holding up the loop.
+
+/// How many unread events a subscriber may fall behind by before older ones are dropped.
+const CHANNEL_CAPACITY: usize = 256;
+
+/// Something that happened in a practice session, in the order it happened.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SessionEvent {
+    /// A new image has been asked for, at `difficulty`.
+    GenerationStarted { difficulty: Difficulty },
+    PromptReady { prompt: String },
+    /// The image is in the image store under `hash`.
+    ImageReady { hash: String, mime_type: String },
+    DescriptionReady { description: String },
+    ChecklistReady { checklist: Vec<ChecklistItem> },
+    /// The next piece of the model's reply while it evaluates the learner's description.
+    EvaluationToken { text: String },
+    /// The evaluation is finished; `feedback` is what the teacher says.
+    EvaluationDone { feedback: String },
+    DifficultyChanged {
+        from: Difficulty,
+        to: Difficulty,
+        reason: String,
+    },
+    /// A step failed; the message is meant for the learner's therapist.
+    Error { message: String },
+}
+
+#[derive(Debug, Default)]
+pub struct EventBus {
+    channels: Mutex<HashMap<String, broadcast::Sender<SessionEvent>>>,
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Receives every event published for `session_id` from now on.
+    pub fn subscribe(&self, session_id: &str) -> Result<broadcast::Receiver<SessionEvent>> {
+        let mut channels = self.channels.lock()?;
+        let sender = channels
+            .entry(session_id.to_string())
+            .or_insert_with(|| broadcast::channel(CHANNEL_CAPACITY).0);
+        Ok(sender.subscribe())
+    }
+
+    pub fn publish(&self, session_id: &str, event: SessionEvent) -> Result<()> {
+        if let Some(sender) = self.channels.lock()?.get(session_id) {
+            // An error only means every subscriber has gone away.
+            let _ = sender.send(event);
+        }
+        Ok(())
+    }
+
+    /// Drops the channel for `session_id`, ending every subscription to it.
+    pub fn close(&self, session_id: &str) -> Result<()> {
+        self.channels.lock()?.remove(session_id);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_events_reach_only_their_session() {
+        let bus = EventBus::new();
+        bus.publish("a", SessionEvent::EvaluationDone { feedback: "unseen".to_string() })
+            .unwrap();
+
+        let mut a = bus.subscribe("a").unwrap();
+        let mut b = bus.subscribe("b").unwrap();
+        bus.publish("a", SessionEvent::EvaluationToken { text: "Gre".to_string() })
+            .unwrap();
+
+        assert_eq!(a.try_recv().unwrap(), SessionEvent::EvaluationToken { text: "Gre".to_string() });
+        assert!(a.try_recv().is_err());
+        assert!(b.try_recv().is_err());
+
+        bus.close("a").unwrap();
+        assert!(matches!(a.try_recv(), Err(broadcast::error::TryRecvError::Closed)));
+
+        let json = serde_json::to_value(SessionEvent::DifficultyChanged {
+            from: Difficulty::Simple,
+            to: Difficulty::Moderate,
+            reason: "two strong sessions".to_string(),
+        })
+        .unwrap();
+        assert_eq!(json["type"], "difficulty_changed");
+        assert_eq!(json["to"], "Moderate");
+    }
+}
@@ -1,135 +1,354 @@
 use std::fs;
 use std::io::Write;
-use std::path::Path;
-use chrono::Local;
+use std::path::{Path, PathBuf};
+use chrono::{Local, NaiveDateTime};
 use serde_json::{json, Value};
-use base64;
 
-/// Save all images from the saved sessions and active session to disk.
-fn save_all_session_images(saved_sessions: &[Value], active_session: &Value) -> String {
+use crate::error::{Result, VisoLearnError};
+use crate::utils::image_store::{ImageRef, ImageStore};
+use crate::utils::session::Session;
+
+/// Save all images from the saved sessions and active session to a new folder in `dir`.
+/// Returns the folder and how many images were saved.
+pub fn save_all_session_images(
+    images: &ImageStore,
+    dir: &Path,
+    saved_sessions: &[Session],
+    active_session: &Session,
+) -> Result<(PathBuf, usize)> {
     let timestamp = Local::now().format("%Y%m%d_%H%M%S").to_string();
     let mut saved_count = 0;
     
     // Create a directory for the images if it doesn't exist
-    let output_dir = format!("saved_images_{}", timestamp);
-    fs::create_dir_all(&output_dir).unwrap_or_else(|e| {
-        eprintln!("Error creating directory: {}", e);
-    });
+    let output_dir = dir.join(format!("saved_images_{}", timestamp));
+    fs::create_dir_all(&output_dir)?;
     
     // Save images from saved sessions
     for (i, session) in saved_sessions.iter().enumerate() {
-        if let Some(image) = session.get("image")
-                                   .and_then(|i| i.as_str())
-                                   .filter(|url| url.starts_with("data:image")) {
-            let filename = format!("{}/session_{}_{}.png", output_dir, i, timestamp);
-            if save_image_from_data_url(image, &filename) {
-                saved_count += 1;
-            }
+        if let Some(image) = &session.image {
+            let filename = output_dir.join(format!("session_{}_{}.{}", i, timestamp, image.extension()));
+            save_image(images, image, &filename)?;
+            saved_count += 1;
         }
     }
     
     // Save image from active session if it exists
-    if let Some(image) = active_session.get("image")
-                                     .and_then(|i| i.as_str())
-                                     .filter(|url| url.starts_with("data:image")) {
-        let filename = format!("{}/active_session_{}.png", output_dir, timestamp);
-        if save_image_from_data_url(image, &filename) {
-            saved_count += 1;
-        }
+    if let Some(image) = &active_session.image {
+        let filename = output_dir.join(format!("active_session_{}.{}", timestamp, image.extension()));
+        save_image(images, image, &filename)?;
+        saved_count += 1;
     }
     
-    format!("✅ Successfully saved {} images to folder: {}", saved_count, output_dir)
+    Ok((output_dir, saved_count))
 }
 
-/// Extract base64 data from a data URL, decode it, and save it as an image file.
-fn save_image_from_data_url(data_url: &str, filename: &str) -> bool {
-    if !data_url.starts_with("data:image") {
-        println!("Invalid data URL format");
-        return false;
-    }
+/// Copy a stored image's original bytes to a file.
+fn save_image(images: &ImageStore, image: &ImageRef, filename: &Path) -> Result<()> {
+    let bytes = images.load(image)?;
+    let mut file = fs::File::create(filename)?;
+    file.write_all(&bytes)?;
+    log::debug!("Saved image to {}", filename.display());
+    Ok(())
+}
+
+/// Save all session data (including active session) to a JSON file in `dir` and return its path.
+pub fn save_session_log(dir: &Path, saved_sessions: &[Session], active_session: &Session) -> Result<PathBuf> {
+    let timestamp = Local::now().format("%Y%m%d_%H%M%S").to_string();
+    let filename = dir.join(format!("session_log_{}.json", timestamp));
+    let active = active_session.prompt.is_some().then_some(active_session);
     
-    match data_url.split(',').nth(1) {
-        Some(base64_data) => {
-            match base64::decode(base64_data) {
-                Ok(image_data) => {
-                    match fs::File::create(filename) {
-                        Ok(mut file) => {
-                            match file.write_all(&image_data) {
-                                Ok(_) => {
-                                    println!("Successfully saved image to {}", filename);
-                                    true
-                                },
-                                Err(e) => {
-                                    println!("Error writing image data: {}", e);
-                                    false
-                                }
-                            }
-                        },
-                        Err(e) => {
-                            println!("Error creating file: {}", e);
-                            false
-                        }
-                    }
-                },
-                Err(e) => {
-                    println!("Error decoding base64 data: {}", e);
-                    false
-                }
-            }
+    // Sessions only hold image references, so the log stays small; the images themselves
+    // are saved by `save_all_session_images`.
+    fs::create_dir_all(dir)?;
+    write_session_log(&filename, saved_sessions, active)?;
+    Ok(filename)
+}
+
+/// The sessions in a session log. The active session is kept apart from the saved ones, so a
+/// reader never has to guess which entry was still being played.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SessionLog {
+    pub saved_sessions: Vec<Session>,
+    pub active_session: Option<Session>,
+}
+
+/// Writes the sessions to `path` as a session log that [`load_session_log`] can read back.
+pub fn write_session_log<P: AsRef<Path>>(path: P, saved_sessions: &[Session], active_session: Option<&Session>) -> Result<()> {
+    let json_str = serde_json::to_string_pretty(&json!({
+        "saved_sessions": saved_sessions,
+        "active_session": active_session,
+    }))?;
+    fs::write(path, json_str)?;
+    Ok(())
+}
+
+/// Reads a log written by [`save_session_log`] (or by older versions of VisoLearn) into typed
+/// sessions. Every entry is migrated to the current schema and checked; an entry that cannot be
+/// read fails the whole load with its position in the log. Images that were stripped from the
+/// log, or are missing from `images`, are re-linked from the matching `saved_images_*` folder
+/// next to it, if there is one.
+///
+/// Older logs are a bare array of sessions; their last entry is taken as the active session if
+/// it was not completed.
+pub fn load_session_log<P: AsRef<Path>>(path: P, images: &ImageStore) -> Result<SessionLog> {
+    let path = path.as_ref();
+    let value: Value = serde_json::from_str(&fs::read_to_string(path)?)?;
+    let mut log = parse_session_log(value, images)?;
+
+    if let Some(image_dir) = find_image_dir(path) {
+        let relinked = relink_images(&mut log, &image_dir, images);
+        log::info!("Re-linked {} images from {}", relinked, image_dir.display());
+    }
+    Ok(log)
+}
+
+/// Loads a session log for resuming: returns the finished sessions and the session to continue.
+/// If the log has no active session, a new one is started with [`next_session`].
+pub fn load_project<P: AsRef<Path>>(path: P, images: &ImageStore) -> Result<(Vec<Session>, Session)> {
+    let SessionLog {
+        saved_sessions,
+        active_session,
+    } = load_session_log(path, images)?;
+    let active_session = active_session.unwrap_or_else(|| next_session(&saved_sessions));
+    Ok((saved_sessions, active_session))
+}
+
+/// A new session with the same learner and settings as the last of `saved_sessions`.
+pub fn next_session(saved_sessions: &[Session]) -> Session {
+    match saved_sessions.last() {
+        Some(last) => Session {
+            id: last.id.clone(),
+            learner_id: last.learner_id.clone(),
+            treatment_plan: last.treatment_plan.clone(),
+            topic_focus: last.topic_focus.clone(),
+            difficulty: last.difficulty,
+            difficulty_reason: last.difficulty_reason.clone(),
+            autism_level: last.autism_level,
+            age: last.age.clone(),
+            attempt_limit: last.attempt_limit,
+            details_threshold: last.details_threshold,
+            image_style: last.image_style,
+            ..Session::default()
         },
-        None => {
-            println!("Invalid data URL format - couldn't split base64 data");
-            false
-        }
+        None => Session::new(),
     }
 }
 
-/// Save all session data (including active session) to a JSON file.
-fn save_session_log(saved_sessions: &[Value], active_session: &Value) -> String {
-    let timestamp = Local::now().format("%Y%m%d_%H%M%S").to_string();
-    let filename = format!("session_log_{}.json", timestamp);
-    
-    // Combine all sessions
-    let mut all_sessions = saved_sessions.to_vec();
-    if active_session.get("prompt").is_some() {
-        all_sessions.push(active_session.clone());
+fn parse_session_log(value: Value, images: &ImageStore) -> Result<SessionLog> {
+    let (entries, has_active) = match value {
+        Value::Object(mut log) if log.contains_key("saved_sessions") => {
+            let mut entries = match log.remove("saved_sessions") {
+                Some(Value::Array(entries)) => entries,
+                _ => return Err(VisoLearnError::Parse("session log: saved_sessions must be an array".to_string())),
+            };
+            let active = log.remove("active_session").filter(|active| !active.is_null());
+            let has_active = active.is_some();
+            entries.extend(active);
+            (entries, has_active)
+        }
+        Value::Array(entries) => {
+            let has_active = entries
+                .last()
+                .is_some_and(|last| !last.get("completed").and_then(Value::as_bool).unwrap_or(false));
+            (entries, has_active)
+        }
+        _ => return Err(VisoLearnError::Parse("session log: expected a JSON array of sessions".to_string())),
+    };
+    let mut saved_sessions = entries
+        .into_iter()
+        .enumerate()
+        .map(|(i, entry)| {
+            let session = Session::from_json_with_images(entry, images)
+                .map_err(|e| VisoLearnError::Parse(format!("session log entry {}: {}", i + 1, e)))?;
+            validate_session(&session)
+                .map_err(|detail| VisoLearnError::Parse(format!("session log entry {}: {}", i + 1, detail)))?;
+            Ok(session)
+        })
+        .collect::<Result<Vec<Session>>>()?;
+    let active_session = if has_active { saved_sessions.pop() } else { None };
+    Ok(SessionLog {
+        saved_sessions,
+        active_session,
+    })
+}
+
+// Checks what serde cannot: values that are well-typed but could not have come from a real run.
+fn validate_session(session: &Session) -> std::result::Result<(), String> {
+    if !(0.0..=1.0).contains(&session.details_threshold) {
+        return Err(format!("details_threshold {} is outside 0-1", session.details_threshold));
     }
-    
-    // Clean up the sessions for saving (removing data URLs to reduce file size)
-    let clean_sessions: Vec<Value> = all_sessions.iter().map(|session| {
-        let mut clean_session = session.clone();
-        
-        if clean_session.get("image").is_some() {
-            clean_session["image"] = json!("[IMAGE_DATA_REMOVED]");
+    if let Some(detail) = session
+        .identified_details
+        .iter()
+        .find(|detail| !session.key_details.contains(detail))
+    {
+        return Err(format!("identified detail \"{}\" is not one of the key details", detail));
+    }
+    Ok(())
+}
+
+// `save_all_session_images` and `save_session_log` each stamp their output with the time they
+// ran, so the image folder for a log is the `saved_images_*` sibling closest in time to it.
+fn find_image_dir(log_path: &Path) -> Option<PathBuf> {
+    let parse = |stamp: &str| NaiveDateTime::parse_from_str(stamp, "%Y%m%d_%H%M%S").ok();
+    let log_time = parse(log_path.file_stem()?.to_str()?.strip_prefix("session_log_")?)?;
+    let parent = match log_path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+
+    fs::read_dir(parent)
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().is_dir())
+        .filter_map(|entry| {
+            let name = entry.file_name().to_str()?.to_string();
+            let time = parse(name.strip_prefix("saved_images_")?)?;
+            Some(((time - log_time).num_seconds().abs(), entry.path()))
+        })
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, path)| path)
+}
+
+/// Fills in missing images from files written by [`save_all_session_images`]: the i-th saved
+/// session from `session_<i>_*` and the active session from `active_session_*`. The files are
+/// added to `images`. Returns how many images were re-linked.
+pub fn relink_images(log: &mut SessionLog, image_dir: &Path, images: &ImageStore) -> usize {
+    let files: Vec<PathBuf> = match fs::read_dir(image_dir) {
+        Ok(entries) => entries.filter_map(|entry| entry.ok()).map(|entry| entry.path()).collect(),
+        Err(_) => return 0,
+    };
+    let find = |prefix: &str| {
+        files.iter().find(|file| {
+            let name = file.file_name().and_then(|name| name.to_str()).unwrap_or_default();
+            name.starts_with(prefix)
+        })
+    };
+
+    let saved = log
+        .saved_sessions
+        .iter_mut()
+        .enumerate()
+        .map(|(i, session)| (format!("session_{}_", i), session));
+    let active = log
+        .active_session
+        .iter_mut()
+        .map(|session| ("active_session_".to_string(), session));
+    let mut relinked = 0;
+    for (prefix, session) in saved.chain(active) {
+        let missing = match &session.image {
+            Some(image) => !images.contains(image).unwrap_or(false),
+            None => true,
+        };
+        if !missing {
+            continue;
         }
-        
-        clean_session
-    }).collect();
-    
-    // Save to file
-    match serde_json::to_string_pretty(&clean_sessions) {
-        Ok(json_str) => {
-            match fs::write(&filename, json_str) {
-                Ok(_) => format!("✅ Session log saved to: {}", filename),
-                Err(e) => {
-                    println!("Error writing session log: {}", e);
-                    format!("❌ Error saving session log: {}", e)
-                }
+        let Some(file) = find(&prefix) else {
+            continue;
+        };
+        match fs::read(file).map_err(VisoLearnError::from).and_then(|bytes| images.put(bytes)) {
+            Ok(image) => {
+                session.image = Some(image);
+                relinked += 1;
             }
-        },
-        Err(e) => {
-            println!("Error serializing session data: {}", e);
-            format!("❌ Error saving session log: {}", e)
+            Err(e) => log::warn!("Could not read {}: {}", file.display(), e),
         }
     }
+    relinked
 }
 
-// Example of how you might use these functions in a main function
-fn main() {
-    // This would be your actual data
-    let saved_sessions: Vec<Value> = vec![];
-    let active_session = json!({});
-    
-    println!("{}", save_all_session_images(&saved_sessions, &active_session));
-    println!("{}", save_session_log(&saved_sessions, &active_session));
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::session_store::new_session_id;
+
+    #[test]
+    fn test_load_project_relinks_images_and_resumes() {
+        let dir = std::env::temp_dir().join(format!("visolearn-log-{}", new_session_id()));
+        let images = dir.join("saved_images_20240101_120003");
+        fs::create_dir_all(&images).unwrap();
+        let (first, second) = (b"\x89PNG\r\n\x1a\nfirst", [0xFF, 0xD8, 0xFF, 0xE0, b'2']);
+        fs::write(images.join("session_0_20240101_120003.png"), first).unwrap();
+        fs::write(images.join("active_session_20240101_120003.jpg"), second).unwrap();
+
+        let log = json!([
+            {
+                "prompt": "A cat on a mat",
+                "image": "[IMAGE_DATA_REMOVED]",
+                "key_details": ["cat", "mat"],
+                "identified_details": ["cat"],
+                "difficulty": "Simple",
+                "completed": true
+            },
+            {
+                "schema_version": 1,
+                "prompt": "A dog in the rain",
+                "key_details": ["dog"],
+                "difficulty": "Moderate",
+                "used_hints": ["Look at the bottom."]
+            }
+        ]);
+        let log_path = dir.join("session_log_20240101_120000.json");
+        fs::write(&log_path, log.to_string()).unwrap();
+
+        let store = ImageStore::in_memory();
+        let (saved, active) = load_project(&log_path, &store).unwrap();
+        assert_eq!(saved.len(), 1);
+        assert_eq!(store.load(saved[0].image.as_ref().unwrap()).unwrap().as_slice(), first);
+        assert_eq!(active.prompt.as_deref(), Some("A dog in the rain"));
+        let active_image = active.image.as_ref().unwrap();
+        assert_eq!(active_image.mime_type, "image/jpeg");
+        assert_eq!(store.load(active_image).unwrap().as_slice(), second);
+        assert_eq!(active.used_hints.len(), 1);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_relinks_an_abandoned_last_session_as_saved() {
+        let dir = std::env::temp_dir().join(format!("visolearn-log-{}", new_session_id()));
+        let images = dir.join("saved_images_20240101_120000");
+        fs::create_dir_all(&images).unwrap();
+        let (first, second) = (b"\x89PNG\r\n\x1a\nfirst", b"\x89PNG\r\n\x1a\nsecond");
+        fs::write(images.join("session_0_20240101_120000.png"), first).unwrap();
+        fs::write(images.join("session_1_20240101_120000.png"), second).unwrap();
+
+        // The second image was skipped, so it was saved without being completed, and nothing
+        // was active when the log was written.
+        let finished = Session {
+            prompt: Some("A cat on a mat".to_string()),
+            completed: true,
+            ..Session::default()
+        };
+        let abandoned = Session {
+            prompt: Some("A dog in the rain".to_string()),
+            ..Session::default()
+        };
+        let log_path = dir.join("session_log_20240101_120000.json");
+        write_session_log(&log_path, &[finished, abandoned], None).unwrap();
+
+        let store = ImageStore::in_memory();
+        let log = load_session_log(&log_path, &store).unwrap();
+        assert_eq!(log.active_session, None);
+        assert_eq!(store.load(log.saved_sessions[0].image.as_ref().unwrap()).unwrap().as_slice(), first);
+        assert_eq!(store.load(log.saved_sessions[1].image.as_ref().unwrap()).unwrap().as_slice(), second);
+
+        let (saved, active) = load_project(&log_path, &store).unwrap();
+        assert_eq!(saved.len(), 2);
+        assert_eq!(active.prompt, None);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_rejects_invalid_logs() {
+        let images = ImageStore::in_memory();
+        assert!(matches!(parse_session_log(json!({ "sessions": [] }), &images), Err(VisoLearnError::Parse(_))));
+
+        let bad = json!([{}, { "key_details": ["cat"], "identified_details": ["dog"] }]);
+        match parse_session_log(bad, &images) {
+            Err(VisoLearnError::Parse(detail)) => assert!(detail.starts_with("session log entry 2"), "{}", detail),
+            other => panic!("expected a parse error, got {:?}", other),
+        }
+    }
 }
\ No newline at end of file
@@ -0,0 +1,284 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use base64::{engine::general_purpose, Engine as _};
+use image::{DynamicImage, ImageOutputFormat};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::error::{Result, VisoLearnError};
+use crate::utils::session::Session;
+
+// Image bytes are stored once, under the SHA-256 of their contents, and sessions keep only an
+// `ImageRef`. On disk each image lives at `<root>/<first two hex digits>/<hash>`; files are
+// read the first time they are needed and then served from memory.
+
+/// A stored image: the SHA-256 of its bytes and their detected MIME type.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ImageRef {
+    pub hash: String,
+    pub mime_type: String,
+}
+
+impl ImageRef {
+    /// File extension for the MIME type, e.g. "png" or "jpg".
+    pub fn extension(&self) -> &'static str {
+        match self.mime_type.as_str() {
+            "image/jpeg" => "jpg",
+            "image/gif" => "gif",
+            "image/webp" => "webp",
+            "image/bmp" => "bmp",
+            _ => "png",
+        }
+    }
+}
+
+/// Detects the MIME type of image bytes from their signature.
+pub fn detect_mime(bytes: &[u8]) -> Option<&'static str> {
+    if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+        Some("image/png")
+    } else if bytes.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        Some("image/jpeg")
+    } else if bytes.starts_with(b"GIF87a") || bytes.starts_with(b"GIF89a") {
+        Some("image/gif")
+    } else if bytes.len() >= 12 && &bytes[..4] == b"RIFF" && &bytes[8..12] == b"WEBP" {
+        Some("image/webp")
+    } else if bytes.starts_with(b"BM") {
+        Some("image/bmp")
+    } else {
+        None
+    }
+}
+
+pub fn sha256_hex(bytes: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(bytes))
+}
+
+/// Splits a `data:<mime>;base64,<payload>` URL into its MIME type and decoded bytes.
+pub fn decode_data_url(data_url: &str) -> Option<(String, Vec<u8>)> {
+    let (header, payload) = data_url.strip_prefix("data:")?.split_once(',')?;
+    let mime_type = header.strip_suffix(";base64")?;
+    let bytes = general_purpose::STANDARD.decode(payload.trim()).ok()?;
+    Some((mime_type.to_string(), bytes))
+}
+
+/// What [`ImageStore::collect_garbage`] removed.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GcReport {
+    pub removed: usize,
+    pub bytes_freed: u64,
+}
+
+/// Content-addressed image storage shared by every session.
+#[derive(Debug)]
+pub struct ImageStore {
+    root: Option<PathBuf>,
+    cache: Mutex<HashMap<String, Arc<Vec<u8>>>>,
+}
+
+impl ImageStore {
+    /// Opens (creating if needed) a store rooted at `root`.
+    pub fn open<P: AsRef<Path>>(root: P) -> Result<Self> {
+        fs::create_dir_all(root.as_ref())?;
+        Ok(Self {
+            root: Some(root.as_ref().to_path_buf()),
+            cache: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// A store that keeps images in memory only, for tests and short-lived tools.
+    pub fn in_memory() -> Self {
+        Self {
+            root: None,
+            cache: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn path_for(&self, hash: &str) -> Option<PathBuf> {
+        let root = self.root.as_ref()?;
+        Some(root.join(&hash[..2.min(hash.len())]).join(hash))
+    }
+
+    /// Stores `bytes` (a no-op if they are already stored) and returns their reference.
+    pub fn put(&self, bytes: Vec<u8>) -> Result<ImageRef> {
+        let mime_type = detect_mime(&bytes)
+            .ok_or_else(|| VisoLearnError::ImageDecode("unrecognised image format".to_string()))?;
+        let image = ImageRef {
+            hash: sha256_hex(&bytes),
+            mime_type: mime_type.to_string(),
+        };
+
+        if let Some(path) = self.path_for(&image.hash) {
+            if !path.exists() {
+                if let Some(dir) = path.parent() {
+                    fs::create_dir_all(dir)?;
+                }
+                // Write then rename, so a crash never leaves a truncated image under a valid hash.
+                let partial = path.with_extension("partial");
+                fs::write(&partial, &bytes)?;
+                fs::rename(&partial, &path)?;
+            }
+        }
+        self.cache.lock()?.insert(image.hash.clone(), Arc::new(bytes));
+        Ok(image)
+    }
+
+    /// Encodes `image` as PNG and stores it.
+    pub fn put_image(&self, image: &DynamicImage) -> Result<ImageRef> {
+        let mut bytes = Vec::new();
+        image.write_to(&mut Cursor::new(&mut bytes), ImageOutputFormat::Png)?;
+        self.put(bytes)
+    }
+
+    /// Stores the image in a base64 data URL.
+    pub fn put_data_url(&self, data_url: &str) -> Result<ImageRef> {
+        let (_, bytes) = decode_data_url(data_url)
+            .ok_or_else(|| VisoLearnError::ImageDecode("not a base64 data URL".to_string()))?;
+        self.put(bytes)
+    }
+
+    pub fn contains(&self, image: &ImageRef) -> Result<bool> {
+        if self.cache.lock()?.contains_key(&image.hash) {
+            return Ok(true);
+        }
+        Ok(self.path_for(&image.hash).is_some_and(|path| path.exists()))
+    }
+
+    /// The image's bytes, read from disk on first use. Fails with `NoImage` if it isn't stored.
+    pub fn load(&self, image: &ImageRef) -> Result<Arc<Vec<u8>>> {
+        if let Some(bytes) = self.cache.lock()?.get(&image.hash) {
+            return Ok(Arc::clone(bytes));
+        }
+        let path = self.path_for(&image.hash).ok_or(VisoLearnError::NoImage)?;
+        let bytes = match fs::read(&path) {
+            Ok(bytes) => bytes,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Err(VisoLearnError::NoImage),
+            Err(e) => return Err(e.into()),
+        };
+        if sha256_hex(&bytes) != image.hash {
+            return Err(VisoLearnError::ImageDecode(format!("stored image {} is damaged", image.hash)));
+        }
+        let bytes = Arc::new(bytes);
+        self.cache.lock()?.insert(image.hash.clone(), Arc::clone(&bytes));
+        Ok(bytes)
+    }
+
+    pub fn load_image(&self, image: &ImageRef) -> Result<DynamicImage> {
+        Ok(image::load_from_memory(&self.load(image)?)?)
+    }
+
+    /// The image as a `data:` URL, for HTML views and exports.
+    pub fn data_url(&self, image: &ImageRef) -> Result<String> {
+        Ok(format!(
+            "data:{};base64,{}",
+            image.mime_type,
+            general_purpose::STANDARD.encode(self.load(image)?.as_slice())
+        ))
+    }
+
+    /// Deletes every stored image whose hash is not in `referenced`.
+    pub fn collect_garbage(&self, referenced: &HashSet<String>) -> Result<GcReport> {
+        let mut report = GcReport::default();
+        let mut cache = self.cache.lock()?;
+        cache.retain(|hash, bytes| {
+            let keep = referenced.contains(hash);
+            if !keep && self.root.is_none() {
+                report.removed += 1;
+                report.bytes_freed += bytes.len() as u64;
+            }
+            keep
+        });
+        drop(cache);
+
+        let Some(root) = &self.root else {
+            return Ok(report);
+        };
+        for shard in fs::read_dir(root)? {
+            let shard = shard?.path();
+            if !shard.is_dir() {
+                continue;
+            }
+            for file in fs::read_dir(&shard)? {
+                let path = file?.path();
+                let name = path.file_name().and_then(|name| name.to_str()).unwrap_or_default();
+                if referenced.contains(name) {
+                    continue;
+                }
+                report.bytes_freed += fs::metadata(&path)?.len();
+                fs::remove_file(&path)?;
+                report.removed += 1;
+            }
+            let _ = fs::remove_dir(&shard); // Only succeeds once the shard is empty.
+        }
+        Ok(report)
+    }
+
+    /// Deletes every stored image not used by one of `sessions`.
+    pub fn collect_unreferenced<'a, I>(&self, sessions: I) -> Result<GcReport>
+    where
+        I: IntoIterator<Item = &'a Session>,
+    {
+        let referenced = sessions
+            .into_iter()
+            .filter_map(|session| session.image.as_ref())
+            .map(|image| image.hash.clone())
+            .collect();
+        self.collect_garbage(&referenced)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::session_store::new_session_id;
+
+    fn png(width: u32) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        DynamicImage::new_rgb8(width, 1)
+            .write_to(&mut Cursor::new(&mut bytes), ImageOutputFormat::Png)
+            .unwrap();
+        bytes
+    }
+
+    #[test]
+    fn test_detects_mime_types() {
+        assert_eq!(detect_mime(&png(1)), Some("image/png"));
+        assert_eq!(detect_mime(&[0xFF, 0xD8, 0xFF, 0xE0]), Some("image/jpeg"));
+        assert_eq!(detect_mime(b"RIFF\0\0\0\0WEBPVP8 "), Some("image/webp"));
+        assert_eq!(detect_mime(b"hello"), None);
+        assert!(matches!(ImageStore::in_memory().put(b"hello".to_vec()), Err(VisoLearnError::ImageDecode(_))));
+    }
+
+    #[test]
+    fn test_images_load_lazily_and_are_garbage_collected() {
+        let root = std::env::temp_dir().join(format!("visolearn-images-{}", new_session_id()));
+        let (kept, dropped) = {
+            let store = ImageStore::open(&root).unwrap();
+            let kept = store.put(png(2)).unwrap();
+            // The same bytes always give the same reference.
+            assert_eq!(store.put(png(2)).unwrap(), kept);
+            (kept, store.put(png(3)).unwrap())
+        };
+        assert_eq!(kept.mime_type, "image/png");
+        assert_eq!(kept.extension(), "png");
+
+        // A new store finds the images on disk.
+        let store = ImageStore::open(&root).unwrap();
+        assert_eq!(store.cache.lock().unwrap().len(), 0);
+        assert_eq!(store.load_image(&dropped).unwrap().width(), 3);
+        assert!(store.data_url(&kept).unwrap().starts_with("data:image/png;base64,"));
+
+        let mut session = Session::new();
+        session.image = Some(kept.clone());
+        let report = store.collect_unreferenced([&session]).unwrap();
+        assert_eq!(report.removed, 1);
+        assert!(store.contains(&kept).unwrap());
+        assert!(!store.contains(&dropped).unwrap());
+        assert!(matches!(store.load(&dropped), Err(VisoLearnError::NoImage)));
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
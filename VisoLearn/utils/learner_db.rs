@@ -0,0 +1,319 @@
+use std::collections::HashSet;
+use std::path::Path;
+use std::sync::Mutex;
+
+use rusqlite::{params, Connection, OptionalExtension, Row};
+
+use crate::config::{ImageStyle, SupportLevel};
+use crate::error::{Result, VisoLearnError};
+use crate::utils::image_store::ImageStore;
+use crate::utils::learner_profile::LearnerProfile;
+use crate::utils::session::Session;
+
+// Profiles are stored as columns; sessions are stored as their serialized JSON so that
+// older rows go through `Session::from_json` and its schema migrations when loaded.
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS learners (
+        id                    TEXT PRIMARY KEY,
+        name                  TEXT NOT NULL,
+        age                   TEXT NOT NULL,
+        support_level         TEXT NOT NULL,
+        treatment_plan        TEXT,
+        topic_focus           TEXT,
+        preferred_styles      TEXT NOT NULL DEFAULT '[]',
+        sensory_sensitivities TEXT NOT NULL DEFAULT '[]'
+    );
+    CREATE TABLE IF NOT EXISTS sessions (
+        seq        INTEGER PRIMARY KEY AUTOINCREMENT,
+        learner_id TEXT NOT NULL REFERENCES learners(id) ON DELETE CASCADE,
+        session_id TEXT NOT NULL,
+        active     INTEGER NOT NULL,
+        data       TEXT NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS sessions_by_learner ON sessions (learner_id, seq);
+";
+
+/// A learner's saved program: their profile, finished sessions (oldest first) and the
+/// session that was in progress when it was last saved.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LearnerProgram {
+    pub profile: LearnerProfile,
+    pub saved_sessions: Vec<Session>,
+    pub active_session: Option<Session>,
+}
+
+/// Local SQLite storage for learner profiles and their sessions.
+pub struct LearnerDatabase {
+    conn: Mutex<Connection>,
+}
+
+impl LearnerDatabase {
+    /// Opens (creating if needed) the database at `path`.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::init(Connection::open(path)?)
+    }
+
+    pub fn open_in_memory() -> Result<Self> {
+        Self::init(Connection::open_in_memory()?)
+    }
+
+    fn init(conn: Connection) -> Result<Self> {
+        conn.execute_batch("PRAGMA foreign_keys = ON;")?;
+        conn.execute_batch(SCHEMA)?;
+        Ok(Self {
+            conn: Mutex::new(conn),
+        })
+    }
+
+    /// Inserts or replaces a profile.
+    pub fn save_profile(&self, profile: &LearnerProfile) -> Result<()> {
+        let styles: Vec<&str> = profile.preferred_styles.iter().map(ImageStyle::as_str).collect();
+        self.conn.lock()?.execute(
+            "INSERT INTO learners
+                 (id, name, age, support_level, treatment_plan, topic_focus, preferred_styles, sensory_sensitivities)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+             ON CONFLICT(id) DO UPDATE SET
+                 name = excluded.name,
+                 age = excluded.age,
+                 support_level = excluded.support_level,
+                 treatment_plan = excluded.treatment_plan,
+                 topic_focus = excluded.topic_focus,
+                 preferred_styles = excluded.preferred_styles,
+                 sensory_sensitivities = excluded.sensory_sensitivities",
+            params![
+                profile.id,
+                profile.name,
+                profile.age,
+                profile.support_level.as_str(),
+                profile.treatment_plan,
+                profile.topic_focus,
+                serde_json::to_string(&styles)?,
+                serde_json::to_string(&profile.sensory_sensitivities)?,
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub fn get_profile(&self, learner_id: &str) -> Result<Option<LearnerProfile>> {
+        let conn = self.conn.lock()?;
+        let row = conn
+            .query_row(
+                "SELECT id, name, age, support_level, treatment_plan, topic_focus, preferred_styles, sensory_sensitivities
+                 FROM learners WHERE id = ?1",
+                params![learner_id],
+                ProfileRow::read,
+            )
+            .optional()?;
+        row.map(ProfileRow::into_profile).transpose()
+    }
+
+    /// Every profile, sorted by name.
+    pub fn list_profiles(&self) -> Result<Vec<LearnerProfile>> {
+        let conn = self.conn.lock()?;
+        let mut stmt = conn.prepare(
+            "SELECT id, name, age, support_level, treatment_plan, topic_focus, preferred_styles, sensory_sensitivities
+             FROM learners ORDER BY name COLLATE NOCASE, id",
+        )?;
+        let rows = stmt.query_map([], ProfileRow::read)?.collect::<rusqlite::Result<Vec<_>>>()?;
+        rows.into_iter().map(ProfileRow::into_profile).collect()
+    }
+
+    /// Deletes a profile and all of its sessions. Returns whether the profile existed.
+    pub fn delete_profile(&self, learner_id: &str) -> Result<bool> {
+        let deleted = self
+            .conn
+            .lock()?
+            .execute("DELETE FROM learners WHERE id = ?1", params![learner_id])?;
+        Ok(deleted > 0)
+    }
+
+    /// Replaces the stored sessions for `learner_id` with `saved_sessions` and `active_session`.
+    /// Every session is linked to the learner before it is written.
+    pub fn save_program(&self, learner_id: &str, saved_sessions: &[Session], active_session: Option<&Session>) -> Result<()> {
+        let mut conn = self.conn.lock()?;
+        let tx = conn.transaction()?;
+        let known: bool = tx.query_row(
+            "SELECT EXISTS(SELECT 1 FROM learners WHERE id = ?1)",
+            params![learner_id],
+            |row| row.get(0),
+        )?;
+        if !known {
+            return Err(VisoLearnError::State(format!("no learner profile with id {}", learner_id)));
+        }
+
+        tx.execute("DELETE FROM sessions WHERE learner_id = ?1", params![learner_id])?;
+        {
+            let mut insert = tx.prepare(
+                "INSERT INTO sessions (learner_id, session_id, active, data) VALUES (?1, ?2, ?3, ?4)",
+            )?;
+            let sessions = saved_sessions
+                .iter()
+                .map(|session| (session, false))
+                .chain(active_session.map(|session| (session, true)));
+            for (session, active) in sessions {
+                let mut session = session.clone();
+                session.learner_id = Some(learner_id.to_string());
+                insert.execute(params![learner_id, session.id, active, session.to_json()?.to_string()])?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Loads a learner's profile and sessions, or `None` if there is no such learner.
+    /// Images saved inline by older versions are moved into `images`.
+    pub fn load_program(&self, learner_id: &str, images: &ImageStore) -> Result<Option<LearnerProgram>> {
+        let Some(profile) = self.get_profile(learner_id)? else {
+            return Ok(None);
+        };
+
+        let conn = self.conn.lock()?;
+        let mut stmt = conn.prepare("SELECT active, data FROM sessions WHERE learner_id = ?1 ORDER BY seq")?;
+        let rows = stmt
+            .query_map(params![learner_id], |row| Ok((row.get::<_, bool>(0)?, row.get::<_, String>(1)?)))?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        let mut program = LearnerProgram {
+            profile,
+            saved_sessions: Vec::new(),
+            active_session: None,
+        };
+        for (active, data) in rows {
+            let session = Session::from_json_with_images(serde_json::from_str(&data)?, images)?;
+            if active {
+                program.active_session = Some(session);
+            } else {
+                program.saved_sessions.push(session);
+            }
+        }
+        Ok(Some(program))
+    }
+
+    /// Hashes of every image used by a stored session, for `ImageStore::collect_garbage`.
+    pub fn referenced_images(&self) -> Result<HashSet<String>> {
+        let conn = self.conn.lock()?;
+        let mut stmt = conn.prepare("SELECT json_extract(data, '$.image.hash') FROM sessions")?;
+        let hashes = stmt
+            .query_map([], |row| row.get::<_, Option<String>>(0))?
+            .filter_map(|hash| hash.transpose())
+            .collect::<rusqlite::Result<HashSet<_>>>()?;
+        Ok(hashes)
+    }
+}
+
+// A `learners` row before its text columns are parsed into typed values.
+struct ProfileRow {
+    id: String,
+    name: String,
+    age: String,
+    support_level: String,
+    treatment_plan: Option<String>,
+    topic_focus: Option<String>,
+    preferred_styles: String,
+    sensory_sensitivities: String,
+}
+
+impl ProfileRow {
+    fn read(row: &Row) -> rusqlite::Result<Self> {
+        Ok(Self {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            age: row.get(2)?,
+            support_level: row.get(3)?,
+            treatment_plan: row.get(4)?,
+            topic_focus: row.get(5)?,
+            preferred_styles: row.get(6)?,
+            sensory_sensitivities: row.get(7)?,
+        })
+    }
+
+    fn into_profile(self) -> Result<LearnerProfile> {
+        let styles: Vec<String> = serde_json::from_str(&self.preferred_styles)?;
+        Ok(LearnerProfile {
+            id: self.id,
+            name: self.name,
+            age: self.age,
+            support_level: self.support_level.parse::<SupportLevel>()?,
+            treatment_plan: self.treatment_plan,
+            topic_focus: self.topic_focus,
+            preferred_styles: styles
+                .iter()
+                .map(|style| style.parse::<ImageStyle>())
+                .collect::<Result<Vec<_>>>()?,
+            sensory_sensitivities: serde_json::from_str(&self.sensory_sensitivities)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Difficulty;
+
+    fn sample_profile() -> LearnerProfile {
+        LearnerProfile {
+            age: "7".to_string(),
+            support_level: SupportLevel::Level3,
+            treatment_plan: Some("Name one emotion per picture.".to_string()),
+            preferred_styles: vec![ImageStyle::Watercolor],
+            sensory_sensitivities: vec!["bright colours".to_string()],
+            ..LearnerProfile::new("Robin")
+        }
+    }
+
+    #[test]
+    fn test_profiles_round_trip() {
+        let db = LearnerDatabase::open_in_memory().unwrap();
+        let mut profile = sample_profile();
+        db.save_profile(&profile).unwrap();
+        db.save_profile(&LearnerProfile::new("alex")).unwrap();
+        assert_eq!(db.get_profile(&profile.id).unwrap(), Some(profile.clone()));
+
+        profile.topic_focus = Some("Feelings".to_string());
+        db.save_profile(&profile).unwrap();
+        let names: Vec<String> = db.list_profiles().unwrap().into_iter().map(|p| p.name).collect();
+        assert_eq!(names, vec!["alex", "Robin"]);
+        assert_eq!(db.get_profile(&profile.id).unwrap().unwrap().topic_focus.as_deref(), Some("Feelings"));
+
+        assert!(db.delete_profile(&profile.id).unwrap());
+        assert_eq!(db.get_profile(&profile.id).unwrap(), None);
+    }
+
+    #[test]
+    fn test_program_resumes_from_disk() {
+        let path = std::env::temp_dir().join(format!("visolearn-test-{}.db", crate::utils::session_store::new_session_id()));
+        let profile = sample_profile();
+        let mut finished = profile.new_session();
+        finished.key_details = vec!["smiling girl".to_string()];
+        finished.completed = true;
+        let images = ImageStore::in_memory();
+        let mut active = profile.new_session();
+        active.difficulty = Difficulty::Simple;
+        active.image = Some(images.put_data_url("data:image/png;base64,iVBORw0KGgo=").unwrap());
+        active.chat.push(("Child".to_string(), "a girl".to_string()));
+
+        {
+            let db = LearnerDatabase::open(&path).unwrap();
+            db.save_profile(&profile).unwrap();
+            db.save_program(&profile.id, &[finished.clone()], Some(&active)).unwrap();
+            // Saving again replaces rather than duplicates.
+            db.save_program(&profile.id, &[finished.clone()], Some(&active)).unwrap();
+        }
+
+        let db = LearnerDatabase::open(&path).unwrap();
+        let program = db.load_program(&profile.id, &images).unwrap().unwrap();
+        assert_eq!(program.profile, profile);
+        assert_eq!(program.saved_sessions, vec![finished]);
+        assert_eq!(program.active_session, Some(active));
+        assert_eq!(db.load_program("nobody", &images).unwrap(), None);
+        let hash = program.active_session.as_ref().unwrap().image.as_ref().unwrap().hash.clone();
+        assert_eq!(db.referenced_images().unwrap(), HashSet::from([hash]));
+        assert!(matches!(
+            db.save_program("nobody", &[], None),
+            Err(VisoLearnError::State(_))
+        ));
+
+        db.delete_profile(&profile.id).unwrap();
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,112 @@
+use serde::{Deserialize, Serialize};
+
+use crate::config::{ImageStyle, SupportLevel};
+use crate::utils::session::Session;
+use crate::utils::session_store::new_session_id;
+
+/// A child working through a VisoLearn program. Holds the settings a therapist would
+/// otherwise re-enter before every image, so a program can be resumed days later.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LearnerProfile {
+    pub id: String,
+    /// A name or pseudonym; only ever shown to the therapist.
+    pub name: String,
+    pub age: String,
+    pub support_level: SupportLevel,
+    /// Overrides the support level's default plan when set.
+    pub treatment_plan: Option<String>,
+    pub topic_focus: Option<String>,
+    /// Image styles the child responds well to, most preferred first.
+    pub preferred_styles: Vec<ImageStyle>,
+    /// Things to keep out of images, e.g. "bright flashing lights" or "crowds".
+    pub sensory_sensitivities: Vec<String>,
+}
+
+impl Default for LearnerProfile {
+    fn default() -> Self {
+        LearnerProfile {
+            id: new_session_id(),
+            name: String::new(),
+            age: "3".to_string(),
+            support_level: SupportLevel::Level1,
+            treatment_plan: None,
+            topic_focus: None,
+            preferred_styles: Vec::new(),
+            sensory_sensitivities: Vec::new(),
+        }
+    }
+}
+
+impl LearnerProfile {
+    pub fn new(name: &str) -> Self {
+        LearnerProfile {
+            name: name.trim().to_string(),
+            ..Self::default()
+        }
+    }
+
+    /// The style to use for the next image: the first preferred style, or the default.
+    pub fn image_style(&self) -> ImageStyle {
+        self.preferred_styles.first().copied().unwrap_or_default()
+    }
+
+    /// The treatment plan to use, with sensory sensitivities appended so that prompts avoid them.
+    pub fn effective_treatment_plan(&self) -> String {
+        let plan = self
+            .treatment_plan
+            .as_deref()
+            .map(str::trim)
+            .filter(|plan| !plan.is_empty())
+            .unwrap_or_else(|| self.support_level.default_treatment_plan());
+        if self.sensory_sensitivities.is_empty() {
+            plan.to_string()
+        } else {
+            format!(
+                "{} Sensory sensitivities - keep these out of the image: {}.",
+                plan,
+                self.sensory_sensitivities.join(", ")
+            )
+        }
+    }
+
+    /// A fresh session linked to this learner and carrying their settings.
+    pub fn new_session(&self) -> Session {
+        Session {
+            learner_id: Some(self.id.clone()),
+            age: self.age.clone(),
+            autism_level: self.support_level,
+            treatment_plan: Some(self.effective_treatment_plan()),
+            topic_focus: self.topic_focus.clone(),
+            image_style: self.image_style(),
+            ..Session::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_new_session_carries_profile_settings() {
+        let profile = LearnerProfile {
+            age: "6".to_string(),
+            support_level: SupportLevel::Level2,
+            topic_focus: Some("Animals".to_string()),
+            preferred_styles: vec![ImageStyle::Cartoon, ImageStyle::Watercolor],
+            sensory_sensitivities: vec!["loud crowds".to_string()],
+            ..LearnerProfile::new(" Sam ")
+        };
+        assert_eq!(profile.name, "Sam");
+
+        let session = profile.new_session();
+        assert_eq!(session.learner_id.as_deref(), Some(profile.id.as_str()));
+        assert_eq!(session.age, "6");
+        assert_eq!(session.autism_level, SupportLevel::Level2);
+        assert_eq!(session.image_style, ImageStyle::Cartoon);
+        let plan = session.treatment_plan.unwrap();
+        assert!(plan.starts_with(SupportLevel::Level2.default_treatment_plan()));
+        assert!(plan.ends_with("loud crowds."));
+    }
+}
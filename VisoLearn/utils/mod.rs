@@ -1,9 +1,26 @@
 // Export utility modules
+#[cfg(not(target_arch = "wasm32"))]
+pub mod bundle;
+pub mod events;
+#[cfg(not(target_arch = "wasm32"))]
 pub mod file_operations;
+pub mod image_store;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod learner_db;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod learner_profile;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod report;
+pub mod session;
+pub mod session_store;
+#[cfg(not(target_arch = "wasm32"))]
 pub mod state_management;
+pub mod theme;
 pub mod visualization;
 
 // Re-export commonly used functions from utility modules
-pub use file_operations::{save_project, load_project, export_image};
-pub use state_management::{SessionState, create_session, save_session};
+#[cfg(not(target_arch = "wasm32"))]
+pub use file_operations::{save_all_session_images, save_session_log};
+#[cfg(not(target_arch = "wasm32"))]
+pub use state_management::{chat_respond, generate_image_and_reset_chat, Backends};
 pub use visualization::{render_histogram, display_metrics};
\ No newline at end of file
@@ -0,0 +1,547 @@
+use std::fs;
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+
+use base64::{engine::general_purpose, Engine as _};
+use chrono::{DateTime, Local, NaiveDate, Utc};
+use image::{DynamicImage, ImageOutputFormat};
+use maud::{html, Markup, PreEscaped, DOCTYPE};
+use printpdf::{BuiltinFont, Image, ImageTransform, IndirectFontRef, Mm, PdfDocument, PdfLayerReference};
+
+use crate::config::Difficulty;
+use crate::error::{Result, VisoLearnError};
+use crate::utils::image_store::{ImageRef, ImageStore};
+use crate::utils::learner_db::LearnerDatabase;
+use crate::utils::learner_profile::LearnerProfile;
+use crate::utils::session::Session;
+use crate::utils::visualization::{ChartFormat, ProgressChart, ProgressMetrics};
+
+// Progress reports for therapists and parents. A report is built from the same sessions that
+// `chat_respond` produces and `save_session_log` writes, then rendered as a standalone HTML page
+// (images embedded as data URLs) or a PDF.
+
+/// Largest side of a report thumbnail, in pixels.
+pub const THUMBNAIL_SIZE: u32 = 160;
+
+/// An inclusive range of calendar days. An open end means "no limit".
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct DateRange {
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+}
+
+impl DateRange {
+    pub fn new(from: Option<NaiveDate>, to: Option<NaiveDate>) -> Self {
+        Self { from, to }
+    }
+
+    pub fn is_unbounded(&self) -> bool {
+        self.from.is_none() && self.to.is_none()
+    }
+
+    /// Whether a session dated `date` belongs in the report. Undated sessions (saved before
+    /// sessions were timestamped) are only included when the range is unbounded.
+    pub fn contains(&self, date: Option<DateTime<Utc>>) -> bool {
+        let Some(date) = date else {
+            return self.is_unbounded();
+        };
+        let day = date.with_timezone(&Local).date_naive();
+        self.from.is_none_or(|from| day >= from) && self.to.is_none_or(|to| day <= to)
+    }
+
+    pub fn describe(&self) -> String {
+        match (self.from, self.to) {
+            (None, None) => "All sessions".to_string(),
+            (Some(from), None) => format!("From {}", from),
+            (None, Some(to)) => format!("Up to {}", to),
+            (Some(from), Some(to)) => format!("{} to {}", from, to),
+        }
+    }
+}
+
+/// One image's results, as shown in a report.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SessionSummary {
+    pub number: usize,
+    pub date: Option<DateTime<Utc>>,
+    pub prompt: String,
+    pub difficulty: Difficulty,
+    pub identified: Vec<String>,
+    pub missed: Vec<String>,
+    pub hints_used: usize,
+    pub attempts_used: u32,
+    pub attempt_limit: u32,
+    pub completed: bool,
+    pub image: Option<ImageRef>,
+}
+
+impl SessionSummary {
+    pub fn from_session(number: usize, session: &Session) -> Self {
+        let (identified, missed) = session
+            .key_details
+            .iter()
+            .cloned()
+            .partition(|detail| session.identified_details.contains(detail));
+        Self {
+            number,
+            date: session.date(),
+            prompt: session.prompt.clone().unwrap_or_default(),
+            difficulty: session.difficulty,
+            identified,
+            missed,
+            hints_used: session.used_hints.len(),
+            attempts_used: session.attempt_count,
+            attempt_limit: session.attempt_limit,
+            completed: session.completed,
+            image: session.image.clone(),
+        }
+    }
+
+    fn date_label(&self) -> String {
+        self.date
+            .map(|date| date.with_timezone(&Local).format("%Y-%m-%d %H:%M").to_string())
+            .unwrap_or_else(|| "date unknown".to_string())
+    }
+}
+
+/// A learner's progress over a date range.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProgressReport {
+    pub learner: LearnerProfile,
+    pub range: DateRange,
+    pub generated_at: DateTime<Utc>,
+    pub sessions: Vec<SessionSummary>,
+    pub metrics: ProgressMetrics,
+}
+
+impl ProgressReport {
+    /// Builds a report from sessions in the order they were played. Sessions that were never
+    /// shown an image are skipped.
+    pub fn new(learner: &LearnerProfile, sessions: &[Session], range: DateRange) -> Self {
+        let included: Vec<&Session> = sessions
+            .iter()
+            .filter(|session| session.prompt.is_some() && range.contains(session.date()))
+            .collect();
+        Self {
+            learner: learner.clone(),
+            range,
+            generated_at: Utc::now(),
+            sessions: included
+                .iter()
+                .enumerate()
+                .map(|(i, session)| SessionSummary::from_session(i + 1, session))
+                .collect(),
+            metrics: ProgressMetrics::from_sessions(included),
+        }
+    }
+
+    /// Builds a report from a learner's saved program, including the session in progress.
+    pub fn for_learner(db: &LearnerDatabase, images: &ImageStore, learner_id: &str, range: DateRange) -> Result<Self> {
+        let program = db
+            .load_program(learner_id, images)?
+            .ok_or_else(|| VisoLearnError::State(format!("no learner profile with id {}", learner_id)))?;
+        let mut sessions = program.saved_sessions;
+        sessions.extend(program.active_session);
+        Ok(Self::new(&program.profile, &sessions, range))
+    }
+
+    pub fn total_identified(&self) -> usize {
+        self.sessions.iter().map(|s| s.identified.len()).sum()
+    }
+
+    pub fn total_details(&self) -> usize {
+        self.sessions.iter().map(|s| s.identified.len() + s.missed.len()).sum()
+    }
+
+    pub fn total_hints(&self) -> usize {
+        self.sessions.iter().map(|s| s.hints_used).sum()
+    }
+
+    fn summary_line(&self) -> String {
+        let total = self.total_details();
+        let percent = if total == 0 {
+            0.0
+        } else {
+            self.total_identified() as f32 * 100.0 / total as f32
+        };
+        format!(
+            "{} sessions - {} of {} details identified ({:.0}%) - {} hints used",
+            self.sessions.len(),
+            self.total_identified(),
+            total,
+            percent,
+            self.total_hints()
+        )
+    }
+
+    /// Renders the report as a single HTML page with no external resources.
+    pub fn to_html(&self, images: &ImageStore) -> Result<String> {
+        let style = format!(
+            "body {{ font-family: sans-serif; margin: 2rem; color: #222222; }}
+    table {{ border-collapse: collapse; width: 100%; }}
+    th, td {{ border: 1px solid #cccccc; padding: 6px; text-align: left; vertical-align: top; }}
+    th {{ background-color: #f0f0f0; }}
+    .identified {{ color: #1e7a1e; }}
+    .missed {{ color: #a33030; }}
+    .thumb {{ max-width: {thumb}px; max-height: {thumb}px; }}
+    .chart {{ margin-bottom: 1rem; }}",
+            thumb = THUMBNAIL_SIZE
+        );
+        // Learner names, prompts and details come from people and models, so they are only
+        // ever interpolated through maud, which escapes them.
+        let markup = html! {
+            (DOCTYPE)
+            html lang="en" {
+                head {
+                    meta charset="utf-8";
+                    title { "Progress report - " (self.learner.name) }
+                    style { (PreEscaped(style)) }
+                }
+                body {
+                    h1 { "Progress report: " (self.learner.name) }
+                    p {
+                        "Age " (self.learner.age) " · " (self.learner.support_level) " · "
+                        (self.range.describe()) " · generated "
+                        (self.generated_at.with_timezone(&Local).format("%Y-%m-%d %H:%M"))
+                    }
+                    p { strong { (self.summary_line()) } }
+                    h2 { "Progress" }
+                    (self.charts_markup())
+                    h2 { "Sessions" }
+                    @if self.sessions.is_empty() {
+                        p { "No sessions in this period." }
+                    } @else {
+                        table {
+                            tr {
+                                th { "#" } th { "Image" } th { "Date" } th { "Difficulty" }
+                                th { "Identified" } th { "Missed" } th { "Hints" } th { "Attempts" }
+                            }
+                            @for session in &self.sessions {
+                                tr {
+                                    td { (session.number) }
+                                    td {
+                                        @match &session.image {
+                                            Some(image) => @match thumbnail_data_url(images, image) {
+                                                Ok(url) => img class="thumb" src=(url) alt=(session.prompt);,
+                                                Err(_) => "(image unavailable)",
+                                            },
+                                            None => "(no image)",
+                                        }
+                                    }
+                                    td {
+                                        (session.date_label())
+                                        @if !session.completed { " (in progress)" }
+                                    }
+                                    td { (session.difficulty) }
+                                    td class="identified" { (details_markup(&session.identified)) }
+                                    td class="missed" { (details_markup(&session.missed)) }
+                                    td { (session.hints_used) }
+                                    td { (session.attempts_used) " / " (session.attempt_limit) }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        };
+        Ok(markup.into_string())
+    }
+
+    fn charts_markup(&self) -> Markup {
+        html! {
+            @if self.sessions.is_empty() {
+                p { "No sessions in this period." }
+            } @else {
+                @for chart in ProgressChart::ALL {
+                    @match self.metrics.render(chart, ChartFormat::Svg) {
+                        Ok(rendered) => (rendered.to_markup(chart.title())),
+                        Err(e) => p { (chart.title()) ": " (e.user_message()) },
+                    }
+                }
+            }
+        }
+    }
+
+    /// Renders the report as an A4 PDF.
+    pub fn to_pdf(&self, images: &ImageStore) -> Result<Vec<u8>> {
+        let mut pdf = PdfWriter::new(&format!("Progress report - {}", self.learner.name))?;
+
+        pdf.text(&format!("Progress report: {}", self.learner.name), 18.0, &pdf.bold.clone());
+        pdf.text(
+            &format!(
+                "Age {} - {} - {}",
+                self.learner.age,
+                self.learner.support_level,
+                self.range.describe()
+            ),
+            10.0,
+            &pdf.regular.clone(),
+        );
+        pdf.text(&self.summary_line(), 10.0, &pdf.regular.clone());
+        pdf.gap(4.0);
+
+        pdf.text("Progress", 13.0, &pdf.bold.clone());
+        for chart in ProgressChart::ALL {
+            let rendered = self.metrics.render(chart, ChartFormat::Png)?;
+            pdf.chart(&image::load_from_memory(&rendered.bytes)?);
+        }
+        pdf.gap(4.0);
+
+        pdf.text("Sessions", 13.0, &pdf.bold.clone());
+        if self.sessions.is_empty() {
+            pdf.text("No sessions in this period.", 10.0, &pdf.regular.clone());
+        }
+        for session in &self.sessions {
+            let thumbnail = session
+                .image
+                .as_ref()
+                .and_then(|image| thumbnail(images, image).ok());
+            pdf.session(session, thumbnail.as_ref());
+        }
+        pdf.finish()
+    }
+}
+
+/// Writes `<stem>.html` and `<stem>.pdf` into `dir`, returning both paths.
+pub fn write_reports(dir: &Path, stem: &str, report: &ProgressReport, images: &ImageStore) -> Result<(PathBuf, PathBuf)> {
+    fs::create_dir_all(dir)?;
+    let html_path = dir.join(format!("{}.html", stem));
+    let pdf_path = dir.join(format!("{}.pdf", stem));
+    fs::write(&html_path, report.to_html(images)?)?;
+    fs::write(&pdf_path, report.to_pdf(images)?)?;
+    Ok((html_path, pdf_path))
+}
+
+/// Writes a learner's report as `progress_<name>_<timestamp>.html` and `.pdf` in the working
+/// directory and returns both paths.
+pub fn save_progress_report(
+    db: &LearnerDatabase,
+    images: &ImageStore,
+    learner_id: &str,
+    range: DateRange,
+) -> Result<(PathBuf, PathBuf)> {
+    let report = ProgressReport::for_learner(db, images, learner_id, range)?;
+    let name: String = report
+        .learner
+        .name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    let stem = format!("progress_{}_{}", name, Local::now().format("%Y%m%d_%H%M%S"));
+    write_reports(Path::new("."), &stem, &report, images)
+}
+
+fn details_markup(details: &[String]) -> Markup {
+    html! {
+        @if details.is_empty() {
+            "-"
+        } @else {
+            @for (i, detail) in details.iter().enumerate() {
+                @if i > 0 { br; }
+                (detail)
+            }
+        }
+    }
+}
+
+/// A small copy of a stored image, for reports.
+pub fn thumbnail(images: &ImageStore, image: &ImageRef) -> Result<DynamicImage> {
+    Ok(images.load_image(image)?.thumbnail(THUMBNAIL_SIZE, THUMBNAIL_SIZE))
+}
+
+fn thumbnail_data_url(images: &ImageStore, image: &ImageRef) -> Result<String> {
+    let mut bytes = Vec::new();
+    thumbnail(images, image)?.write_to(&mut Cursor::new(&mut bytes), ImageOutputFormat::Png)?;
+    Ok(format!("data:image/png;base64,{}", general_purpose::STANDARD.encode(bytes)))
+}
+
+// The built-in PDF fonts only cover Latin-1; anything else is replaced.
+fn pdf_text(text: &str) -> String {
+    text.chars().map(|c| if (c as u32) < 0x100 { c } else { '?' }).collect()
+}
+
+const PAGE_WIDTH: f32 = 210.0;
+const PAGE_HEIGHT: f32 = 297.0;
+const MARGIN: f32 = 15.0;
+
+// Lays out the PDF top to bottom, starting a new page when the next block doesn't fit.
+struct PdfWriter {
+    doc: printpdf::PdfDocumentReference,
+    layer: PdfLayerReference,
+    regular: IndirectFontRef,
+    bold: IndirectFontRef,
+    y: f32,
+}
+
+impl PdfWriter {
+    fn new(title: &str) -> Result<Self> {
+        let (doc, page, layer) = PdfDocument::new(pdf_text(title), Mm(PAGE_WIDTH), Mm(PAGE_HEIGHT), "Report");
+        let regular = doc.add_builtin_font(BuiltinFont::Helvetica)?;
+        let bold = doc.add_builtin_font(BuiltinFont::HelveticaBold)?;
+        let layer = doc.get_page(page).get_layer(layer);
+        Ok(Self {
+            doc,
+            layer,
+            regular,
+            bold,
+            y: PAGE_HEIGHT - MARGIN,
+        })
+    }
+
+    fn ensure_space(&mut self, height: f32) {
+        if self.y - height < MARGIN {
+            let (page, layer) = self.doc.add_page(Mm(PAGE_WIDTH), Mm(PAGE_HEIGHT), "Report");
+            self.layer = self.doc.get_page(page).get_layer(layer);
+            self.y = PAGE_HEIGHT - MARGIN;
+        }
+    }
+
+    fn gap(&mut self, height: f32) {
+        self.y -= height;
+    }
+
+    fn text_at(&self, text: &str, size: f32, x: f32, y: f32, font: &IndirectFontRef) {
+        self.layer.use_text(pdf_text(text), size, Mm(x), Mm(y), font);
+    }
+
+    fn text(&mut self, text: &str, size: f32, font: &IndirectFontRef) {
+        let line_height = size * 0.5;
+        self.ensure_space(line_height);
+        self.y -= line_height;
+        self.text_at(text, size, MARGIN, self.y, font);
+    }
+
+    // Places a chart rendered by `ProgressMetrics::render` across the page width.
+    fn chart(&mut self, chart: &DynamicImage) {
+        let dpi = chart.width() as f32 / ((PAGE_WIDTH - 2.0 * MARGIN) / 25.4);
+        let height = chart.height() as f32 / dpi * 25.4;
+        self.ensure_space(height + 2.0);
+        self.y -= height + 2.0;
+        Image::from_dynamic_image(&DynamicImage::ImageRgb8(chart.to_rgb8())).add_to_layer(
+            self.layer.clone(),
+            ImageTransform {
+                translate_x: Some(Mm(MARGIN)),
+                translate_y: Some(Mm(self.y)),
+                dpi: Some(dpi),
+                ..Default::default()
+            },
+        );
+    }
+
+    fn session(&mut self, session: &SessionSummary, thumbnail: Option<&DynamicImage>) {
+        // Thumbnails are placed at 150 dpi, so THUMBNAIL_SIZE pixels is about 27mm.
+        let block = 32.0;
+        self.ensure_space(block);
+        let top = self.y;
+        if let Some(thumbnail) = thumbnail {
+            let height_mm = thumbnail.height() as f32 / 150.0 * 25.4;
+            Image::from_dynamic_image(&DynamicImage::ImageRgb8(thumbnail.to_rgb8())).add_to_layer(
+                self.layer.clone(),
+                ImageTransform {
+                    translate_x: Some(Mm(MARGIN)),
+                    translate_y: Some(Mm(top - 2.0 - height_mm)),
+                    dpi: Some(150.0),
+                    ..Default::default()
+                },
+            );
+        }
+
+        let x = MARGIN + 32.0;
+        let lines = [
+            format!(
+                "#{} - {} - {}{}",
+                session.number,
+                session.date_label(),
+                session.difficulty,
+                if session.completed { "" } else { " (in progress)" }
+            ),
+            format!("Identified: {}", join_or_dash(&session.identified)),
+            format!("Missed: {}", join_or_dash(&session.missed)),
+            format!(
+                "Hints: {}   Attempts: {} / {}",
+                session.hints_used, session.attempts_used, session.attempt_limit
+            ),
+        ];
+        for (i, line) in lines.iter().enumerate() {
+            let font = if i == 0 { &self.bold } else { &self.regular };
+            let mut text = line.clone();
+            if text.chars().count() > 95 {
+                text = text.chars().take(92).collect::<String>() + "...";
+            }
+            self.text_at(&text, 9.0, x, top - 5.0 - i as f32 * 5.0, font);
+        }
+        self.y = top - block;
+    }
+
+    fn finish(self) -> Result<Vec<u8>> {
+        Ok(self.doc.save_to_bytes()?)
+    }
+}
+
+fn join_or_dash(details: &[String]) -> String {
+    if details.is_empty() {
+        "-".to_string()
+    } else {
+        details.join(", ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn played(day: u32, difficulty: Difficulty, images: &ImageStore) -> Session {
+        let mut session = Session::new();
+        session.prompt = Some(format!("Picture <{}>", day));
+        session.difficulty = difficulty;
+        session.key_details = vec!["red ball".to_string(), "green tree".to_string()];
+        session.identified_details = vec!["red ball".to_string()];
+        session.attempt_count = 2;
+        session.completed = true;
+        session.completed_at = Some(Utc.with_ymd_and_hms(2024, 3, day, 12, 0, 0).unwrap());
+        session.image = images.put_image(&DynamicImage::new_rgb8(400, 300)).ok();
+        session
+    }
+
+    #[test]
+    fn test_report_filters_by_date_and_summarises() {
+        let images = ImageStore::in_memory();
+        let sessions = vec![
+            played(1, Difficulty::VerySimple, &images),
+            played(5, Difficulty::Simple, &images),
+            played(9, Difficulty::Moderate, &images),
+            Session::new(), // Never shown an image.
+        ];
+        let range = DateRange::new(NaiveDate::from_ymd_opt(2024, 3, 4), NaiveDate::from_ymd_opt(2024, 3, 10));
+        let report = ProgressReport::new(&LearnerProfile::new("Sam"), &sessions, range);
+
+        assert_eq!(report.sessions.len(), 2);
+        assert_eq!(report.sessions[0].number, 1);
+        assert_eq!(report.sessions[0].difficulty, Difficulty::Simple);
+        assert_eq!(report.sessions[0].missed, vec!["green tree"]);
+        assert_eq!((report.total_identified(), report.total_details()), (2, 4));
+        assert!(!range.contains(None));
+        assert!(DateRange::default().contains(None));
+    }
+
+    #[test]
+    fn test_renders_html_and_pdf() {
+        let images = ImageStore::in_memory();
+        let sessions = vec![played(1, Difficulty::VerySimple, &images), played(2, Difficulty::Simple, &images)];
+        let report = ProgressReport::new(&LearnerProfile::new("Sam & Co"), &sessions, DateRange::default());
+
+        let html = report.to_html(&images).unwrap();
+        assert!(html.contains("Progress report: Sam &amp; Co"));
+        assert!(html.contains(r#"<div class="chart" role="img" aria-label="Difficulty over time"><svg"#));
+        assert!(html.contains(r#"<img class="thumb" src="data:image/png;base64,"#));
+        assert!(html.contains("alt=\"Picture &lt;2&gt;\""));
+        assert!(html.contains("2 sessions - 2 of 4 details identified (50%) - 0 hints used"));
+
+        let thumb = thumbnail(&images, sessions[0].image.as_ref().unwrap()).unwrap();
+        assert_eq!((thumb.width(), thumb.height()), (160, 120));
+
+        let pdf = report.to_pdf(&images).unwrap();
+        assert!(pdf.starts_with(b"%PDF"));
+    }
+}
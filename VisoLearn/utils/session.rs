@@ -0,0 +1,334 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::config::{Difficulty, ImageStyle, SupportLevel};
+use crate::error::{Result, VisoLearnError};
+use crate::models::hints::UsedHint;
+use crate::utils::image_store::{ImageRef, ImageStore};
+use crate::utils::session_store::new_session_id;
+
+/// Version written to every serialized [`Session`]. Bump it and add a step to
+/// [`migrate_session`] whenever the stored shape changes.
+pub const SESSION_SCHEMA_VERSION: u32 = 3;
+
+/// One practice run on a single image: the UI state, the saved-session history
+/// and the exported logs all use this type.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Session {
+    pub schema_version: u32,
+    /// Key for this learner's entry in the `SessionStore`; kept when advancing to a new image.
+    pub id: String,
+    /// The `LearnerProfile` this session belongs to, if any.
+    pub learner_id: Option<String>,
+    pub prompt: Option<String>,
+    /// The image, held in the `ImageStore`.
+    pub image: Option<ImageRef>,
+    pub image_description: Option<String>,
+    pub chat: Vec<(String, String)>,     // (Speaker, Message)
+    pub treatment_plan: Option<String>,
+    pub topic_focus: Option<String>,
+    pub key_details: Vec<String>,
+    pub identified_details: Vec<String>,
+    /// Hints shown to the learner, in the order they were given.
+    pub used_hints: Vec<UsedHint>,
+    pub difficulty: Difficulty,
+    /// Why the progression engine chose `difficulty`, for teachers reviewing the log.
+    pub difficulty_reason: Option<String>,
+    pub autism_level: SupportLevel,
+    pub age: String,
+    pub attempt_limit: u32,
+    pub attempt_count: u32,
+    pub details_threshold: f32, // Fraction (0.1-1.0) of key details needed to advance.
+    pub image_style: ImageStyle,
+    pub completed: bool,
+    /// When the image was shown; unknown for sessions saved before this was recorded.
+    pub started_at: Option<DateTime<Utc>>,
+    pub completed_at: Option<DateTime<Utc>>,
+    /// When the learner first named one of the key details.
+    pub first_detail_at: Option<DateTime<Utc>>,
+}
+
+impl Default for Session {
+    fn default() -> Self {
+        Session {
+            schema_version: SESSION_SCHEMA_VERSION,
+            id: new_session_id(),
+            learner_id: None,
+            prompt: None,
+            image: None,
+            image_description: None,
+            chat: Vec::new(),
+            treatment_plan: None,
+            topic_focus: None,
+            key_details: Vec::new(),
+            identified_details: Vec::new(),
+            used_hints: Vec::new(),
+            difficulty: Difficulty::VerySimple,
+            difficulty_reason: None,
+            autism_level: SupportLevel::Level1,
+            age: "3".to_string(),
+            attempt_limit: 3,
+            attempt_count: 0,
+            details_threshold: 0.7,
+            image_style: ImageStyle::Realistic,
+            completed: false,
+            started_at: None,
+            completed_at: None,
+            first_detail_at: None,
+        }
+    }
+}
+
+/// The learner settings a new image is generated for, as a session records them.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SessionSettings {
+    pub age: String,
+    pub support_level: SupportLevel,
+    pub topic_focus: String,
+    pub treatment_plan: String,
+    pub attempt_limit: u32,
+    /// Fraction (0.1-1.0) of key details needed to advance; a percentage is also accepted.
+    pub details_threshold: f32,
+    pub image_style: ImageStyle,
+}
+
+impl Session {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// When the session finished, or else when it started.
+    pub fn date(&self) -> Option<DateTime<Utc>> {
+        self.completed_at.or(self.started_at)
+    }
+
+    pub fn settings(&self) -> SessionSettings {
+        SessionSettings {
+            age: self.age.clone(),
+            support_level: self.autism_level,
+            topic_focus: self.topic_focus.clone().unwrap_or_default(),
+            treatment_plan: self.treatment_plan.clone().unwrap_or_default(),
+            attempt_limit: self.attempt_limit,
+            details_threshold: self.details_threshold,
+            image_style: self.image_style,
+        }
+    }
+
+    /// Reads a session saved by any version of VisoLearn, migrating it to the current schema.
+    pub fn from_json(value: Value) -> Result<Self> {
+        Ok(serde_json::from_value(migrate_session(value)?)?)
+    }
+
+    /// Like [`Session::from_json`], but first moves an inline data-URL image (as written before
+    /// schema version 3) into `images`, so it is kept rather than dropped by the migration.
+    pub fn from_json_with_images(mut value: Value, images: &ImageStore) -> Result<Self> {
+        let inline = value
+            .get("image")
+            .and_then(Value::as_str)
+            .filter(|image| image.starts_with("data:"))
+            .map(str::to_string);
+        if let Some(data_url) = inline {
+            value["image"] = serde_json::to_value(images.put_data_url(&data_url)?)?;
+        }
+        Self::from_json(value)
+    }
+
+    pub fn to_json(&self) -> Result<Value> {
+        Ok(serde_json::to_value(self)?)
+    }
+}
+
+// A checklist item that tracks whether a key detail was identified.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ChecklistItem {
+    pub detail: String,
+    pub identified: bool,
+    pub id: usize,
+    #[serde(default)]
+    pub match_score: f32, // Best fuzzy-match score (0.0-1.0) seen for this detail.
+}
+
+/// Upgrades a serialized session, one schema version at a time, to [`SESSION_SCHEMA_VERSION`].
+/// Sessions without a `schema_version` are treated as version 0.
+pub fn migrate_session(mut value: Value) -> Result<Value> {
+    let object = value
+        .as_object_mut()
+        .ok_or_else(|| VisoLearnError::Parse("session: expected a JSON object".to_string()))?;
+    let mut version = match object.get("schema_version") {
+        None => 0,
+        Some(v) => v.as_u64().ok_or_else(|| {
+            VisoLearnError::Parse(format!("session: invalid schema_version {}", v))
+        })? as u32,
+    };
+    if version > SESSION_SCHEMA_VERSION {
+        return Err(VisoLearnError::Parse(format!(
+            "session: schema version {} is newer than this app supports ({})",
+            version, SESSION_SCHEMA_VERSION
+        )));
+    }
+
+    while version < SESSION_SCHEMA_VERSION {
+        match version {
+            0 => migrate_v0_to_v1(object),
+            1 => migrate_v1_to_v2(object),
+            2 => migrate_v2_to_v3(object),
+            _ => unreachable!("no migration from session schema version {}", version),
+        }
+        version += 1;
+        object.insert("schema_version".to_string(), Value::from(version));
+    }
+    Ok(value)
+}
+
+// Version 0 is the shape written by the original app and the first session logs: optional text
+// fields were saved as "", the threshold could be a percentage, numbers could be strings (and
+// the age a number), the image could be a "[IMAGE_DATA_REMOVED]" marker and there was no id.
+fn migrate_v0_to_v1(object: &mut serde_json::Map<String, Value>) {
+    for key in ["prompt", "image", "image_description", "treatment_plan", "topic_focus"] {
+        let blank = matches!(object.get(key), Some(Value::String(s)) if s.trim().is_empty());
+        if blank {
+            object.insert(key.to_string(), Value::Null);
+        }
+    }
+    if object.get("image").and_then(Value::as_str) == Some("[IMAGE_DATA_REMOVED]") {
+        object.insert("image".to_string(), Value::Null);
+    }
+
+    if let Some(age) = object.get("age").and_then(Value::as_u64) {
+        object.insert("age".to_string(), Value::from(age.to_string()));
+    }
+    for key in ["attempt_limit", "attempt_count"] {
+        if let Some(n) = object.get(key).and_then(Value::as_str).and_then(|s| s.trim().parse::<u32>().ok()) {
+            object.insert(key.to_string(), Value::from(n));
+        }
+    }
+
+    let threshold = match object.get("details_threshold") {
+        Some(Value::String(s)) => s.trim().parse::<f64>().ok(),
+        Some(v) => v.as_f64(),
+        None => None,
+    };
+    if let Some(mut threshold) = threshold {
+        if threshold > 1.0 {
+            threshold /= 100.0;
+        }
+        object.insert("details_threshold".to_string(), Value::from(threshold));
+    }
+
+    if !object.contains_key("id") {
+        object.insert("id".to_string(), Value::from(new_session_id()));
+    }
+}
+
+// Version 1 stored hints as plain text. Keep the text; the detail it was for is unknown, so
+// leave it unset and count each one as a full-answer hint.
+fn migrate_v1_to_v2(object: &mut serde_json::Map<String, Value>) {
+    let Some(Value::Array(hints)) = object.get_mut("used_hints") else {
+        return;
+    };
+    for hint in hints.iter_mut() {
+        if let Value::String(text) = hint {
+            *hint = serde_json::json!({
+                "detail_id": null,
+                "detail": "",
+                "level": "Answer",
+                "text": text,
+            });
+        }
+    }
+}
+
+// Version 2 kept the image inline as a data URL; version 3 keeps an `ImageRef` into the image
+// store. Without a store to move it into (see `Session::from_json_with_images`) it is dropped.
+fn migrate_v2_to_v3(object: &mut serde_json::Map<String, Value>) {
+    if matches!(object.get("image"), Some(Value::String(_))) {
+        object.insert("image".to_string(), Value::Null);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::hints::HintLevel;
+    use serde_json::json;
+
+    #[test]
+    fn test_session_round_trip() {
+        let mut session = Session::new();
+        session.prompt = Some("A red kite".to_string());
+        session.chat.push(("Child".to_string(), "a kite".to_string()));
+        session.key_details = vec!["red kite".to_string()];
+        session.started_at = Some(Utc::now());
+
+        let value = session.to_json().unwrap();
+        assert_eq!(value["schema_version"], json!(SESSION_SCHEMA_VERSION));
+        assert_eq!(value["chat"], json!([["Child", "a kite"]]));
+        assert_eq!(Session::from_json(value).unwrap(), session);
+    }
+
+    #[test]
+    fn test_migrates_unversioned_sessions() {
+        let legacy = json!({
+            "prompt": "A dog in a park",
+            "image": "[IMAGE_DATA_REMOVED]",
+            "chat": [["Child", "a dog"], ["Teacher", "Well done!"]],
+            "treatment_plan": "",
+            "topic_focus": "Animals",
+            "key_details": ["brown dog"],
+            "identified_details": [],
+            "difficulty": "Simple",
+            "age": 5,
+            "autism_level": "Level 2",
+            "attempt_limit": "4",
+            "attempt_count": 1,
+            "details_threshold": 70,
+            "image_style": "Cartoon",
+            "used_hints": ["Look near the tree."]
+        });
+
+        let session = Session::from_json(legacy).unwrap();
+        assert_eq!(session.schema_version, SESSION_SCHEMA_VERSION);
+        assert_eq!(session.image, None);
+        assert_eq!(session.treatment_plan, None);
+        assert_eq!(session.topic_focus.as_deref(), Some("Animals"));
+        assert_eq!(session.age, "5");
+        assert_eq!(session.difficulty, Difficulty::Simple);
+        assert_eq!(session.autism_level, SupportLevel::Level2);
+        assert_eq!(session.image_style, ImageStyle::Cartoon);
+        assert_eq!(session.attempt_limit, 4);
+        assert!((session.details_threshold - 0.7).abs() < 1e-6);
+        assert_eq!(session.chat[1], ("Teacher".to_string(), "Well done!".to_string()));
+        assert!(!session.id.is_empty());
+        assert!(!session.completed);
+        assert_eq!(session.used_hints.len(), 1);
+        assert_eq!(session.used_hints[0].text, "Look near the tree.");
+        assert_eq!(session.used_hints[0].level, HintLevel::Answer);
+        assert_eq!(session.used_hints[0].detail_id, None);
+    }
+
+    #[test]
+    fn test_moves_inline_images_into_the_store() {
+        let images = ImageStore::in_memory();
+        let png = "data:image/png;base64,iVBORw0KGgoAAAANSUhEUg==";
+        let v2 = json!({ "schema_version": 2, "image": png });
+
+        let session = Session::from_json_with_images(v2.clone(), &images).unwrap();
+        let image = session.image.unwrap();
+        assert_eq!(image.mime_type, "image/png");
+        assert_eq!(images.data_url(&image).unwrap(), png);
+        assert_eq!(Session::from_json(v2).unwrap().image, None);
+    }
+
+    #[test]
+    fn test_rejects_newer_schema_versions() {
+        let future = json!({ "schema_version": SESSION_SCHEMA_VERSION + 1 });
+        assert!(matches!(Session::from_json(future), Err(VisoLearnError::Parse(_))));
+        assert!(matches!(Session::from_json(json!([1, 2])), Err(VisoLearnError::Parse(_))));
+        assert!(matches!(
+            Session::from_json(json!({ "difficulty": "Medium" })),
+            Err(VisoLearnError::Parse(_))
+        ));
+    }
+}
@@ -0,0 +1,167 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+
+use crate::error::Result;
+use crate::utils::events::EventBus;
+use crate::utils::image_store::{ImageRef, ImageStore};
+
+/// Returns a new id: 128 random bits from the operating system, as hex. The id is all that
+/// guards a session's routes, so it must not be guessable from another one.
+pub fn new_session_id() -> String {
+    let mut bytes = [0u8; 16];
+    getrandom::getrandom(&mut bytes).expect("the operating system's random number generator is unavailable");
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// The generated content belonging to one practice session.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SessionAssets {
+    pub image: Option<ImageRef>,
+    pub prompt: Option<String>,
+    pub description: Option<String>,
+}
+
+/// Holds each session's image, prompt and description, keyed by session id, so that
+/// learners using the app at the same time never see each other's images. The image bytes
+/// themselves live in the shared `ImageStore`.
+#[derive(Debug)]
+pub struct SessionStore {
+    sessions: Mutex<HashMap<String, SessionAssets>>,
+    images: ImageStore,
+    events: EventBus,
+}
+
+impl Default for SessionStore {
+    fn default() -> Self {
+        Self::with_images(ImageStore::in_memory())
+    }
+}
+
+impl SessionStore {
+    /// A store whose images are kept in memory only.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_images(images: ImageStore) -> Self {
+        Self {
+            sessions: Mutex::new(HashMap::new()),
+            images,
+            events: EventBus::new(),
+        }
+    }
+
+    pub fn images(&self) -> &ImageStore {
+        &self.images
+    }
+
+    /// Live events for each session, published as the practice loop runs.
+    pub fn events(&self) -> &EventBus {
+        &self.events
+    }
+
+    /// Returns a copy of the assets stored for `session_id`, if any.
+    pub fn get(&self, session_id: &str) -> Result<Option<SessionAssets>> {
+        Ok(self.sessions.lock()?.get(session_id).cloned())
+    }
+
+    /// Applies `f` to the assets for `session_id`, creating an empty entry first if needed.
+    pub fn update<F>(&self, session_id: &str, f: F) -> Result<()>
+    where
+        F: FnOnce(&mut SessionAssets),
+    {
+        let mut sessions = self.sessions.lock()?;
+        f(sessions.entry(session_id.to_string()).or_default());
+        Ok(())
+    }
+
+    /// Drops everything stored for `session_id`, returning it, and closes its event channel.
+    pub fn remove(&self, session_id: &str) -> Result<Option<SessionAssets>> {
+        self.events.close(session_id)?;
+        Ok(self.sessions.lock()?.remove(session_id))
+    }
+
+    /// Hashes of the images held by every live session, for `ImageStore::collect_garbage`.
+    pub fn referenced_images(&self) -> Result<HashSet<String>> {
+        Ok(self
+            .sessions
+            .lock()?
+            .values()
+            .filter_map(|assets| assets.image.as_ref())
+            .map(|image| image.hash.clone())
+            .collect())
+    }
+
+    pub fn len(&self) -> Result<usize> {
+        Ok(self.sessions.lock()?.len())
+    }
+
+    pub fn is_empty(&self) -> Result<bool> {
+        Ok(self.len()? == 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_session_ids_are_random_hex() {
+        let ids: HashSet<String> = (0..100).map(|_| new_session_id()).collect();
+        assert_eq!(ids.len(), 100);
+        assert!(ids.iter().all(|id| id.len() == 32 && id.chars().all(|c| c.is_ascii_hexdigit())));
+    }
+
+    #[test]
+        fn test_sessions_are_isolated() {
+        let store = SessionStore::new();
+        let (a, b) = (new_session_id(), new_session_id());
+        assert_ne!(a, b);
+
+        let image = ImageRef {
+            hash: "ab12".to_string(),
+            mime_type: "image/png".to_string(),
+        };
+        store.update(&a, |assets| assets.image = Some(image.clone())).unwrap();
+        store
+            .update(&b, |assets| assets.prompt = Some("a red kite".to_string()))
+            .unwrap();
+
+        let first = store.get(&a).unwrap().unwrap();
+        assert_eq!(first.image, Some(image));
+        assert_eq!(first.prompt, None);
+        assert_eq!(store.get(&b).unwrap().unwrap().image, None);
+
+        assert!(store.remove(&a).unwrap().is_some());
+        assert_eq!(store.get(&a).unwrap(), None);
+        assert_eq!(store.len().unwrap(), 1);
+    }
+
+    #[test]
+    fn test_concurrent_updates_do_not_overwrite_each_other() {
+        let store = Arc::new(SessionStore::new());
+        let handles: Vec<_> = (0..8)
+            .map(|i| {
+                let store = Arc::clone(&store);
+                std::thread::spawn(move || {
+                    let id = format!("learner-{}", i);
+                    for _ in 0..50 {
+                        store
+                            .update(&id, |assets| assets.description = Some(format!("image for {}", id)))
+                            .unwrap();
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        assert_eq!(store.len().unwrap(), 8);
+        for i in 0..8 {
+            let assets = store.get(&format!("learner-{}", i)).unwrap().unwrap();
+            assert_eq!(assets.description, Some(format!("image for learner-{}", i)));
+        }
+    }
+}
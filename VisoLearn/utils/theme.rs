@@ -0,0 +1,217 @@
+use serde::{Deserialize, Serialize};
+
+use crate::config::Palette;
+
+// Themes are built from `ThemeOptions`: a palette plus independent accessibility switches, so a
+// therapist can pick e.g. the muted palette with a dyslexia-friendly font and larger text.
+
+/// The accessibility choices a theme is built from.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ThemeOptions {
+    pub palette: Palette,
+    /// A typeface with weighted letter bottoms, plus wider letter and line spacing.
+    pub dyslexia_font: bool,
+    pub large_text: bool,
+    /// No transitions or animation, whatever the browser's own setting.
+    pub reduced_motion: bool,
+    /// Blue and orange instead of green and red, with shape marks rather than coloured emoji.
+    pub colour_blind_safe: bool,
+}
+
+/// Colours, type and cues used by the HTML views in `visualization` and the UI.
+/// Every colour is a CSS colour.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Theme {
+    pub background: String,
+    pub text: String,
+    /// Secondary text, such as the threshold explanation under the progress bar.
+    pub muted_text: String,
+    pub border: String,
+    /// Checklist rows that have not been identified yet.
+    pub item_background: String,
+    pub identified_background: String,
+    pub identified_text: String,
+    /// The empty part of the progress bar.
+    pub track: String,
+    pub progress: String,
+    /// The marker showing how many details are needed to advance.
+    pub threshold: String,
+    pub font_family: String,
+    pub font_size_px: u32,
+    pub letter_spacing: String,
+    pub line_height: f32,
+    pub reduced_motion: bool,
+    /// Emoji in marks and messages. Off for low-stimulation and colour-blind-safe themes,
+    /// since ✅/❌ differ mostly by colour.
+    pub emoji: bool,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme::dark()
+    }
+}
+
+impl Theme {
+    /// White text on black, the original VisoLearn look.
+    pub fn dark() -> Self {
+        Theme {
+            background: "#000000".to_string(),
+            text: "#ffffff".to_string(),
+            muted_text: "#dddddd".to_string(),
+            border: "#444444".to_string(),
+            item_background: "#222222".to_string(),
+            identified_background: "#1e4620".to_string(),
+            identified_text: "#7fff7f".to_string(),
+            track: "#333333".to_string(),
+            progress: "#4CAF50".to_string(),
+            threshold: "#ff6b6b".to_string(),
+            font_family: "sans-serif".to_string(),
+            font_size_px: 16,
+            letter_spacing: "normal".to_string(),
+            line_height: 1.4,
+            reduced_motion: false,
+            emoji: true,
+        }
+    }
+
+    /// Black and white with yellow highlights, for low vision.
+    pub fn high_contrast() -> Self {
+        Theme {
+            background: "#000000".to_string(),
+            text: "#ffffff".to_string(),
+            muted_text: "#ffffff".to_string(),
+            border: "#ffffff".to_string(),
+            item_background: "#000000".to_string(),
+            identified_background: "#ffff00".to_string(),
+            identified_text: "#000000".to_string(),
+            track: "#ffffff".to_string(),
+            progress: "#ffff00".to_string(),
+            threshold: "#00ffff".to_string(),
+            font_size_px: 18,
+            ..Theme::dark()
+        }
+    }
+
+    /// Soft, low-saturation colours with no emoji or motion, for learners who are easily
+    /// overstimulated.
+    pub fn muted_pastel() -> Self {
+        Theme {
+            background: "#f4f1ec".to_string(),
+            text: "#3d3d3d".to_string(),
+            muted_text: "#6b6b6b".to_string(),
+            border: "#d8d2c8".to_string(),
+            item_background: "#ebe6de".to_string(),
+            identified_background: "#dfe9e1".to_string(),
+            identified_text: "#2f4f3a".to_string(),
+            track: "#e2ddd5".to_string(),
+            progress: "#a8c5b0".to_string(),
+            threshold: "#c9a27e".to_string(),
+            reduced_motion: true,
+            emoji: false,
+            ..Theme::dark()
+        }
+    }
+
+    pub fn from_options(options: &ThemeOptions) -> Self {
+        let mut theme = match options.palette {
+            Palette::Dark => Theme::dark(),
+            Palette::HighContrast => Theme::high_contrast(),
+            Palette::MutedPastel => Theme::muted_pastel(),
+        };
+        if options.dyslexia_font {
+            theme.font_family = "OpenDyslexic, 'Comic Sans MS', Verdana, sans-serif".to_string();
+            theme.letter_spacing = "0.05em".to_string();
+            theme.line_height = 1.8;
+        }
+        if options.large_text {
+            theme.font_size_px += 6;
+        }
+        if options.reduced_motion {
+            theme.reduced_motion = true;
+        }
+        if options.colour_blind_safe {
+            // Okabe-Ito blue and orange are distinguishable with every common colour deficiency.
+            theme.progress = "#0072b2".to_string();
+            theme.threshold = "#e69f00".to_string();
+            match options.palette {
+                Palette::Dark => {
+                    theme.identified_background = "#0b3a5a".to_string();
+                    theme.identified_text = "#9fd3ff".to_string();
+                }
+                Palette::MutedPastel => {
+                    theme.identified_background = "#dbe7f3".to_string();
+                    theme.identified_text = "#1f3f5f".to_string();
+                }
+                // Already yellow on black.
+                Palette::HighContrast => {}
+            }
+            theme.emoji = false;
+        }
+        theme
+    }
+
+    /// Inline style for the boxes that hold each view.
+    pub fn panel_style(&self) -> String {
+        format!(
+            "background-color: {}; color: {}; padding: 15px; border-radius: 8px; {}",
+            self.background,
+            self.text,
+            self.font_style()
+        )
+    }
+
+    pub fn font_style(&self) -> String {
+        format!(
+            "font-family: {}; font-size: {}px; letter-spacing: {}; line-height: {};",
+            self.font_family, self.font_size_px, self.letter_spacing, self.line_height
+        )
+    }
+
+    /// A CSS `transition` declaration, or nothing when motion is reduced.
+    pub fn transition(&self, property: &str) -> String {
+        if self.reduced_motion {
+            String::new()
+        } else {
+            format!("transition: {} 0.3s;", property)
+        }
+    }
+
+    /// Marks for identified and not-yet-identified details. They differ in shape, not just colour.
+    pub fn marks(&self) -> (&'static str, &'static str) {
+        if self.emoji {
+            ("✅", "❌")
+        } else {
+            ("✓", "○")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_options_build_accessible_themes() {
+        assert_eq!(Theme::from_options(&ThemeOptions::default()), Theme::dark());
+
+        let theme = Theme::from_options(&ThemeOptions {
+            palette: Palette::MutedPastel,
+            dyslexia_font: true,
+            large_text: true,
+            colour_blind_safe: true,
+            ..ThemeOptions::default()
+        });
+        assert!(theme.font_family.starts_with("OpenDyslexic"));
+        assert_eq!(theme.font_size_px, 22);
+        assert_eq!(theme.progress, "#0072b2");
+        assert_eq!(theme.marks(), ("✓", "○"));
+        assert_eq!(theme.transition("background-color"), "");
+        assert!(theme.panel_style().contains("font-size: 22px"));
+
+        let options: ThemeOptions = serde_json::from_str(r#"{"palette": "High Contrast"}"#).unwrap();
+        assert_eq!(Theme::from_options(&options).identified_background, "#ffff00");
+    }
+}
@@ -1,92 +1,98 @@
-use std::collections::HashMap;
-use serde_json::{Value, json};
+use std::collections::BTreeMap;
+use std::io::Cursor;
+
+use base64::{engine::general_purpose, Engine as _};
+use image::{DynamicImage, ImageOutputFormat, RgbImage};
+use maud::{html, Markup, PreEscaped};
+use plotters::chart::MeshStyle;
+use plotters::coord::Shift;
+use plotters::prelude::*;
+
+use crate::config::Difficulty;
+use crate::error::{Result, VisoLearnError};
+use crate::utils::session::{ChecklistItem, Session};
+use crate::utils::theme::Theme;
 
 /// Updates the difficulty label based on the active session
-pub fn update_difficulty_label(active_session: &Value) -> String {
-    let difficulty = active_session.get("difficulty")
-        .and_then(|d| d.as_str())
-        .unwrap_or("Very Simple");
-    
-    format!("**Current Difficulty:** {}", difficulty)
+pub fn update_difficulty_label(active_session: &Session) -> String {
+    format!("**Current Difficulty:** {}", active_session.difficulty)
 }
 
 /// Updates the checklist HTML based on the provided checklist items
-pub fn update_checklist_html(checklist: &[Value]) -> String {
-    if checklist.is_empty() {
-        return r#"
-            <div id="checklist-container" style="background-color: #000000; color: #ffffff; padding: 15px; border-radius: 8px;">
-                <p>Generate an image to see details to identify.</p>
-            </div>
-        "#.to_string();
-    }
-
-    let mut html_content = r#"
-        <div id="checklist-container" style="background-color: #000000; color: #ffffff; padding: 15px; border-radius: 8px;">
-            <style>
-                .checklist-item {
-                    display: flex;
-                    align-items: center;
-                    margin-bottom: 10px;
-                    padding: 8px;
-                    border-radius: 5px;
-                    transition: background-color 0.3s;
+pub fn update_checklist_html(checklist: &[ChecklistItem], theme: &Theme) -> String {
+    // Details come from the vision model, so they are only ever interpolated through maud,
+    // which escapes them.
+    let (identified_mark, missing_mark) = theme.marks();
+    let markup = html! {
+        div id="checklist-container" style=(theme.panel_style()) {
+            @if checklist.is_empty() {
+                p { "Generate an image to see details to identify." }
+            } @else {
+                style { (PreEscaped(checklist_css(theme))) }
+                @for item in checklist {
+                    div class={ "checklist-item " (if item.identified { "identified" } else { "not-identified" }) } {
+                        span class="checkmark" aria-label=(if item.identified { "found" } else { "not found yet" }) {
+                            (if item.identified { identified_mark } else { missing_mark })
+                        }
+                        span { (item.detail) }
+                    }
                 }
-                .identified {
-                    background-color: #1e4620;
-                    text-decoration: line-through;
-                    color: #7fff7f;
-                }
-                .not-identified {
-                    background-color: #222222;
-                    color: #ffffff;
-                }
-                .checkmark {
-                    margin-right: 10px;
-                    font-size: 1.2em;
-                }
-            </style>
-    "#.to_string();
-
-    for item in checklist {
-        let detail = item.get("detail")
-            .and_then(|d| d.as_str())
-            .unwrap_or("Unknown detail");
-        
-        let identified = item.get("identified")
-            .and_then(|i| i.as_bool())
-            .unwrap_or(false);
-        
-        let css_class = if identified { "identified" } else { "not-identified" };
-        let checkmark = if identified { "✅" } else { "❌" };
-        
-        html_content.push_str(&format!(r#"
-            <div class="checklist-item {}">
-                <span class="checkmark">{}</span>
-                <span>{}</span>
-            </div>
-        "#, css_class, checkmark, detail));
-    }
-
-    html_content.push_str(r#"
-        </div>
-    "#);
-    
-    html_content
+            }
+        }
+    };
+    markup.into_string()
+}
+
+fn checklist_css(theme: &Theme) -> String {
+    format!(
+        r#"
+        .checklist-item {{
+            display: flex;
+            align-items: center;
+            margin-bottom: 10px;
+            padding: 8px;
+            border-radius: 5px;
+            {transition}
+        }}
+        @media (prefers-reduced-motion: reduce) {{
+            .checklist-item {{ transition: none; }}
+        }}
+        .identified {{
+            background-color: {identified_background};
+            text-decoration: line-through;
+            color: {identified_text};
+        }}
+        .not-identified {{
+            background-color: {item_background};
+            color: {text};
+        }}
+        .checkmark {{
+            margin-right: 10px;
+            font-size: 1.2em;
+        }}
+        "#,
+        identified_background = theme.identified_background,
+        identified_text = theme.identified_text,
+        item_background = theme.item_background,
+        text = theme.text,
+        transition = theme.transition("background-color"),
+    )
 }
 
 /// Updates the progress HTML based on the checklist and active session
-pub fn update_progress_html(checklist: &[Value], active_session: &Value) -> String {
+pub fn update_progress_html(checklist: &[ChecklistItem], active_session: &Session, theme: &Theme) -> String {
     if checklist.is_empty() {
-        return r#"
-            <div id="progress-container" style="background-color: #000000; color: #ffffff; padding: 15px; border-radius: 8px;">
-                <p>No active session.</p>
-            </div>
-        "#.to_string();
+        return html! {
+            div id="progress-container" style=(theme.panel_style()) {
+                p { "No active session." }
+            }
+        }
+        .into_string();
     }
 
     let total_items = checklist.len();
     let identified_items = checklist.iter()
-        .filter(|item| item.get("identified").and_then(|i| i.as_bool()).unwrap_or(false))
+        .filter(|item| item.identified)
         .count();
     
     let percentage = if total_items > 0 {
@@ -94,41 +100,19 @@ pub fn update_progress_html(checklist: &[Value], active_session: &Value) -> Stri
     } else {
         0.0
     };
-    
-    let progress_bar_width = format!("{}%", percentage);
 
     // Calculate threshold
-    let details_threshold = active_session.get("details_threshold")
-        .and_then(|t| t.as_f64())
-        .unwrap_or(0.7);
-    
+    let details_threshold = active_session.details_threshold as f64;
+
     let threshold_count = (total_items as f64 * details_threshold).ceil() as usize;
     let threshold_percentage = (threshold_count as f64 / total_items as f64) * 100.0;
 
-    let mut html_content = format!(r#"
-        <div id="progress-container" style="background-color: #000000; color: #ffffff; padding: 15px; border-radius: 8px;">
-            <h3>Progress: {identified_items} / {total_items} details</h3>
-            <div style="width: 100%; background-color: #333333; border-radius: 5px; margin-bottom: 10px; position: relative;">
-                <div style="width: {progress_bar_width}; height: 24px; background-color: #4CAF50; border-radius: 5px;"></div>
-                <div style="position: absolute; top: 0; bottom: 0; left: {threshold_percentage}%; width: 2px; background-color: #ff6b6b;"></div>
-                <div style="position: absolute; top: -15px; left: {percentage_offset}%; color: #ff6b6b; font-weight: bold;">⚠️</div>
-            </div>
-            <p style="font-size: 14px; text-align: center; color: #dddddd;">
-                Need to identify at least {threshold_count} details ({threshold_percent}%) to advance
-            </p>
-            <p style="font-size: 16px; font-weight: bold; text-align: center; color: #ffffff;">
-    "#, 
-        identified_items = identified_items,
-        total_items = total_items,
-        progress_bar_width = progress_bar_width,
-        threshold_percentage = threshold_percentage,
-        percentage_offset = threshold_percentage - 5.0,
-        threshold_count = threshold_count,
-        threshold_percent = (details_threshold * 100.0) as i32
-    );
-
     let message = if identified_items >= threshold_count {
-        "🎉 Threshold reached! Ready to advance! 🎉"
+        if theme.emoji {
+            "🎉 Threshold reached! Ready to advance! 🎉"
+        } else {
+            "Threshold reached! Ready to advance!"
+        }
     } else if percentage >= 75.0 {
         "Almost there! Keep going!"
     } else if percentage >= 50.0 {
@@ -139,70 +123,668 @@ pub fn update_progress_html(checklist: &[Value], active_session: &Value) -> Stri
         "Let's find more details!"
     };
 
-    html_content.push_str(message);
-    html_content.push_str(r#"
-            </p>
-        </div>
-    "#);
-    
-    html_content
+    let markup = html! {
+        div id="progress-container" style=(theme.panel_style()) {
+            h3 { "Progress: " (identified_items) " / " (total_items) " details" }
+            div style={ "width: 100%; background-color: " (theme.track) "; border-radius: 5px; margin-bottom: 10px; position: relative;" } {
+                div style={ "width: " (percentage) "%; height: 24px; background-color: " (theme.progress) "; border-radius: 5px;" } {}
+                div style={ "position: absolute; top: 0; bottom: 0; left: " (threshold_percentage) "%; width: 2px; background-color: " (theme.threshold) ";" } {}
+                div style={ "position: absolute; top: -15px; left: " (threshold_percentage - 5.0) "%; color: " (theme.threshold) "; font-weight: bold;" } {
+                    (if theme.emoji { "⚠️" } else { "▼" })
+                }
+            }
+            p style={ "font-size: 14px; text-align: center; color: " (theme.muted_text) ";" } {
+                "Need to identify at least " (threshold_count) " details (" ((details_threshold * 100.0) as i32) "%) to advance"
+            }
+            p style={ "font-size: 16px; font-weight: bold; text-align: center; color: " (theme.text) ";" } {
+                (message)
+            }
+        }
+    };
+    markup.into_string()
 }
 
 /// Updates the attempt counter based on the active session
-pub fn update_attempt_counter(active_session: &Value) -> String {
-    let current_count = active_session.get("attempt_count")
-        .and_then(|c| c.as_i64())
-        .unwrap_or(0);
-    
-    let limit = active_session.get("attempt_limit")
-        .and_then(|l| l.as_i64())
-        .unwrap_or(3);
-    
-    format!(r#"
-        <div id="attempt-counter" style="margin-top: 10px; padding: 10px; background-color: #000000; color: #ffffff; border-radius: 5px; border: 1px solid #444;">
-            <p style="margin: 0; font-weight: bold; text-align: center;">Attempts: {current_count}/{limit}</p>
-        </div>
-    "#)
-}
-
-// Example usage function
-pub fn example_usage() {
-    // Create a sample active session
-    let active_session = json!({
-        "difficulty": "Medium",
-        "details_threshold": 0.7,
-        "attempt_count": 1,
-        "attempt_limit": 3
-    });
-
-    // Create a sample checklist
-    let checklist = vec![
-        json!({
-            "detail": "Red flower in the corner",
-            "identified": true
-        }),
-        json!({
-            "detail": "Blue sky background",
-            "identified": true
-        }),
-        json!({
-            "detail": "Mountain silhouette",
-            "identified": false
-        }),
-        json!({
-            "detail": "Small bird in flight",
-            "identified": false
-        })
+pub fn update_attempt_counter(active_session: &Session, theme: &Theme) -> String {
+    let markup = html! {
+        div id="attempt-counter" style={
+            "margin-top: 10px; padding: 10px; background-color: " (theme.background)
+            "; color: " (theme.text) "; border-radius: 5px; border: 1px solid " (theme.border) "; " (theme.font_style())
+        } {
+            p style="margin: 0; font-weight: bold; text-align: center;" {
+                "Attempts: " (active_session.attempt_count) "/" (active_session.attempt_limit)
+            }
+        }
+    };
+    markup.into_string()
+}
+
+// Progress charts are drawn with plotters, once per output format: SVG for the UI and HTML
+// reports, PNG for the PDF report. Each chart implements `Plot`, which draws onto any plotters
+// backend, so both formats share the same drawing code.
+
+/// Size of a rendered chart, in pixels.
+pub const CHART_SIZE: (u32, u32) = (640, 300);
+
+const CHART_COLOR: RGBColor = RGBColor(51, 102, 204);
+
+/// The colours a chart is drawn in. The default is dark on white, for reports and printing;
+/// charts shown in the UI take theirs from the theme.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ChartColors {
+    pub background: RGBColor,
+    pub text: RGBColor,
+    /// Grid lines.
+    pub grid: RGBColor,
+    /// Lines, points and bars.
+    pub series: RGBColor,
+}
+
+impl Default for ChartColors {
+    fn default() -> Self {
+        ChartColors {
+            background: WHITE,
+            text: BLACK,
+            grid: RGBColor(204, 204, 204),
+            series: CHART_COLOR,
+        }
+    }
+}
+
+impl ChartColors {
+    /// The theme's background, text, border and progress colours. A colour that isn't a hex
+    /// CSS colour keeps its default.
+    pub fn from_theme(theme: &Theme) -> Self {
+        let default = ChartColors::default();
+        ChartColors {
+            background: css_rgb(&theme.background).unwrap_or(default.background),
+            text: css_rgb(&theme.text).unwrap_or(default.text),
+            grid: css_rgb(&theme.border).unwrap_or(default.grid),
+            series: css_rgb(&theme.progress).unwrap_or(default.series),
+        }
+    }
+
+    fn font(&self, size: u32) -> TextStyle<'static> {
+        ("sans-serif", size).into_font().color(&self.text)
+    }
+
+    fn mesh<'m, 'a, 'b, X: Ranged, Y: Ranged, DB: DrawingBackend>(
+        &self,
+        mesh: &'m mut MeshStyle<'a, 'b, X, Y, DB>,
+    ) -> &'m mut MeshStyle<'a, 'b, X, Y, DB> {
+        mesh.axis_style(self.text)
+            .bold_line_style(self.grid)
+            .light_line_style(self.grid.mix(0.4))
+            .label_style(self.font(12))
+            .axis_desc_style(self.font(14))
+    }
+}
+
+// Parses `#rgb` and `#rrggbb`.
+fn css_rgb(colour: &str) -> Option<RGBColor> {
+    let hex = colour.trim().strip_prefix('#')?;
+    let digits: Vec<u8> = hex.chars().map(|c| c.to_digit(16).map(|d| d as u8)).collect::<Option<_>>()?;
+    match digits[..] {
+        [r, g, b] => Some(RGBColor(r * 17, g * 17, b * 17)),
+        [r1, r2, g1, g2, b1, b2] => Some(RGBColor(r1 * 16 + r2, g1 * 16 + g2, b1 * 16 + b2)),
+        _ => None,
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChartFormat {
+    Svg,
+    Png,
+}
+
+/// A chart rendered to SVG markup or PNG bytes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RenderedChart {
+    pub format: ChartFormat,
+    pub bytes: Vec<u8>,
+}
+
+impl RenderedChart {
+    pub fn mime_type(&self) -> &'static str {
+        match self.format {
+            ChartFormat::Svg => "image/svg+xml",
+            ChartFormat::Png => "image/png",
+        }
+    }
+
+    pub fn to_data_url(&self) -> String {
+        format!("data:{};base64,{}", self.mime_type(), general_purpose::STANDARD.encode(&self.bytes))
+    }
+
+    /// Markup for embedding the chart in a page: SVG inline, PNG as an `<img>`.
+    pub fn to_markup(&self, alt: &str) -> Markup {
+        match self.format {
+            // plotters escapes the text it writes into the SVG.
+            ChartFormat::Svg => html! {
+                div class="chart" role="img" aria-label=(alt) {
+                    (PreEscaped(String::from_utf8_lossy(&self.bytes)))
+                }
+            },
+            ChartFormat::Png => html! {
+                img class="chart" src=(self.to_data_url()) alt=(alt);
+            },
+        }
+    }
+}
+
+/// The charts available for a learner's progress.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProgressChart {
+    IdentificationRate,
+    DifficultyTimeline,
+    TimeToFirstDetail,
+    TopicAccuracy,
+}
+
+impl ProgressChart {
+    pub const ALL: [ProgressChart; 4] = [
+        ProgressChart::IdentificationRate,
+        ProgressChart::DifficultyTimeline,
+        ProgressChart::TimeToFirstDetail,
+        ProgressChart::TopicAccuracy,
     ];
 
-    // Use the functions
-    let difficulty_label = update_difficulty_label(&active_session);
-    let checklist_html = update_checklist_html(&checklist);
-    let progress_html = update_progress_html(&checklist, &active_session);
-    let attempt_counter = update_attempt_counter(&active_session);
+    pub fn title(&self) -> &'static str {
+        match self {
+            ProgressChart::IdentificationRate => "Details identified per session",
+            ProgressChart::DifficultyTimeline => "Difficulty over time",
+            ProgressChart::TimeToFirstDetail => "Time to first detail",
+            ProgressChart::TopicAccuracy => "Accuracy by topic",
+        }
+    }
+}
+
+/// The numbers behind the progress charts for one session.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SessionMetrics {
+    pub number: usize,
+    pub difficulty: Difficulty,
+    pub topic: String,
+    pub identified: usize,
+    pub total: usize,
+    pub seconds_to_first_detail: Option<f64>,
+}
+
+impl SessionMetrics {
+    /// Percentage of key details identified.
+    pub fn identification_rate(&self) -> f64 {
+        if self.total == 0 {
+            0.0
+        } else {
+            self.identified as f64 * 100.0 / self.total as f64
+        }
+    }
+}
+
+/// Progress metrics for a run of sessions, in the order they were played.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ProgressMetrics {
+    pub sessions: Vec<SessionMetrics>,
+}
+
+impl ProgressMetrics {
+    /// Sessions that were never shown an image are skipped.
+    pub fn from_sessions<'a, I>(sessions: I) -> Self
+    where
+        I: IntoIterator<Item = &'a Session>,
+    {
+        let sessions = sessions
+            .into_iter()
+            .filter(|session| session.prompt.is_some())
+            .enumerate()
+            .map(|(i, session)| SessionMetrics {
+                number: i + 1,
+                difficulty: session.difficulty,
+                topic: session
+                    .topic_focus
+                    .clone()
+                    .filter(|topic| !topic.trim().is_empty())
+                    .unwrap_or_else(|| "General".to_string()),
+                identified: session.identified_details.len(),
+                total: session.key_details.len(),
+                seconds_to_first_detail: session
+                    .started_at
+                    .zip(session.first_detail_at)
+                    .map(|(start, first)| (first - start).num_milliseconds().max(0) as f64 / 1000.0),
+            })
+            .collect();
+        Self { sessions }
+    }
+
+    pub fn first_detail_times(&self) -> Vec<f64> {
+        self.sessions.iter().filter_map(|s| s.seconds_to_first_detail).collect()
+    }
+
+    /// Percentage of key details identified for each topic, sorted by topic.
+    pub fn topic_accuracy(&self) -> Vec<(String, f64)> {
+        let mut totals: BTreeMap<&str, (usize, usize)> = BTreeMap::new();
+        for session in &self.sessions {
+            let entry = totals.entry(&session.topic).or_default();
+            entry.0 += session.identified;
+            entry.1 += session.total;
+        }
+        totals
+            .into_iter()
+            .map(|(topic, (identified, total))| {
+                let rate = if total == 0 { 0.0 } else { identified as f64 * 100.0 / total as f64 };
+                (topic.to_string(), rate)
+            })
+            .collect()
+    }
+
+    /// Renders `chart` in the default, printable colours.
+    pub fn render(&self, chart: ProgressChart, format: ChartFormat) -> Result<RenderedChart> {
+        self.render_in(chart, format, &ChartColors::default())
+    }
+
+    pub fn render_in(&self, chart: ProgressChart, format: ChartFormat, colors: &ChartColors) -> Result<RenderedChart> {
+        match chart {
+            ProgressChart::TimeToFirstDetail => render_histogram(
+                &self.first_detail_times(),
+                first_detail_bucket(&self.first_detail_times()),
+                chart.title(),
+                "seconds",
+                format,
+                colors,
+            ),
+            _ => render(&MetricsPlot { chart, metrics: self }, format, colors),
+        }
+    }
+}
+
+// Rounds bucket widths up to 5, 10, 15, 30 or 60 seconds so there are at most eight buckets.
+fn first_detail_bucket(times: &[f64]) -> f64 {
+    let longest = times.iter().cloned().fold(0.0, f64::max);
+    [5.0, 10.0, 15.0, 30.0, 60.0]
+        .into_iter()
+        .find(|width| longest / width < 8.0)
+        .unwrap_or((longest / 8.0 / 60.0).ceil() * 60.0)
+}
+
+/// Renders a histogram of `values` grouped into buckets `bucket_width` wide.
+pub fn render_histogram(
+    values: &[f64],
+    bucket_width: f64,
+    caption: &str,
+    unit: &str,
+    format: ChartFormat,
+    colors: &ChartColors,
+) -> Result<RenderedChart> {
+    render(
+        &HistogramPlot {
+            values,
+            bucket_width: bucket_width.max(f64::EPSILON),
+            caption,
+            unit,
+        },
+        format,
+        colors,
+    )
+}
+
+/// HTML for the progress charts, for showing in the UI.
+pub fn display_metrics(sessions: &[Session], theme: &Theme) -> String {
+    let metrics = ProgressMetrics::from_sessions(sessions);
+    let colors = ChartColors::from_theme(theme);
+    let (_, missing_mark) = theme.marks();
+    let markup = html! {
+        div id="metrics-container" style=(theme.panel_style()) {
+            @for chart in ProgressChart::ALL {
+                @match metrics.render_in(chart, ChartFormat::Svg, &colors) {
+                    Ok(rendered) => (rendered.to_markup(chart.title())),
+                    Err(e) => {
+                        p { (missing_mark) " " (e.user_message()) }
+                    }
+                }
+            }
+        }
+    };
+    markup.into_string()
+}
+
+// Something that can draw itself onto any plotters backend.
+trait Plot {
+    fn draw<DB: DrawingBackend>(&self, area: &DrawingArea<DB, Shift>, colors: &ChartColors) -> Result<()>;
+}
+
+fn chart_error<E: std::fmt::Display>(e: E) -> VisoLearnError {
+    VisoLearnError::Report(format!("chart: {}", e))
+}
+
+fn render<P: Plot>(plot: &P, format: ChartFormat, colors: &ChartColors) -> Result<RenderedChart> {
+    let (width, height) = CHART_SIZE;
+    let bytes = match format {
+        ChartFormat::Svg => {
+            let mut svg = String::new();
+            {
+                let root = SVGBackend::with_string(&mut svg, CHART_SIZE).into_drawing_area();
+                root.fill(&colors.background).map_err(chart_error)?;
+                plot.draw(&root, colors)?;
+                root.present().map_err(chart_error)?;
+            }
+            svg.into_bytes()
+        }
+        ChartFormat::Png => {
+            let mut pixels = vec![0u8; (width * height * 3) as usize];
+            {
+                let root = BitMapBackend::with_buffer(&mut pixels, CHART_SIZE).into_drawing_area();
+                root.fill(&colors.background).map_err(chart_error)?;
+                plot.draw(&root, colors)?;
+                root.present().map_err(chart_error)?;
+            }
+            let image = RgbImage::from_raw(width, height, pixels)
+                .ok_or_else(|| VisoLearnError::Report("chart: pixel buffer has the wrong size".to_string()))?;
+            let mut bytes = Vec::new();
+            DynamicImage::ImageRgb8(image).write_to(&mut Cursor::new(&mut bytes), ImageOutputFormat::Png)?;
+            bytes
+        }
+    };
+    Ok(RenderedChart { format, bytes })
+}
+
+// Shown instead of axes when there is nothing to plot.
+fn draw_empty<DB: DrawingBackend>(
+    area: &DrawingArea<DB, Shift>,
+    colors: &ChartColors,
+    caption: &str,
+    message: &str,
+) -> Result<()> {
+    let area = area.titled(caption, colors.font(20)).map_err(chart_error)?;
+    area.draw(&Text::new(message, (20, 20), colors.font(16)))
+        .map_err(chart_error)?;
+    Ok(())
+}
+
+struct MetricsPlot<'a> {
+    chart: ProgressChart,
+    metrics: &'a ProgressMetrics,
+}
+
+impl Plot for MetricsPlot<'_> {
+    fn draw<DB: DrawingBackend>(&self, area: &DrawingArea<DB, Shift>, colors: &ChartColors) -> Result<()> {
+        let sessions = &self.metrics.sessions;
+        if sessions.is_empty() {
+            return draw_empty(area, colors, self.chart.title(), "No sessions yet.");
+        }
+        let last = sessions.len() + 1;
 
-    println!("Difficulty Label: {}", difficulty_label);
-    println!("Checklist HTML length: {} chars", checklist_html.len());
-    println!("Progress HTML length: {} chars", progress_html.len());
-    println!("Attempt Counter HTML length: {} chars", attempt_counter.len());
+        match self.chart {
+            ProgressChart::IdentificationRate => {
+                let mut chart = ChartBuilder::on(area)
+                    .caption(self.chart.title(), colors.font(20))
+                    .margin(10)
+                    .x_label_area_size(35)
+                    .y_label_area_size(45)
+                    .build_cartesian_2d(0..last, 0.0..100.0)
+                    .map_err(chart_error)?;
+                colors
+                    .mesh(&mut chart.configure_mesh())
+                    .x_desc("Session")
+                    .y_desc("% identified")
+                    .draw()
+                    .map_err(chart_error)?;
+                let points: Vec<(usize, f64)> = sessions.iter().map(|s| (s.number, s.identification_rate())).collect();
+                chart
+                    .draw_series(LineSeries::new(points.clone(), colors.series.stroke_width(2)))
+                    .map_err(chart_error)?;
+                chart
+                    .draw_series(points.into_iter().map(|point| Circle::new(point, 4, colors.series.filled())))
+                    .map_err(chart_error)?;
+            }
+            ProgressChart::DifficultyTimeline => {
+                let levels = Difficulty::ALL.len() as f64;
+                let mut chart = ChartBuilder::on(area)
+                    .caption(self.chart.title(), colors.font(20))
+                    .margin(10)
+                    .x_label_area_size(35)
+                    .y_label_area_size(100)
+                    .build_cartesian_2d(0..last, -0.5..levels - 0.5)
+                    .map_err(chart_error)?;
+                colors
+                    .mesh(&mut chart.configure_mesh())
+                    .x_desc("Session")
+                    .y_labels(Difficulty::ALL.len())
+                    .y_label_formatter(&|y| difficulty_label(*y))
+                    .draw()
+                    .map_err(chart_error)?;
+                let points: Vec<(usize, f64)> = sessions
+                    .iter()
+                    .map(|s| (s.number, difficulty_level(s.difficulty) as f64))
+                    .collect();
+                chart
+                    .draw_series(LineSeries::new(points.clone(), colors.series.stroke_width(2)))
+                    .map_err(chart_error)?;
+                chart
+                    .draw_series(points.into_iter().map(|point| Circle::new(point, 4, colors.series.filled())))
+                    .map_err(chart_error)?;
+            }
+            ProgressChart::TopicAccuracy => {
+                let topics = self.metrics.topic_accuracy();
+                let mut chart = ChartBuilder::on(area)
+                    .caption(self.chart.title(), colors.font(20))
+                    .margin(10)
+                    .x_label_area_size(35)
+                    .y_label_area_size(45)
+                    .build_cartesian_2d((0..topics.len() - 1).into_segmented(), 0.0..100.0)
+                    .map_err(chart_error)?;
+                colors
+                    .mesh(&mut chart.configure_mesh())
+                    .disable_x_mesh()
+                    .x_labels(topics.len() + 1)
+                    .x_label_formatter(&|value| match value {
+                        SegmentValue::CenterOf(i) => topics.get(*i).map(|(topic, _)| shorten(topic, 16)).unwrap_or_default(),
+                        _ => String::new(),
+                    })
+                    .y_desc("% identified")
+                    .draw()
+                    .map_err(chart_error)?;
+                chart
+                    .draw_series(
+                        Histogram::vertical(&chart)
+                            .style(colors.series.filled())
+                            .margin(10)
+                            .data(topics.iter().enumerate().map(|(i, (_, rate))| (i, *rate))),
+                    )
+                    .map_err(chart_error)?;
+            }
+            ProgressChart::TimeToFirstDetail => unreachable!("drawn by render_histogram"),
+        }
+        Ok(())
+    }
+}
+
+struct HistogramPlot<'a> {
+    values: &'a [f64],
+    bucket_width: f64,
+    caption: &'a str,
+    unit: &'a str,
+}
+
+impl Plot for HistogramPlot<'_> {
+    fn draw<DB: DrawingBackend>(&self, area: &DrawingArea<DB, Shift>, colors: &ChartColors) -> Result<()> {
+        if self.values.is_empty() {
+            return draw_empty(area, colors, self.caption, "No data yet.");
+        }
+        let bucket = |value: f64| (value.max(0.0) / self.bucket_width) as u32;
+        let buckets = self.values.iter().map(|v| bucket(*v)).max().unwrap_or(0) + 1;
+        let mut counts = vec![0u32; buckets as usize];
+        for value in self.values {
+            counts[bucket(*value) as usize] += 1;
+        }
+        let highest = counts.iter().copied().max().unwrap_or(0);
+
+        let mut chart = ChartBuilder::on(area)
+            .caption(self.caption, colors.font(20))
+            .margin(10)
+            .x_label_area_size(35)
+            .y_label_area_size(45)
+            .build_cartesian_2d((0..buckets - 1).into_segmented(), 0..highest + 1)
+            .map_err(chart_error)?;
+        colors
+            .mesh(&mut chart.configure_mesh())
+            .disable_x_mesh()
+            .x_labels(buckets as usize + 1)
+            .x_label_formatter(&|value| match value {
+                SegmentValue::CenterOf(i) => format!(
+                    "{}-{}",
+                    *i as f64 * self.bucket_width,
+                    (*i + 1) as f64 * self.bucket_width
+                ),
+                _ => String::new(),
+            })
+            .x_desc(self.unit)
+            .y_desc("Sessions")
+            .draw()
+            .map_err(chart_error)?;
+        chart
+            .draw_series(
+                Histogram::vertical(&chart)
+                    .style(colors.series.filled())
+                    .margin(4)
+                    .data(self.values.iter().map(|value| (bucket(*value), 1))),
+            )
+            .map_err(chart_error)?;
+        Ok(())
+    }
+}
+
+fn difficulty_level(difficulty: Difficulty) -> usize {
+    Difficulty::ALL.iter().position(|d| *d == difficulty).unwrap_or(0)
+}
+
+fn difficulty_label(y: f64) -> String {
+    let level = y.round();
+    if (y - level).abs() > 0.01 || level < 0.0 {
+        return String::new();
+    }
+    Difficulty::ALL
+        .get(level as usize)
+        .map(|d| d.as_str().to_string())
+        .unwrap_or_default()
+}
+
+fn shorten(text: &str, max_chars: usize) -> String {
+    if text.chars().count() <= max_chars {
+        text.to_string()
+    } else {
+        text.chars().take(max_chars - 1).collect::<String>() + "…"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, Utc};
+
+    fn played(topic: &str, difficulty: Difficulty, identified: usize, seconds: Option<i64>) -> Session {
+        let started = Utc::now();
+        Session {
+            prompt: Some(format!("A picture about {}", topic)),
+            topic_focus: Some(topic.to_string()),
+            difficulty,
+            key_details: vec!["one".to_string(), "two".to_string(), "three".to_string(), "four".to_string()],
+            identified_details: vec!["one".to_string(), "two".to_string(), "three".to_string(), "four".to_string()]
+                [..identified]
+                .to_vec(),
+            started_at: Some(started),
+            first_detail_at: seconds.map(|s| started + Duration::seconds(s)),
+            ..Session::default()
+        }
+    }
+
+    #[test]
+    fn test_html_views_escape_details_and_use_the_theme() {
+        let checklist = vec![
+            ChecklistItem {
+                detail: "<script>alert(1)</script>".to_string(),
+                identified: false,
+                id: 0,
+                match_score: 0.0,
+            },
+            ChecklistItem {
+                detail: "cats & dogs".to_string(),
+                identified: true,
+                id: 1,
+                match_score: 1.0,
+            },
+        ];
+        let theme = Theme {
+            background: "#102030".to_string(),
+            ..Theme::default()
+        };
+
+        let html = update_checklist_html(&checklist, &theme);
+        assert!(!html.contains("<script>"));
+        assert!(html.contains("&lt;script&gt;alert(1)&lt;/script&gt;"));
+        assert!(html.contains("cats &amp; dogs"));
+        assert!(html.contains("background-color: #102030"));
+        assert!(!html.contains("#000000"));
+
+        let session = Session {
+            details_threshold: 0.5,
+            ..Session::default()
+        };
+        let progress = update_progress_html(&checklist, &session, &theme);
+        assert!(progress.contains("Progress: 1 / 2 details"));
+        assert!(progress.contains("Threshold reached"));
+        assert!(update_attempt_counter(&session, &theme).contains("background-color: #102030"));
+
+        let calm = Theme::muted_pastel();
+        let html = update_checklist_html(&checklist, &calm);
+        assert!(!html.contains("✅") && !html.contains("transition: background-color"));
+        assert!(html.contains(r#"aria-label="found">✓"#));
+        assert!(!update_progress_html(&checklist, &session, &calm).contains("🎉"));
+    }
+
+    #[test]
+    fn test_progress_metrics() {
+        let sessions = vec![
+            played("animals", Difficulty::VerySimple, 1, Some(40)),
+            Session::new(), // Never shown an image.
+            played("animals", Difficulty::Simple, 3, Some(12)),
+            played("food", Difficulty::Simple, 2, None),
+        ];
+        let metrics = ProgressMetrics::from_sessions(&sessions);
+
+        assert_eq!(metrics.sessions.len(), 3);
+        assert_eq!(metrics.sessions[1].number, 2);
+        assert_eq!(metrics.sessions[1].identification_rate(), 75.0);
+        assert_eq!(metrics.first_detail_times(), vec![40.0, 12.0]);
+        assert_eq!(
+            metrics.topic_accuracy(),
+            vec![("animals".to_string(), 50.0), ("food".to_string(), 50.0)]
+        );
+        assert_eq!(first_detail_bucket(&metrics.first_detail_times()), 10.0);
+    }
+
+    #[test]
+    fn test_renders_every_chart_as_svg_and_png() {
+        let sessions = vec![
+            played("animals", Difficulty::VerySimple, 1, Some(40)),
+            played("food", Difficulty::Moderate, 3, Some(12)),
+        ];
+        let metrics = ProgressMetrics::from_sessions(&sessions);
+        for chart in ProgressChart::ALL {
+            let svg = metrics.render(chart, ChartFormat::Svg).unwrap();
+            assert!(String::from_utf8(svg.bytes).unwrap().contains("<svg"), "{:?}", chart);
+            let png = metrics.render(chart, ChartFormat::Png).unwrap();
+            assert_eq!(image::load_from_memory(&png.bytes).unwrap().width(), CHART_SIZE.0);
+        }
+
+        // Charts with nothing to show still render.
+        let empty = ProgressMetrics::default();
+        assert!(empty.render(ProgressChart::DifficultyTimeline, ChartFormat::Svg).is_ok());
+        assert!(render_histogram(&[], 10.0, "Empty", "seconds", ChartFormat::Png, &ChartColors::default()).is_ok());
+        let html = display_metrics(&sessions, &Theme::muted_pastel());
+        assert!(html.contains("Accuracy by topic"));
+        assert!(html.contains("background-color: #f4f1ec") && !html.contains("#ffffff"));
+        assert_eq!(ChartColors::from_theme(&Theme::dark()).background, BLACK);
+        assert_eq!(css_rgb("#fa0"), Some(RGBColor(255, 170, 0)));
+        assert_eq!(css_rgb("navy"), None);
+    }
 }
\ No newline at end of file